@@ -0,0 +1,4 @@
+ALTER TABLE scans
+    DROP COLUMN IF EXISTS error,
+    DROP COLUMN IF EXISTS finished_at,
+    DROP COLUMN IF EXISTS started_at;
@@ -0,0 +1,4 @@
+ALTER TABLE scans
+    ADD COLUMN started_at TIMESTAMP,
+    ADD COLUMN finished_at TIMESTAMP,
+    ADD COLUMN error TEXT;
@@ -0,0 +1,13 @@
+CREATE TABLE host_services (
+    id SERIAL PRIMARY KEY,
+    host_id INTEGER NOT NULL REFERENCES hosts(id) ON DELETE CASCADE,
+    port INTEGER NOT NULL,
+    protocol VARCHAR(10) NOT NULL,
+    state VARCHAR(20) NOT NULL,
+    service_name VARCHAR(100),
+    product VARCHAR(255),
+    version VARCHAR(100),
+    extra_info TEXT,
+    cpe TEXT,
+    UNIQUE (host_id, port, protocol)
+);
@@ -0,0 +1,3 @@
+ALTER TABLE hosts
+    DROP COLUMN IF EXISTS os_accuracy,
+    DROP COLUMN IF EXISTS os;
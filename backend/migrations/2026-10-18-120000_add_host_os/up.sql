@@ -0,0 +1,3 @@
+ALTER TABLE hosts
+    ADD COLUMN os VARCHAR(255),
+    ADD COLUMN os_accuracy INTEGER;
@@ -0,0 +1,5 @@
+ALTER TABLE scans
+    DROP COLUMN options,
+    DROP COLUMN profile_id;
+
+DROP TABLE IF EXISTS scan_profiles;
@@ -0,0 +1,15 @@
+CREATE TABLE scan_profiles (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR(100) NOT NULL UNIQUE,
+    scanner_type VARCHAR(50) NOT NULL,
+    options JSONB NOT NULL DEFAULT '{}'
+);
+
+ALTER TABLE scans
+    ADD COLUMN profile_id INTEGER REFERENCES scan_profiles(id) ON DELETE SET NULL,
+    ADD COLUMN options JSONB NOT NULL DEFAULT '{}';
+
+INSERT INTO scan_profiles (name, scanner_type, options) VALUES
+    ('nmap full TCP + -sV', 'nmap', '{"ports": "1-65535", "service_detection": true, "timing": 4}'),
+    ('nmap top-100 UDP', 'nmap', '{"udp": true, "top_ports": 100}'),
+    ('nuclei critical+high cves', 'nuclei', '{"tags": ["cve"], "severity": ["critical", "high"]}');
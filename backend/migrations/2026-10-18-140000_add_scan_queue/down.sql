@@ -0,0 +1,5 @@
+DROP INDEX IF EXISTS scans_queue_idx;
+
+ALTER TABLE scans
+    DROP COLUMN queued_at,
+    DROP COLUMN priority;
@@ -0,0 +1,5 @@
+ALTER TABLE scans
+    ADD COLUMN priority INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN queued_at TIMESTAMP NOT NULL DEFAULT now();
+
+CREATE INDEX scans_queue_idx ON scans (scanner_type, priority DESC, queued_at) WHERE status = 'queued';
@@ -0,0 +1,10 @@
+CREATE TABLE scope_audit_log (
+    id SERIAL PRIMARY KEY,
+    project_id UUID NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    scanner_type VARCHAR(50) NOT NULL,
+    target TEXT NOT NULL,
+    overridden BOOLEAN NOT NULL DEFAULT FALSE,
+    justification TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT now()
+);
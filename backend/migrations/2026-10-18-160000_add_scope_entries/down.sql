@@ -0,0 +1,13 @@
+ALTER TABLE projects ADD COLUMN scope TEXT;
+
+UPDATE projects
+SET scope = entries.scope
+FROM (
+    SELECT project_id, string_agg(value, E'\n' ORDER BY id) AS scope
+    FROM scope_entries
+    WHERE kind <> 'exclusion'
+    GROUP BY project_id
+) entries
+WHERE projects.id = entries.project_id;
+
+DROP TABLE scope_entries;
@@ -0,0 +1,34 @@
+CREATE TABLE scope_entries (
+    id SERIAL PRIMARY KEY,
+    project_id UUID NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    kind VARCHAR(20) NOT NULL
+        CHECK (kind IN ('cidr', 'ip_range', 'domain', 'url_prefix', 'exclusion')),
+    value TEXT NOT NULL,
+    note TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT now()
+);
+
+CREATE INDEX scope_entries_project_id_idx ON scope_entries (project_id);
+
+-- Split the free-text scope the same way the backend does and guess the kind
+-- of every token.
+INSERT INTO scope_entries (project_id, kind, value)
+SELECT project_id,
+       CASE
+           WHEN token LIKE '%://%' THEN 'url_prefix'
+           WHEN token ~ '^([0-9]{1,3}(\.[0-9]{1,3}){3}|[0-9A-Fa-f]*:[0-9A-Fa-f:.]*)/[0-9]{1,3}$'
+               THEN 'cidr'
+           WHEN token ~ '^[0-9]{1,3}(\.[0-9]{1,3}){3}(-[0-9]{1,3}((\.[0-9]{1,3}){3})?)?$'
+               OR token ~ '^[0-9A-Fa-f]*:[0-9A-Fa-f:.]*(-[0-9A-Fa-f]*:[0-9A-Fa-f:.]*)?$'
+               THEN 'ip_range'
+           ELSE 'domain'
+       END,
+       token
+FROM (
+    SELECT id AS project_id, regexp_split_to_table(scope, '[[:space:],;]+') AS token
+    FROM projects
+    WHERE scope IS NOT NULL
+) tokens
+WHERE token <> '';
+
+ALTER TABLE projects DROP COLUMN scope;
@@ -0,0 +1,2 @@
+ALTER TABLE scans DROP COLUMN schedule_id;
+DROP TABLE scan_schedules;
@@ -0,0 +1,23 @@
+CREATE TABLE scan_schedules (
+    id SERIAL PRIMARY KEY,
+    project_id UUID NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    scanner_type VARCHAR(50) NOT NULL,
+    target VARCHAR(100) NOT NULL,
+    profile_id INTEGER REFERENCES scan_profiles(id) ON DELETE SET NULL,
+    options JSONB NOT NULL DEFAULT '{}',
+    priority INTEGER NOT NULL DEFAULT 0,
+    -- Exactly one of run_at (one-off) and cron (recurring) is set.
+    run_at TIMESTAMP,
+    cron VARCHAR(100),
+    next_run_at TIMESTAMP,
+    last_run_at TIMESTAMP,
+    paused BOOLEAN NOT NULL DEFAULT FALSE,
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT now(),
+    CHECK ((run_at IS NULL) <> (cron IS NULL))
+);
+
+CREATE INDEX scan_schedules_due_idx ON scan_schedules (next_run_at) WHERE NOT paused;
+
+ALTER TABLE scans
+    ADD COLUMN schedule_id INTEGER REFERENCES scan_schedules(id) ON DELETE SET NULL;
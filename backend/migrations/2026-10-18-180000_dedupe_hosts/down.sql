@@ -0,0 +1 @@
+ALTER TABLE hosts DROP CONSTRAINT hosts_project_id_ip_address_key;
@@ -0,0 +1,50 @@
+-- Every host is merged into the oldest record with the same address in the
+-- project before the addresses are made unique.
+CREATE TEMPORARY TABLE duplicate_hosts AS
+SELECT id, keep_id
+FROM (
+    SELECT id, min(id) OVER (PARTITION BY project_id, ip_address) AS keep_id
+    FROM hosts
+) AS grouped
+WHERE id <> keep_id;
+
+UPDATE hosts
+SET hostname = coalesce(latest.hostname, hosts.hostname),
+    os = coalesce(latest.os, hosts.os),
+    os_accuracy = CASE WHEN latest.os IS NULL THEN hosts.os_accuracy ELSE latest.os_accuracy END
+FROM (
+    SELECT DISTINCT ON (d.keep_id) d.keep_id, h.hostname, h.os, h.os_accuracy
+    FROM duplicate_hosts d
+    JOIN hosts h ON h.id = d.id
+    ORDER BY d.keep_id, h.id DESC
+) AS latest
+WHERE hosts.id = latest.keep_id;
+
+INSERT INTO issue_hosts (issue_id, host_id)
+SELECT ih.issue_id, d.keep_id
+FROM issue_hosts ih
+JOIN duplicate_hosts d ON d.id = ih.host_id
+ON CONFLICT DO NOTHING;
+
+DELETE FROM issue_hosts WHERE host_id IN (SELECT id FROM duplicate_hosts);
+
+-- The most recently saved service wins when several duplicates know the same port.
+INSERT INTO host_services (host_id, port, protocol, state, service_name, product, version, extra_info, cpe)
+SELECT DISTINCT ON (d.keep_id, s.port, s.protocol)
+       d.keep_id, s.port, s.protocol, s.state, s.service_name, s.product, s.version, s.extra_info, s.cpe
+FROM host_services s
+JOIN duplicate_hosts d ON d.id = s.host_id
+ORDER BY d.keep_id, s.port, s.protocol, s.id DESC
+ON CONFLICT (host_id, port, protocol) DO UPDATE
+SET state = excluded.state,
+    service_name = excluded.service_name,
+    product = excluded.product,
+    version = excluded.version,
+    extra_info = excluded.extra_info,
+    cpe = excluded.cpe;
+
+DELETE FROM hosts WHERE id IN (SELECT id FROM duplicate_hosts);
+
+DROP TABLE duplicate_hosts;
+
+ALTER TABLE hosts ADD CONSTRAINT hosts_project_id_ip_address_key UNIQUE (project_id, ip_address);
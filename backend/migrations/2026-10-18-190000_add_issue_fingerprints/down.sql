@@ -0,0 +1,6 @@
+DROP TABLE issue_endpoints;
+
+ALTER TABLE issues
+    DROP CONSTRAINT issues_project_id_fingerprint_key,
+    DROP COLUMN manually_edited,
+    DROP COLUMN fingerprint;
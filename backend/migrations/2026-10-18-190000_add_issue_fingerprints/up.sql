@@ -0,0 +1,17 @@
+-- Scanner findings are merged into one issue per fingerprint, issues created
+-- by hand have none.
+ALTER TABLE issues
+    ADD COLUMN fingerprint VARCHAR(255),
+    ADD COLUMN manually_edited BOOLEAN NOT NULL DEFAULT false;
+
+ALTER TABLE issues ADD CONSTRAINT issues_project_id_fingerprint_key UNIQUE (project_id, fingerprint);
+
+CREATE TABLE issue_endpoints (
+    id SERIAL PRIMARY KEY,
+    issue_id UUID NOT NULL REFERENCES issues(id) ON DELETE CASCADE,
+    host_id INTEGER REFERENCES hosts(id) ON DELETE SET NULL,
+    matched_at TEXT NOT NULL,
+    first_seen_at TIMESTAMP NOT NULL DEFAULT now(),
+    last_seen_at TIMESTAMP NOT NULL DEFAULT now(),
+    UNIQUE (issue_id, matched_at)
+);
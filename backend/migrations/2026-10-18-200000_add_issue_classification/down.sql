@@ -0,0 +1,6 @@
+ALTER TABLE issues
+    DROP COLUMN cvss_vector,
+    DROP COLUMN tags,
+    DROP COLUMN reference_urls,
+    DROP COLUMN cwe_ids,
+    DROP COLUMN cve_ids;
@@ -0,0 +1,6 @@
+ALTER TABLE issues
+    ADD COLUMN cve_ids TEXT[] NOT NULL DEFAULT '{}',
+    ADD COLUMN cwe_ids TEXT[] NOT NULL DEFAULT '{}',
+    ADD COLUMN reference_urls TEXT[] NOT NULL DEFAULT '{}',
+    ADD COLUMN tags TEXT[] NOT NULL DEFAULT '{}',
+    ADD COLUMN cvss_vector VARCHAR(255);
@@ -0,0 +1,2 @@
+-- Where a host was found, e.g. "subfinder:crtsh".
+ALTER TABLE hosts ADD COLUMN sources TEXT[] NOT NULL DEFAULT '{}';
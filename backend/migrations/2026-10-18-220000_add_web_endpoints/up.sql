@@ -0,0 +1,19 @@
+-- Paths found by content discovery, kept apart from issues until someone
+-- promotes an interesting one.
+CREATE TABLE web_endpoints (
+    id SERIAL PRIMARY KEY,
+    project_id UUID NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    host_id INTEGER REFERENCES hosts(id) ON DELETE SET NULL,
+    url TEXT NOT NULL,
+    status_code INTEGER NOT NULL,
+    content_length BIGINT NOT NULL,
+    content_type VARCHAR(255),
+    redirect_location TEXT,
+    category VARCHAR(50),
+    issue_id UUID REFERENCES issues(id) ON DELETE SET NULL,
+    first_seen_at TIMESTAMP NOT NULL DEFAULT now(),
+    last_seen_at TIMESTAMP NOT NULL DEFAULT now(),
+    UNIQUE (project_id, url)
+);
+
+CREATE INDEX web_endpoints_host_id_idx ON web_endpoints (host_id);
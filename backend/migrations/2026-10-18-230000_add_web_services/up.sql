@@ -0,0 +1,21 @@
+-- Web servers found by probing open ports, with what they told about
+-- themselves.
+CREATE TABLE web_services (
+    id SERIAL PRIMARY KEY,
+    project_id UUID NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    host_id INTEGER REFERENCES hosts(id) ON DELETE SET NULL,
+    url TEXT NOT NULL,
+    status_code INTEGER NOT NULL,
+    title TEXT,
+    server VARCHAR(255),
+    content_type VARCHAR(255),
+    redirect_location TEXT,
+    tls_subject TEXT,
+    tls_sans TEXT[] NOT NULL DEFAULT '{}',
+    technologies TEXT[] NOT NULL DEFAULT '{}',
+    first_seen_at TIMESTAMP NOT NULL DEFAULT now(),
+    last_seen_at TIMESTAMP NOT NULL DEFAULT now(),
+    UNIQUE (project_id, url)
+);
+
+CREATE INDEX web_services_host_id_idx ON web_services (host_id);
@@ -0,0 +1,12 @@
+-- Files a scan left in its directory: the raw output, the stderr log and the
+-- command line it ran.
+CREATE TABLE scan_artifacts (
+    id SERIAL PRIMARY KEY,
+    scan_id UUID NOT NULL REFERENCES scans(id) ON DELETE CASCADE,
+    kind VARCHAR(20) NOT NULL,
+    file_name TEXT NOT NULL,
+    path TEXT NOT NULL,
+    size BIGINT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT now(),
+    UNIQUE (scan_id, file_name)
+);
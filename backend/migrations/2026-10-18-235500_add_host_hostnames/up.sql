@@ -0,0 +1,4 @@
+-- Every name a host was found under, e.g. subdomains sharing a CDN address.
+ALTER TABLE hosts ADD COLUMN hostnames TEXT[] NOT NULL DEFAULT '{}';
+
+UPDATE hosts SET hostnames = ARRAY[hostname] WHERE hostname IS NOT NULL;
@@ -1,5 +1,25 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    host_services (id) {
+        id -> Int4,
+        host_id -> Int4,
+        port -> Int4,
+        #[max_length = 10]
+        protocol -> Varchar,
+        #[max_length = 20]
+        state -> Varchar,
+        #[max_length = 100]
+        service_name -> Nullable<Varchar>,
+        #[max_length = 255]
+        product -> Nullable<Varchar>,
+        #[max_length = 100]
+        version -> Nullable<Varchar>,
+        extra_info -> Nullable<Text>,
+        cpe -> Nullable<Text>,
+    }
+}
+
 diesel::table! {
     hosts (id) {
         id -> Int4,
@@ -7,6 +27,22 @@ diesel::table! {
         hostname -> Nullable<Varchar>,
         ip_address -> Varchar,
         project_id -> Uuid,
+        #[max_length = 255]
+        os -> Nullable<Varchar>,
+        os_accuracy -> Nullable<Int4>,
+        sources -> Array<Text>,
+        hostnames -> Array<Text>,
+    }
+}
+
+diesel::table! {
+    issue_endpoints (id) {
+        id -> Int4,
+        issue_id -> Uuid,
+        host_id -> Nullable<Int4>,
+        matched_at -> Text,
+        first_seen_at -> Timestamp,
+        last_seen_at -> Timestamp,
     }
 }
 
@@ -26,6 +62,15 @@ diesel::table! {
         mitigation -> Nullable<Text>,
         cvss -> Float8,
         project_id -> Uuid,
+        #[max_length = 255]
+        fingerprint -> Nullable<Varchar>,
+        manually_edited -> Bool,
+        cve_ids -> Array<Text>,
+        cwe_ids -> Array<Text>,
+        reference_urls -> Array<Text>,
+        tags -> Array<Text>,
+        #[max_length = 255]
+        cvss_vector -> Nullable<Varchar>,
     }
 }
 
@@ -35,7 +80,6 @@ diesel::table! {
         #[max_length = 100]
         name -> Varchar,
         description -> Nullable<Text>,
-        scope -> Nullable<Text>,
         start_date -> Date,
         end_date -> Date,
         folder -> Text,
@@ -80,6 +124,52 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    scan_artifacts (id) {
+        id -> Int4,
+        scan_id -> Uuid,
+        #[max_length = 20]
+        kind -> Varchar,
+        file_name -> Text,
+        path -> Text,
+        size -> Int8,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    scan_profiles (id) {
+        id -> Int4,
+        #[max_length = 100]
+        name -> Varchar,
+        #[max_length = 50]
+        scanner_type -> Varchar,
+        options -> Jsonb,
+    }
+}
+
+diesel::table! {
+    scan_schedules (id) {
+        id -> Int4,
+        project_id -> Uuid,
+        #[max_length = 50]
+        scanner_type -> Varchar,
+        #[max_length = 100]
+        target -> Varchar,
+        profile_id -> Nullable<Int4>,
+        options -> Jsonb,
+        priority -> Int4,
+        run_at -> Nullable<Timestamp>,
+        #[max_length = 100]
+        cron -> Nullable<Varchar>,
+        next_run_at -> Nullable<Timestamp>,
+        last_run_at -> Nullable<Timestamp>,
+        paused -> Bool,
+        created_by -> Nullable<Uuid>,
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     scans (id) {
         id -> Uuid,
@@ -91,6 +181,40 @@ diesel::table! {
         result_path -> Nullable<Text>,
         #[max_length = 100]
         target -> Varchar,
+        started_at -> Nullable<Timestamp>,
+        finished_at -> Nullable<Timestamp>,
+        error -> Nullable<Text>,
+        profile_id -> Nullable<Int4>,
+        options -> Jsonb,
+        priority -> Int4,
+        queued_at -> Timestamp,
+        schedule_id -> Nullable<Int4>,
+    }
+}
+
+diesel::table! {
+    scope_audit_log (id) {
+        id -> Int4,
+        project_id -> Uuid,
+        user_id -> Nullable<Uuid>,
+        #[max_length = 50]
+        scanner_type -> Varchar,
+        target -> Text,
+        overridden -> Bool,
+        justification -> Nullable<Text>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    scope_entries (id) {
+        id -> Int4,
+        project_id -> Uuid,
+        #[max_length = 20]
+        kind -> Varchar,
+        value -> Text,
+        note -> Nullable<Text>,
+        created_at -> Timestamp,
     }
 }
 
@@ -137,7 +261,50 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    web_endpoints (id) {
+        id -> Int4,
+        project_id -> Uuid,
+        host_id -> Nullable<Int4>,
+        url -> Text,
+        status_code -> Int4,
+        content_length -> Int8,
+        #[max_length = 255]
+        content_type -> Nullable<Varchar>,
+        redirect_location -> Nullable<Text>,
+        #[max_length = 50]
+        category -> Nullable<Varchar>,
+        issue_id -> Nullable<Uuid>,
+        first_seen_at -> Timestamp,
+        last_seen_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    web_services (id) {
+        id -> Int4,
+        project_id -> Uuid,
+        host_id -> Nullable<Int4>,
+        url -> Text,
+        status_code -> Int4,
+        title -> Nullable<Text>,
+        #[max_length = 255]
+        server -> Nullable<Varchar>,
+        #[max_length = 255]
+        content_type -> Nullable<Varchar>,
+        redirect_location -> Nullable<Text>,
+        tls_subject -> Nullable<Text>,
+        tls_sans -> Array<Text>,
+        technologies -> Array<Text>,
+        first_seen_at -> Timestamp,
+        last_seen_at -> Timestamp,
+    }
+}
+
+diesel::joinable!(host_services -> hosts (host_id));
 diesel::joinable!(hosts -> projects (project_id));
+diesel::joinable!(issue_endpoints -> hosts (host_id));
+diesel::joinable!(issue_endpoints -> issues (issue_id));
 diesel::joinable!(issue_hosts -> hosts (host_id));
 diesel::joinable!(issue_hosts -> issues (issue_id));
 diesel::joinable!(issues -> projects (project_id));
@@ -145,24 +312,47 @@ diesel::joinable!(projects -> teams (team_id));
 diesel::joinable!(proof_of_concepts -> issues (issue_id));
 diesel::joinable!(reports -> projects (project_id));
 diesel::joinable!(reports -> report_templates (template_id));
+diesel::joinable!(scan_artifacts -> scans (scan_id));
+diesel::joinable!(scan_schedules -> projects (project_id));
+diesel::joinable!(scan_schedules -> scan_profiles (profile_id));
+diesel::joinable!(scan_schedules -> users (created_by));
 diesel::joinable!(scans -> projects (project_id));
+diesel::joinable!(scans -> scan_profiles (profile_id));
+diesel::joinable!(scans -> scan_schedules (schedule_id));
+diesel::joinable!(scope_audit_log -> projects (project_id));
+diesel::joinable!(scope_audit_log -> users (user_id));
+diesel::joinable!(scope_entries -> projects (project_id));
 diesel::joinable!(teams -> users (admin_id));
 diesel::joinable!(users_projects -> projects (project_id));
 diesel::joinable!(users_projects -> users (user_id));
 diesel::joinable!(users_teams -> teams (team_id));
 diesel::joinable!(users_teams -> users (user_id));
+diesel::joinable!(web_endpoints -> hosts (host_id));
+diesel::joinable!(web_endpoints -> issues (issue_id));
+diesel::joinable!(web_endpoints -> projects (project_id));
+diesel::joinable!(web_services -> hosts (host_id));
+diesel::joinable!(web_services -> projects (project_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    host_services,
     hosts,
+    issue_endpoints,
     issue_hosts,
     issues,
     projects,
     proof_of_concepts,
     report_templates,
     reports,
+    scan_artifacts,
+    scan_profiles,
+    scan_schedules,
     scans,
+    scope_audit_log,
+    scope_entries,
     teams,
     users,
     users_projects,
     users_teams,
+    web_endpoints,
+    web_services,
 );
@@ -1,11 +1,13 @@
 use actix_multipart::form::tempfile::TempFile;
 use actix_multipart::form::text::Text;
 use actix_multipart::form::MultipartForm;
-use chrono::NaiveDate;
+use chrono::{NaiveDate, NaiveDateTime};
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use uuid::Uuid;
 
 use crate::models::user::User;
+use crate::services::scope::ScopeKind;
 
 #[derive(Serialize)]
 pub struct UserData {
@@ -23,6 +25,37 @@ pub struct IssueForm {
     pub mitigation: Option<String>,
     pub cvss: Option<f64>,
     pub hosts: Vec<HostForm>,
+    /// Scanner rule that produced the finding, e.g. a nuclei template id.
+    #[serde(default)]
+    pub template_id: Option<String>,
+    /// Exact location of the finding, e.g. the URL nuclei matched at.
+    #[serde(default)]
+    pub matched_at: Option<String>,
+    /// Findings with the same fingerprint are merged into one issue.
+    #[serde(default)]
+    pub fingerprint: Option<String>,
+    /// Evidence reported by the scanner, saved as proofs of concept.
+    #[serde(default)]
+    pub evidence: Vec<EvidenceForm>,
+    #[serde(default)]
+    pub classification: ClassificationForm,
+}
+
+/// Classification metadata of a finding as reported by the scanner.
+#[derive(Debug, Default, Deserialize, Serialize, Clone)]
+pub struct ClassificationForm {
+    pub cve_ids: Vec<String>,
+    pub cwe_ids: Vec<String>,
+    pub reference_urls: Vec<String>,
+    pub tags: Vec<String>,
+    pub cvss_vector: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct EvidenceForm {
+    pub description: String,
+    pub content: String,
+    pub host: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -42,6 +75,7 @@ pub struct ProofOfConceptForm {
 pub struct ProjectForm {
     pub name: String,
     pub description: Option<String>,
+    /// Free-text scope that seeds the scope entries of a new project.
     pub scope: Option<String>,
     pub start_date: Option<NaiveDate>,
     pub end_date: Option<NaiveDate>,
@@ -53,6 +87,66 @@ pub struct ProjectForm {
 pub struct HostForm {
     pub hostname: Option<String>,
     pub ip_address: String,
+    pub os: Option<String>,
+    pub os_accuracy: Option<i32>,
+    #[serde(default)]
+    pub services: Vec<ServiceForm>,
+    /// Where the host was found, e.g. "subfinder:crtsh".
+    #[serde(default)]
+    pub sources: Vec<String>,
+    /// Further names of the address, `hostname` is recorded as well.
+    #[serde(default)]
+    pub hostnames: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ServiceForm {
+    pub port: i32,
+    pub protocol: String,
+    pub state: String,
+    pub service_name: Option<String>,
+    pub product: Option<String>,
+    pub version: Option<String>,
+    pub extra_info: Option<String>,
+    pub cpe: Option<String>,
+}
+
+/// A path content discovery found on a web server.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct WebEndpointForm {
+    pub url: String,
+    pub status_code: i32,
+    pub content_length: i64,
+    pub content_type: Option<String>,
+    pub redirect_location: Option<String>,
+    /// Host part of the URL, an IP address or a hostname.
+    pub host: String,
+}
+
+/// A web server found by probing a port.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct WebServiceForm {
+    pub url: String,
+    /// Host part of the URL, an IP address or a hostname.
+    pub host: String,
+    /// Address the probe connected to.
+    pub ip_address: String,
+    pub status_code: i32,
+    pub title: Option<String>,
+    pub server: Option<String>,
+    pub content_type: Option<String>,
+    pub redirect_location: Option<String>,
+    pub tls_subject: Option<String>,
+    #[serde(default)]
+    pub tls_sans: Vec<String>,
+    #[serde(default)]
+    pub technologies: Vec<String>,
+    /// Security headers the response lacks, in lower case.
+    #[serde(default)]
+    pub missing_headers: Vec<String>,
+    /// Status line and headers of the response, kept as evidence.
+    #[serde(default)]
+    pub response_head: String,
 }
 
 #[derive(Debug, MultipartForm)]
@@ -62,6 +156,50 @@ pub struct UploadReportTemplateForm {
     pub name: Text<String>,
 }
 
+#[derive(Debug, MultipartForm)]
+pub struct UploadScanResultForm {
+    #[multipart(limit = "50MB")]
+    pub file: Option<TempFile>,
+}
+
+#[derive(Debug, MultipartForm)]
+pub struct UploadWordlistForm {
+    #[multipart(limit = "50MB")]
+    pub file: Option<TempFile>,
+    /// File name of the wordlist, the name of the upload if not set.
+    pub name: Option<Text<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ScanProfileForm {
+    pub name: String,
+    pub scanner_type: String,
+    #[serde(default)]
+    pub options: Map<String, Value>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ScanScheduleForm {
+    pub r#type: String,
+    pub target: String,
+    pub profile_id: Option<i32>,
+    #[serde(default)]
+    pub options: Map<String, Value>,
+    #[serde(default)]
+    pub priority: i32,
+    /// Time of a one-off scan, mutually exclusive with `cron`.
+    pub run_at: Option<NaiveDateTime>,
+    /// Cron expression of a recurring scan.
+    pub cron: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ScopeEntryForm {
+    pub kind: ScopeKind,
+    pub value: String,
+    pub note: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct ReportTemplateForm {
     pub file: Vec<u8>,
@@ -72,6 +210,15 @@ pub struct ReportTemplateForm {
 #[derive(Deserialize)]
 pub struct ReportForm {
     pub template_id: i32,
+    /// Adds the changes between two scans as a section of the report.
+    #[serde(default)]
+    pub scan_diff: Option<ScanDiffForm>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ScanDiffForm {
+    pub from: Uuid,
+    pub to: Uuid,
 }
 
 impl UserData {
@@ -0,0 +1,238 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use actix_multipart::form::MultipartForm;
+use actix_web::{post, web, HttpResponse};
+use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::PgConnection;
+use log::error;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::dtos::handlers::UploadScanResultForm;
+use crate::models::scan::{NewScan, Scan};
+use crate::models::scan_artifact::ScanArtifact;
+use crate::services::scanner::nmap::NmapService;
+use crate::services::scanner::nuclei::NucleiService;
+use crate::services::scanner::types::{
+    AnyScanResult, Error, HostsScanResult, IssuesScanResult, ScanOptions, ScanStatus,
+};
+use crate::utils::config::CONFIG;
+use crate::utils::errors::AppError;
+
+#[derive(Debug, Serialize)]
+pub struct ImportResponse {
+    pub scan_id: Uuid,
+    pub status: String,
+    pub hosts: usize,
+    pub issues: usize,
+    /// Hosts added to the project by the import.
+    pub hosts_created: usize,
+    /// Known hosts the import merged new data into.
+    pub hosts_updated: usize,
+}
+
+/// Copies the uploaded file into the scan directory so the raw output is kept
+/// next to the results of regular scans, and listed as their artifact.
+fn store_upload(
+    conn: &mut PgConnection,
+    upload: &Path,
+    scan_id: Uuid,
+    file_name: &str,
+) -> Result<String, Error> {
+    let scan_path = Path::new(&CONFIG.scans_path).join(scan_id.to_string());
+    fs::create_dir_all(&scan_path)
+        .map_err(|e| Error::IoError(format!("creating scan directory: {}", e)))?;
+    let output_file = scan_path.join(file_name);
+    fs::copy(upload, &output_file)
+        .map_err(|e| Error::IoError(format!("storing uploaded file: {}", e)))?;
+    ScanArtifact::record(conn, scan_id, &scan_path)
+        .map_err(|e| Error::SaveFailed(format!("recording artifacts: {}", e)))?;
+    Ok(output_file.to_string_lossy().to_string())
+}
+
+#[post("/{project_id}/import/nmap")]
+pub async fn import_nmap_handler(
+    pool: web::Data<Pool<ConnectionManager<PgConnection>>>,
+    path: web::Path<String>,
+    MultipartForm(form): MultipartForm<UploadScanResultForm>,
+) -> Result<HttpResponse, AppError> {
+    let project_id = Uuid::parse_str(&path.into_inner()).map_err(|_| AppError::BadRequest)?;
+    let file = form.file.ok_or(AppError::BadRequest)?;
+    let original_name = file.file_name.clone().unwrap_or_else(|| "nmap".to_string());
+
+    let response = web::block(move || {
+        let mut conn = pool.get().map_err(|e| {
+            error!("Failed to get database connection: {}", e);
+            AppError::InternalServerError
+        })?;
+
+        let scan_record = Scan::create_scan(&mut conn, NewScan {
+            project_id,
+            scanner_type: "nmap-import".to_string(),
+            status: ScanStatus::Queued.into(),
+            target: original_name.chars().take(100).collect(),
+            result_path: None,
+            profile_id: None,
+            options: ScanOptions::default().into_value(),
+            priority: 0,
+            schedule_id: None,
+        })?;
+        Scan::mark_running(&mut conn, scan_record.id)?;
+
+        let content = match fs::read_to_string(file.file.path()) {
+            Ok(content) => content,
+            Err(e) => {
+                let reason = format!("Failed to read uploaded file: {}", e);
+                error!("{}", reason);
+                Scan::mark_failed(&mut conn, scan_record.id, reason)?;
+                return Err(AppError::BadRequest);
+            },
+        };
+        let file_name = if content.trim_start().starts_with('<') { "scan.xml" } else { "scan.gnmap" };
+        let stored = store_upload(&mut conn, file.file.path(), scan_record.id, file_name);
+        let output_file = match stored {
+            Ok(output_file) => output_file,
+            Err(e) => {
+                error!("Failed to store uploaded scan result: {}", e);
+                Scan::mark_failed(&mut conn, scan_record.id, e.to_string())?;
+                return Err(AppError::InternalServerError);
+            },
+        };
+
+        let hosts = match NmapService::parse_output(&content) {
+            Ok(hosts) => hosts,
+            Err(e) => {
+                error!("Failed to parse imported nmap output: {}", e);
+                Scan::mark_failed(&mut conn, scan_record.id, e.to_string())?;
+                return Err(AppError::BadRequest);
+            },
+        };
+        let hosts_count = hosts.len();
+
+        let scan_result = AnyScanResult::Hosts(HostsScanResult {
+            output_file: output_file.clone(),
+            hosts,
+        });
+        let saved = match scan_result.save_data(project_id, &mut conn) {
+            Ok(saved) => saved,
+            Err(e) => {
+                Scan::mark_failed(&mut conn, scan_record.id, e.to_string())?;
+                return Err(AppError::DatabaseError);
+            },
+        };
+        let scan_record = Scan::mark_completed(&mut conn, scan_record.id, output_file)?
+            .ok_or(AppError::BadRequest)?;
+
+        Ok::<ImportResponse, AppError>(ImportResponse {
+            scan_id: scan_record.id,
+            status: scan_record.status,
+            hosts: hosts_count,
+            issues: 0,
+            hosts_created: saved.created,
+            hosts_updated: saved.updated,
+        })
+    })
+    .await??;
+
+    Ok(HttpResponse::Created().json(response))
+}
+
+#[post("/{project_id}/import/nuclei")]
+pub async fn import_nuclei_handler(
+    pool: web::Data<Pool<ConnectionManager<PgConnection>>>,
+    path: web::Path<String>,
+    MultipartForm(form): MultipartForm<UploadScanResultForm>,
+) -> Result<HttpResponse, AppError> {
+    let project_id = Uuid::parse_str(&path.into_inner()).map_err(|_| AppError::BadRequest)?;
+    let file = form.file.ok_or(AppError::BadRequest)?;
+    let original_name = file.file_name.clone().unwrap_or_else(|| "nuclei".to_string());
+
+    let response = web::block(move || {
+        let mut conn = pool.get().map_err(|e| {
+            error!("Failed to get database connection: {}", e);
+            AppError::InternalServerError
+        })?;
+
+        let scan_record = Scan::create_scan(&mut conn, NewScan {
+            project_id,
+            scanner_type: "nuclei-import".to_string(),
+            status: ScanStatus::Queued.into(),
+            target: original_name.chars().take(100).collect(),
+            result_path: None,
+            profile_id: None,
+            options: ScanOptions::default().into_value(),
+            priority: 0,
+            schedule_id: None,
+        })?;
+        Scan::mark_running(&mut conn, scan_record.id)?;
+
+        let mut reader = match fs::File::open(file.file.path()) {
+            Ok(upload) => BufReader::new(upload),
+            Err(e) => {
+                let reason = format!("Failed to read uploaded file: {}", e);
+                error!("{}", reason);
+                Scan::mark_failed(&mut conn, scan_record.id, reason)?;
+                return Err(AppError::BadRequest);
+            },
+        };
+        let is_array = reader
+            .fill_buf()
+            .map(|buf| buf.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'['))
+            .unwrap_or(false);
+        let file_name = if is_array { "scan.json" } else { "scan.jsonl" };
+        let stored = store_upload(&mut conn, file.file.path(), scan_record.id, file_name);
+        let output_file = match stored {
+            Ok(output_file) => output_file,
+            Err(e) => {
+                error!("Failed to store uploaded scan result: {}", e);
+                Scan::mark_failed(&mut conn, scan_record.id, e.to_string())?;
+                return Err(AppError::InternalServerError);
+            },
+        };
+
+        let findings = match NucleiService::parse_nuclei_output(reader) {
+            Ok(findings) => NucleiService::parse_to_issues(findings),
+            Err(e) => {
+                error!("Failed to parse imported nuclei output: {}", e);
+                Scan::mark_failed(&mut conn, scan_record.id, e.to_string())?;
+                return Err(AppError::BadRequest);
+            },
+        };
+        let issues_count = findings.len();
+        let hosts_count = findings
+            .iter()
+            .flat_map(|finding| &finding.hosts)
+            .map(|host| host.ip_address.as_str())
+            .collect::<HashSet<_>>()
+            .len();
+
+        let scan_result = AnyScanResult::Issues(IssuesScanResult {
+            output_file: output_file.clone(),
+            findings,
+        });
+        let saved = match scan_result.save_data(project_id, &mut conn) {
+            Ok(saved) => saved,
+            Err(e) => {
+                Scan::mark_failed(&mut conn, scan_record.id, e.to_string())?;
+                return Err(AppError::DatabaseError);
+            },
+        };
+        let scan_record = Scan::mark_completed(&mut conn, scan_record.id, output_file)?
+            .ok_or(AppError::BadRequest)?;
+
+        Ok::<ImportResponse, AppError>(ImportResponse {
+            scan_id: scan_record.id,
+            status: scan_record.status,
+            hosts: hosts_count,
+            issues: issues_count,
+            hosts_created: saved.created,
+            hosts_updated: saved.updated,
+        })
+    })
+    .await??;
+
+    Ok(HttpResponse::Created().json(response))
+}
@@ -1,5 +1,7 @@
 pub mod auth_handlers;
+pub mod import_handlers;
 pub mod project_handlers;
+pub mod scanner_handlers;
 pub mod team_handlers;
 pub mod template_handlers;
 pub mod user_handlers;
@@ -1,20 +1,30 @@
 use std::io::Read;
+use std::path::Path;
 use std::sync::Arc;
 
 use actix_multipart::form::tempfile::TempFile;
+use actix_session::Session;
 use actix_multipart::form::text::Text;
 use actix_multipart::form::MultipartForm;
+use actix_web::web::Bytes;
 use actix_web::{delete, get, post, put, web, HttpResponse};
+use chrono::Utc;
 use diesel::r2d2::{ConnectionManager, Pool};
 use diesel::PgConnection;
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
 
+use futures::stream::{self, StreamExt};
 use log::{debug, error};
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tokio::sync::broadcast::error::RecvError;
 use uuid::Uuid;
 
 use crate::dtos::handlers::{
     CreateIssueForm, HostForm, IssueForm, ProjectForm, ProofOfConceptForm, ReportForm,
+    ScanDiffForm, ScanScheduleForm, ScopeEntryForm,
 };
+use crate::middleware::auth::UserSession;
 use crate::models::host::Host;
 use crate::models::issue::Issue;
 use crate::models::project::Project;
@@ -22,9 +32,22 @@ use crate::models::proof_of_concept::ProofOfConcept;
 use crate::models::report::Report;
 use crate::models::report_template::ReportTemplate;
 use crate::models::scan::{NewScan, Scan};
+use crate::models::scan_artifact::ScanArtifact;
+use crate::models::scan_profile::ScanProfile;
+use crate::models::scan_schedule::{NewScanSchedule, ScanSchedule};
+use crate::models::scope_audit::ScopeAuditRecord;
+use crate::models::scope_entry::{NewScopeEntry, ScopeEntry};
+use crate::models::web_endpoint::WebEndpoint;
+use crate::models::web_service::WebService;
 use crate::services;
 use crate::services::report::{MarkdownService, ReportGenerator};
-use crate::services::scanner::{Scanner, ScannerService, VulnerabilityScanner};
+use crate::services::scanner::diff::{ScanChanges, ScanDiff};
+use crate::services::scanner::events::ScanEvent;
+use crate::services::scanner::queue::ScanQueue;
+use crate::services::scanner::scheduler::{next_run, parse_cron};
+use crate::services::scanner::types::{ScanOptions, ScanStatus};
+use crate::services::scanner::{parse_targets, ScannerService, VulnerabilityScanner};
+use crate::services::scope::{enforce_scope, ScopeRule, ScopeTarget};
 use crate::utils::errors::AppError;
 
 #[get("/")]
@@ -260,14 +283,19 @@ pub async fn get_host_handler(
             error!("Failed to get database connection: {}", e);
             AppError::InternalServerError
         })?;
-        Host::get_host(&mut conn, host_id).map_err(|e| {
-            error!("Failed to get hosts by project id: {}", e);
-            AppError::DatabaseError
-        })
+        Host::get_host(&mut conn, host_id)
+            .and_then(|host| host.map(|host| host.to_full_response(&mut conn)).transpose())
+            .map_err(|e| {
+                error!("Failed to get hosts by project id: {}", e);
+                AppError::DatabaseError
+            })
     })
     .await??;
 
-    Ok(HttpResponse::Ok().json(host))
+    match host {
+        Some(host) => Ok(HttpResponse::Ok().json(host)),
+        None => Err(AppError::NotFound),
+    }
 }
 
 #[post("/{project_id}/host")]
@@ -283,9 +311,13 @@ pub async fn create_host_handler(
             error!("Failed to get database connection: {}", e);
             AppError::InternalServerError
         })?;
-        Host::create_host(&mut conn, &data.into_inner(), project_id).map_err(|e| {
-            error!("Failed to create host: {}", e);
-            AppError::DatabaseError
+        Host::create_host(&mut conn, &data.into_inner(), project_id).map_err(|e| match e {
+            // The project already has a host with this IP address.
+            DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => AppError::BadRequest,
+            e => {
+                error!("Failed to create host: {}", e);
+                AppError::DatabaseError
+            },
         })
     })
     .await??;
@@ -305,9 +337,13 @@ pub async fn update_host_handler(
             error!("Failed to get database connection: {}", e);
             AppError::InternalServerError
         })?;
-        Host::update_host(&mut conn, &data.into_inner(), project_id, host_id).map_err(|e| {
-            error!("Failed to update host by project id: {}", e);
-            AppError::DatabaseError
+        Host::update_host(&mut conn, &data.into_inner(), project_id, host_id).map_err(|e| match e {
+            // The project already has a host with this IP address.
+            DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => AppError::BadRequest,
+            e => {
+                error!("Failed to update host by project id: {}", e);
+                AppError::DatabaseError
+            },
         })
     })
     .await??;
@@ -347,6 +383,84 @@ pub async fn delete_host_handler(
     }
 }
 
+#[derive(Debug, Deserialize)]
+pub struct WebEndpointsQuery {
+    pub host_id: Option<i32>,
+}
+
+#[get("/{project_id}/endpoints")]
+pub async fn get_web_endpoints_handler(
+    pool: web::Data<Pool<ConnectionManager<PgConnection>>>,
+    path: web::Path<String>,
+    query: web::Query<WebEndpointsQuery>,
+) -> Result<HttpResponse, AppError> {
+    let project_id = Uuid::parse_str(&path.into_inner()).map_err(|_| AppError::BadRequest)?;
+    let endpoints = web::block(move || {
+        let mut conn = pool.get().map_err(|e| {
+            error!("Failed to get database connection: {}", e);
+            AppError::InternalServerError
+        })?;
+        WebEndpoint::get_by_project(&mut conn, project_id, query.host_id).map_err(|e| {
+            error!("Failed to get web endpoints: {}", e);
+            AppError::DatabaseError
+        })
+    })
+    .await??;
+    Ok(HttpResponse::Ok().json(endpoints))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WebServicesQuery {
+    pub host_id: Option<i32>,
+}
+
+#[get("/{project_id}/web-services")]
+pub async fn get_web_services_handler(
+    pool: web::Data<Pool<ConnectionManager<PgConnection>>>,
+    path: web::Path<String>,
+    query: web::Query<WebServicesQuery>,
+) -> Result<HttpResponse, AppError> {
+    let project_id = Uuid::parse_str(&path.into_inner()).map_err(|_| AppError::BadRequest)?;
+    let services = web::block(move || {
+        let mut conn = pool.get().map_err(|e| {
+            error!("Failed to get database connection: {}", e);
+            AppError::InternalServerError
+        })?;
+        WebService::get_by_project(&mut conn, project_id, query.host_id).map_err(|e| {
+            error!("Failed to get web services: {}", e);
+            AppError::DatabaseError
+        })
+    })
+    .await??;
+    Ok(HttpResponse::Ok().json(services))
+}
+
+/// Creates an issue from a content discovery hit, or adds the hit to the
+/// issue already created for its category.
+#[post("/{project_id}/endpoints/{endpoint_id}/promote")]
+pub async fn promote_web_endpoint_handler(
+    pool: web::Data<Pool<ConnectionManager<PgConnection>>>,
+    path: web::Path<(String, i32)>,
+) -> Result<HttpResponse, AppError> {
+    let (project_id, endpoint_id) = path.into_inner();
+    let project_id = Uuid::parse_str(&project_id).map_err(|_| AppError::BadRequest)?;
+    let endpoint = web::block(move || {
+        let mut conn = pool.get().map_err(|e| {
+            error!("Failed to get database connection: {}", e);
+            AppError::InternalServerError
+        })?;
+        WebEndpoint::promote(&mut conn, project_id, endpoint_id).map_err(|e| {
+            error!("Failed to promote web endpoint: {}", e);
+            AppError::DatabaseError
+        })
+    })
+    .await??;
+    match endpoint {
+        Some(endpoint) => Ok(HttpResponse::Ok().json(endpoint)),
+        None => Err(AppError::NotFound),
+    }
+}
+
 #[derive(Debug, MultipartForm)]
 struct UploadPocForm {
     #[multipart(limit = "10MB")]
@@ -477,6 +591,7 @@ pub async fn get_report_previews_for_project_handler(
 #[post("/{project_id}/report")]
 pub async fn create_report_handler(
     pool: web::Data<Pool<ConnectionManager<PgConnection>>>,
+    scanner_service: web::Data<Arc<ScannerService>>,
     path: web::Path<String>,
     data: web::Json<ReportForm>,
 ) -> Result<HttpResponse, AppError> {
@@ -490,7 +605,7 @@ pub async fn create_report_handler(
             AppError::DatabaseError
         })?;
 
-        let project_data = Project::get_project_by_id(&mut conn, project_id)
+        let mut project_data = Project::get_project_by_id(&mut conn, project_id)
             .map_err(|e| {
                 error!("Failed to get project data: {}", e);
                 AppError::DatabaseError
@@ -499,6 +614,10 @@ pub async fn create_report_handler(
                 error!("Project not found");
                 AppError::NotFound
             })?;
+        if let Some(form) = &report_form.scan_diff {
+            project_data.scan_diff =
+                Some(diff_scans(&mut conn, &scanner_service, project_id, form)?);
+        }
 
         let service = MarkdownService;
 
@@ -574,6 +693,15 @@ pub async fn get_report_handler(
 pub struct ScanRequest {
     pub r#type: String,
     pub target: String,
+    pub profile_id: Option<i32>,
+    /// Overrides applied on top of the profile options.
+    #[serde(default)]
+    pub options: Map<String, Value>,
+    /// Scans with a higher priority are started first.
+    #[serde(default)]
+    pub priority: i32,
+    /// Justification for scanning targets outside the project scope, admins only.
+    pub scope_override: Option<String>,
     // pub proxy: Option<String>,
 }
 
@@ -583,16 +711,48 @@ pub struct ScanResponse {
     pub status: String,
 }
 
+/// Merges the profile options with the overrides and validates the result
+/// against the option schema of the scanner.
+fn scan_options(
+    conn: &mut PgConnection,
+    scanner: &dyn VulnerabilityScanner,
+    profile_id: Option<i32>,
+    overrides: Map<String, Value>,
+) -> Result<ScanOptions, AppError> {
+    let mut options = Map::new();
+    if let Some(profile_id) = profile_id {
+        let profile = ScanProfile::find_by_id(conn, profile_id)?.ok_or(AppError::NotFound)?;
+        if profile.scanner_type != scanner.name() {
+            return Err(AppError::BadRequest);
+        }
+        options = profile.options_map();
+    }
+    options.extend(overrides);
+    ScanOptions::validate(&scanner.options(), options).map_err(|e| {
+        error!("Invalid scan request: {}", e);
+        AppError::BadRequest
+    })
+}
+
 #[post("/{project_id}/scan")]
 pub async fn start_scan_handler(
     pool: web::Data<Pool<ConnectionManager<PgConnection>>>,
+    session: Session,
     scanner_service: web::Data<Arc<ScannerService>>,
+    scan_queue: web::Data<Arc<ScanQueue>>,
     path: web::Path<String>,
     data: web::Json<ScanRequest>,
 ) -> Result<HttpResponse, AppError> {
     // 1. Подготовка данных
     let project_id = Uuid::parse_str(&path.into_inner()).map_err(|_| AppError::BadRequest)?;
     let scan_request = data.into_inner();
+    let scanner = scanner_service
+        .get(&scan_request.r#type)
+        .ok_or(AppError::BadRequest)?;
+    let targets = parse_targets(&scan_request.target).map_err(|e| {
+        error!("Invalid scan request: {}", e);
+        AppError::BadRequest
+    })?;
 
     // 2. Сохранение в БД
     let mut conn = pool.get().map_err(|e| {
@@ -600,85 +760,668 @@ pub async fn start_scan_handler(
         AppError::DatabaseError
     })?;
 
+    let project = Project::find_by_id(&mut conn, project_id)?.ok_or(AppError::NotFound)?;
+    if !project.in_engagement_window(Utc::now().naive_utc()) {
+        return Err(AppError::OutOfScope(format!(
+            "Project engagement window is {} to {}",
+            project.start_date, project.end_date
+        )));
+    }
+    let user = session.get::<UserSession>("user_data").ok().flatten();
+    enforce_scope(
+        &mut conn,
+        project_id,
+        &scan_request.r#type,
+        &targets,
+        user.as_ref(),
+        scan_request.scope_override.as_deref(),
+    )?;
+
+    let options = scan_options(
+        &mut conn,
+        scanner.as_ref(),
+        scan_request.profile_id,
+        scan_request.options,
+    )?;
+
     let scan_record = Scan::create_scan(&mut conn, NewScan {
         project_id,
         scanner_type: scan_request.r#type.clone(),
-        status: "queued".to_string(),
+        status: ScanStatus::Queued.into(),
         target: scan_request.target.clone(),
         result_path: None,
+        profile_id: scan_request.profile_id,
+        options: options.into_value(),
+        priority: scan_request.priority,
+        schedule_id: None,
     })?;
 
-    // 3. Запуск в фоне
-    let service = scanner_service.clone();
-    let pool = pool.clone();
-    let target = scan_request.target.clone();
-    let scanner_type = scan_request.r#type.clone();
-
-    tokio::spawn(async move {
-        // Получаем новое соединение для фоновой задачи
-        let mut conn = match pool.get() {
-            Ok(conn) => conn,
-            Err(e) => {
-                error!("Background task failed to get DB connection: {}", e);
-                return;
-            }
-        };
-
-        // Выбираем сканер
-        let scanner = match scanner_type.as_str() {
-            "nmap" => Scanner::Nmap(service.get_nmap().await),
-            "nuclei" => Scanner::Nuclei(service.get_nuclei().await),
-            _ => {
-                error!("Unknown scanner type");
-                return;
-            }
-        };
-
-        // Выполняем сканирование и сохраняем результат
-        match scanner.start_scan(scan_record.id, &target).await {
-            Ok(scan_result) => {
-                if let Err(e) = scan_result.save_data(project_id, &mut conn) {
-                    error!("Failed to save scan data: {}", e);
-                }
-            },
-            Err(e) => {
-                error!("Scan failed: {}", e);
-                // Можно обновить статус в БД как failed
-            }
-        }
-    });
+    // 3. Постановка в очередь
+    scan_queue.notify();
 
     // 4. Ответ
     Ok(HttpResponse::Accepted().json(ScanResponse {
         scan_id: scan_record.id,
-        status: "started".to_string(),
+        status: scan_record.status,
     }))
 }
 
+#[get("/{project_id}/scan/{scan_id}/artifacts")]
+pub async fn get_scan_artifacts_handler(
+    pool: web::Data<Pool<ConnectionManager<PgConnection>>>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, AppError> {
+    let (project_id, scan_id) = path.into_inner();
+    let project_id = Uuid::parse_str(&project_id).map_err(|_| AppError::BadRequest)?;
+    let scan_id = Uuid::parse_str(&scan_id).map_err(|_| AppError::BadRequest)?;
+
+    let artifacts = web::block(move || {
+        let mut conn = pool.get().map_err(|e| {
+            error!("Failed to get database connection: {}", e);
+            AppError::InternalServerError
+        })?;
+        Scan::find_by_id(&mut conn, scan_id)?
+            .filter(|scan| scan.project_id == project_id)
+            .ok_or(AppError::NotFound)?;
+        Ok::<Vec<ScanArtifact>, AppError>(ScanArtifact::get_by_scan(&mut conn, scan_id)?)
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(artifacts))
+}
+
+#[get("/{project_id}/scan/{scan_id}/artifacts/{artifact_id}")]
+pub async fn download_scan_artifact_handler(
+    pool: web::Data<Pool<ConnectionManager<PgConnection>>>,
+    path: web::Path<(String, String, i32)>,
+) -> Result<HttpResponse, AppError> {
+    let (project_id, scan_id, artifact_id) = path.into_inner();
+    let project_id = Uuid::parse_str(&project_id).map_err(|_| AppError::BadRequest)?;
+    let scan_id = Uuid::parse_str(&scan_id).map_err(|_| AppError::BadRequest)?;
+
+    let (file_name, data) = web::block(move || {
+        let mut conn = pool.get().map_err(|e| {
+            error!("Failed to get database connection: {}", e);
+            AppError::InternalServerError
+        })?;
+        let scan = Scan::find_by_id(&mut conn, scan_id)?
+            .filter(|scan| scan.project_id == project_id)
+            .ok_or(AppError::NotFound)?;
+        let artifact =
+            ScanArtifact::find_by_id(&mut conn, scan_id, artifact_id)?.ok_or(AppError::NotFound)?;
+        let data = std::fs::read(&artifact.path).map_err(|e| {
+            error!("Failed to read scan artifact {}: {}", artifact.path, e);
+            match e.kind() {
+                std::io::ErrorKind::NotFound => AppError::NotFound,
+                _ => AppError::InternalServerError,
+            }
+        })?;
+        // Downloads of several scans shouldn't all be called scan.xml.
+        let file_name = format!(
+            "{}-{}-{}",
+            scan.scanner_type,
+            &scan.id.to_string()[..8],
+            artifact.file_name
+        );
+        Ok::<(String, Vec<u8>), AppError>((file_name, data))
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok()
+        .content_type(mime_guess::from_path(&file_name).first_or_octet_stream())
+        .append_header((
+            "Content-Disposition",
+            format!("attachment; filename=\"{}\"", file_name),
+        ))
+        .body(data))
+}
+
 #[get("/{project_id}/scan/{scanner_type}/{scan_id}")]
 pub async fn get_scan_result_handler(
-    scanner_service: web::Data<ScannerService>,
+    pool: web::Data<Pool<ConnectionManager<PgConnection>>>,
+    scanner_service: web::Data<Arc<ScannerService>>,
+    path: web::Path<(String, String, String)>,
+) -> Result<HttpResponse, AppError> {
+    let (project_id, scanner_type, scan_id) = path.into_inner();
+    let project_id = Uuid::parse_str(&project_id).map_err(|_| AppError::BadRequest)?;
+    let scan_id = Uuid::parse_str(&scan_id).map_err(|_| AppError::BadRequest)?;
+    let scanner = scanner_service.get(&scanner_type).ok_or(AppError::BadRequest)?;
+
+    let scan = web::block(move || {
+        let mut conn = pool.get().map_err(|e| {
+            error!("Failed to get database connection: {}", e);
+            AppError::InternalServerError
+        })?;
+        Scan::find_by_id(&mut conn, scan_id)?
+            .filter(|scan| scan.project_id == project_id)
+            .ok_or(AppError::NotFound)
+    })
+    .await??;
+
+    // The queue records where the scanner left its output, older scans
+    // only have the scanner's default location.
+    let result = match &scan.result_path {
+        Some(result_path) => scanner.load_result(Path::new(result_path)),
+        None => scanner.get_scan_result(scan_id).await,
+    };
+    match result {
+        Ok(result) => Ok(HttpResponse::Ok().json(result)),
+        Err(e) => {
+            error!("Failed to get scan result: {}", e);
+            Err(AppError::InternalServerError)
+        }
+    }
+}
+
+#[post("/{project_id}/scan/{scan_id}/cancel")]
+pub async fn cancel_scan_handler(
+    pool: web::Data<Pool<ConnectionManager<PgConnection>>>,
+    scanner_service: web::Data<Arc<ScannerService>>,
     path: web::Path<(String, String)>,
 ) -> Result<HttpResponse, AppError> {
-    let (scanner_type, scan_id) = path.into_inner();
-    match scanner_type.as_str() {
-        "nuclei" => {
-            let nuclei = scanner_service.get_nuclei().await;
-            let scanner = nuclei.lock().await;
-            match scanner.get_scan_result(&scan_id).await {
-                Ok(result) => Ok(HttpResponse::Ok().json(result)),
-                Err(_) => Err(AppError::InternalServerError),
-            }
-        },
-        "nmap" => {
-            let nmap = scanner_service.get_nmap().await;
-            let scanner = nmap.lock().await;
-            match scanner.get_scan_result(&scan_id).await {
-                Ok(result) => Ok(HttpResponse::Ok().json(result)),
-                Err(_) => Err(AppError::InternalServerError),
+    let (project_id, scan_id) = path.into_inner();
+    let project_id = Uuid::parse_str(&project_id).map_err(|_| AppError::BadRequest)?;
+    let scan_id = Uuid::parse_str(&scan_id).map_err(|_| AppError::BadRequest)?;
+
+    let scan = web::block(move || {
+        let mut conn = pool.get().map_err(|e| {
+            error!("Failed to get database connection: {}", e);
+            AppError::InternalServerError
+        })?;
+        let scan = Scan::find_by_id(&mut conn, scan_id)?
+            .filter(|scan| scan.project_id == project_id)
+            .ok_or(AppError::NotFound)?;
+        if scan.status != String::from(ScanStatus::Queued)
+            && scan.status != String::from(ScanStatus::Running)
+        {
+            return Err(AppError::BadRequest);
+        }
+        // A scan that finished in the meantime can't be cancelled any more.
+        Scan::mark_cancelled(&mut conn, scan_id, None)
+            .map_err(|e| {
+                error!("Failed to cancel scan: {}", e);
+                AppError::DatabaseError
+            })?
+            .ok_or(AppError::BadRequest)
+    })
+    .await??;
+
+    // Scans the queue never claimed have no process to interrupt.
+    if scan.started_at.is_some() {
+        scanner_service.cancel(scan_id);
+    }
+    scanner_service.finish(scan_id, scan.status.clone());
+
+    Ok(HttpResponse::Ok().json(ScanResponse {
+        scan_id: scan.id,
+        status: scan.status,
+    }))
+}
+
+fn sse_frame(event: &ScanEvent) -> Result<Bytes, actix_web::Error> {
+    let data = serde_json::to_string(event).map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(Bytes::from(format!("event: {}\ndata: {}\n\n", event.name(), data)))
+}
+
+#[get("/{project_id}/scan/{scan_id}/events")]
+pub async fn scan_events_handler(
+    pool: web::Data<Pool<ConnectionManager<PgConnection>>>,
+    scanner_service: web::Data<Arc<ScannerService>>,
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, AppError> {
+    let (project_id, scan_id) = path.into_inner();
+    let project_id = Uuid::parse_str(&project_id).map_err(|_| AppError::BadRequest)?;
+    let scan_id = Uuid::parse_str(&scan_id).map_err(|_| AppError::BadRequest)?;
+
+    // Subscribe before reading the status so no event between the two is lost.
+    let receiver = scanner_service.subscribe(scan_id);
+    let scan = web::block(move || {
+        let mut conn = pool.get().map_err(|e| {
+            error!("Failed to get database connection: {}", e);
+            AppError::InternalServerError
+        })?;
+        Scan::find_by_id(&mut conn, scan_id)?
+            .filter(|scan| scan.project_id == project_id)
+            .ok_or(AppError::NotFound)
+    })
+    .await?;
+    let scan = match scan {
+        Ok(scan) => scan,
+        Err(e) => {
+            drop(receiver);
+            scanner_service.release(scan_id);
+            return Err(e);
+        }
+    };
+
+    let current = ScanEvent::Status { status: scan.status.clone() };
+    if scan.status != String::from(ScanStatus::Queued)
+        && scan.status != String::from(ScanStatus::Running)
+    {
+        scanner_service.finish(scan_id, scan.status);
+        return Ok(HttpResponse::Ok()
+            .content_type("text/event-stream")
+            .streaming(stream::once(async move { sse_frame(&current) })));
+    }
+
+    let events = stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => return Some((sse_frame(&event), receiver)),
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
             }
+        }
+    });
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(stream::once(async move { sse_frame(&current) }).chain(events)))
+}
+
+#[get("/{project_id}/scope/audit")]
+pub async fn get_scope_audit_handler(
+    pool: web::Data<Pool<ConnectionManager<PgConnection>>>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let project_id = Uuid::parse_str(&path.into_inner()).map_err(|_| AppError::BadRequest)?;
+    let records = web::block(move || {
+        let mut conn = pool.get().map_err(|e| {
+            error!("Failed to get database connection: {}", e);
+            AppError::InternalServerError
+        })?;
+        ScopeAuditRecord::get_by_project(&mut conn, project_id).map_err(|e| {
+            error!("Failed to get scope audit log: {}", e);
+            AppError::DatabaseError
+        })
+    })
+    .await??;
+    Ok(HttpResponse::Ok().json(records))
+}
+
+/// Checks the value of the entry against its kind.
+fn validate_scope_entry(project_id: Uuid, form: ScopeEntryForm) -> Result<NewScopeEntry, AppError> {
+    let value = form.value.trim().to_string();
+    if ScopeRule::parse(form.kind, &value).is_none() {
+        error!("Invalid {:?} scope entry: {}", form.kind, value);
+        return Err(AppError::BadRequest);
+    }
+    Ok(NewScopeEntry {
+        project_id,
+        kind: form.kind.into(),
+        value,
+        note: form.note.filter(|note| !note.trim().is_empty()),
+    })
+}
+
+#[get("/{project_id}/scope")]
+pub async fn get_scope_handler(
+    pool: web::Data<Pool<ConnectionManager<PgConnection>>>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let project_id = Uuid::parse_str(&path.into_inner()).map_err(|_| AppError::BadRequest)?;
+    let entries = web::block(move || {
+        let mut conn = pool.get().map_err(|e| {
+            error!("Failed to get database connection: {}", e);
+            AppError::InternalServerError
+        })?;
+        ScopeEntry::get_by_project(&mut conn, project_id).map_err(|e| {
+            error!("Failed to get scope entries: {}", e);
+            AppError::DatabaseError
+        })
+    })
+    .await??;
+    Ok(HttpResponse::Ok().json(entries))
+}
+
+#[post("/{project_id}/scope")]
+pub async fn create_scope_entry_handler(
+    pool: web::Data<Pool<ConnectionManager<PgConnection>>>,
+    path: web::Path<String>,
+    data: web::Json<ScopeEntryForm>,
+) -> Result<HttpResponse, AppError> {
+    let project_id = Uuid::parse_str(&path.into_inner()).map_err(|_| AppError::BadRequest)?;
+    let entry = validate_scope_entry(project_id, data.into_inner())?;
+    let entries = web::block(move || {
+        let mut conn = pool.get().map_err(|e| {
+            error!("Failed to get database connection: {}", e);
+            AppError::InternalServerError
+        })?;
+        Project::find_by_id(&mut conn, project_id)?.ok_or(AppError::NotFound)?;
+        ScopeEntry::create_entries(&mut conn, vec![entry]).map_err(|e| {
+            error!("Failed to create scope entry: {}", e);
+            AppError::DatabaseError
+        })
+    })
+    .await??;
+    Ok(HttpResponse::Created().json(&entries[0]))
+}
+
+#[put("/{project_id}/scope/{entry_id}")]
+pub async fn update_scope_entry_handler(
+    pool: web::Data<Pool<ConnectionManager<PgConnection>>>,
+    path: web::Path<(String, i32)>,
+    data: web::Json<ScopeEntryForm>,
+) -> Result<HttpResponse, AppError> {
+    let (project_id, entry_id) = path.into_inner();
+    let project_id = Uuid::parse_str(&project_id).map_err(|_| AppError::BadRequest)?;
+    let entry = validate_scope_entry(project_id, data.into_inner())?;
+    let entry = web::block(move || {
+        let mut conn = pool.get().map_err(|e| {
+            error!("Failed to get database connection: {}", e);
+            AppError::InternalServerError
+        })?;
+        ScopeEntry::update_entry(&mut conn, entry_id, entry).map_err(|e| {
+            error!("Failed to update scope entry: {}", e);
+            AppError::DatabaseError
+        })
+    })
+    .await??;
+    match entry {
+        Some(entry) => Ok(HttpResponse::Ok().json(entry)),
+        None => Err(AppError::NotFound),
+    }
+}
+
+#[delete("/{project_id}/scope/{entry_id}")]
+pub async fn delete_scope_entry_handler(
+    pool: web::Data<Pool<ConnectionManager<PgConnection>>>,
+    path: web::Path<(String, i32)>,
+) -> Result<HttpResponse, AppError> {
+    let (project_id, entry_id) = path.into_inner();
+    let project_id = Uuid::parse_str(&project_id).map_err(|_| AppError::BadRequest)?;
+    let count = web::block(move || {
+        let mut conn = pool.get().map_err(|e| {
+            error!("Failed to get database connection: {}", e);
+            AppError::InternalServerError
+        })?;
+        ScopeEntry::delete_entry(&mut conn, project_id, entry_id).map_err(|e| {
+            error!("Failed to delete scope entry: {}", e);
+            AppError::DatabaseError
+        })
+    })
+    .await??;
+    match count {
+        0 => Err(AppError::NotFound),
+        _ => Ok(HttpResponse::NoContent().finish()),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ScopeCheckQuery {
+    pub target: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ScopeCheckResponse {
+    pub target: String,
+    pub in_scope: bool,
+}
+
+/// Tells for every target (IP, range, host or URL) whether it may be scanned.
+#[get("/{project_id}/scope/check")]
+pub async fn check_scope_handler(
+    pool: web::Data<Pool<ConnectionManager<PgConnection>>>,
+    path: web::Path<String>,
+    query: web::Query<ScopeCheckQuery>,
+) -> Result<HttpResponse, AppError> {
+    let project_id = Uuid::parse_str(&path.into_inner()).map_err(|_| AppError::BadRequest)?;
+    let targets = parse_targets(&query.target).map_err(|e| {
+        error!("Invalid scope check: {}", e);
+        AppError::BadRequest
+    })?;
+    let scope = web::block(move || {
+        let mut conn = pool.get().map_err(|e| {
+            error!("Failed to get database connection: {}", e);
+            AppError::InternalServerError
+        })?;
+        ScopeEntry::get_scope(&mut conn, project_id).map_err(|e| {
+            error!("Failed to get scope entries: {}", e);
+            AppError::DatabaseError
+        })
+    })
+    .await??;
+    let results: Vec<ScopeCheckResponse> = targets
+        .into_iter()
+        .map(|target| ScopeCheckResponse {
+            in_scope: scope.contains(&ScopeTarget::parse(&target)),
+            target,
+        })
+        .collect();
+    Ok(HttpResponse::Ok().json(results))
+}
+
+/// Compares the stored results of two scans of the project that produce the
+/// same kind of result, e.g. an imported nmap run and a native nmap scan.
+fn diff_scans(
+    conn: &mut PgConnection,
+    scanner_service: &ScannerService,
+    project_id: Uuid,
+    form: &ScanDiffForm,
+) -> Result<ScanDiff, AppError> {
+    let mut scans = Vec::new();
+    for scan_id in [form.from, form.to] {
+        let scan = Scan::find_by_id(conn, scan_id)?
+            .filter(|scan| scan.project_id == project_id)
+            .ok_or(AppError::NotFound)?;
+        scans.push(scan);
+    }
+    let (from, to) = (&scans[0], &scans[1]);
+    Ok(ScanDiff {
+        scanner_type: from.scanner_type.clone(),
+        from: from.id,
+        to: to.id,
+        changes: compare_scans(scanner_service, from, to)?,
+    })
+}
+
+fn compare_scans(
+    scanner_service: &ScannerService,
+    from: &Scan,
+    to: &Scan,
+) -> Result<ScanChanges, AppError> {
+    let load_result = |scan: &Scan| {
+        let scanner = scanner_service.result_reader(&scan.scanner_type).ok_or_else(|| {
+            error!("No scanner reads results of {} scans", scan.scanner_type);
+            AppError::BadRequest
+        })?;
+        let result_path = scan.result_path.as_deref().ok_or_else(|| {
+            error!("Scan {} has no results to compare", scan.id);
+            AppError::BadRequest
+        })?;
+        scanner.load_result(Path::new(result_path)).map_err(|e| {
+            error!("Failed to load result of scan {}: {}", scan.id, e);
+            AppError::InternalServerError
+        })
+    };
+    ScanChanges::compare(&load_result(from)?, &load_result(to)?).map_err(|e| {
+        error!("Can't compare {} and {} scans: {}", from.scanner_type, to.scanner_type, e);
+        AppError::BadRequest
+    })
+}
+
+#[get("/{project_id}/scan/diff")]
+pub async fn get_scan_diff_handler(
+    pool: web::Data<Pool<ConnectionManager<PgConnection>>>,
+    scanner_service: web::Data<Arc<ScannerService>>,
+    path: web::Path<String>,
+    query: web::Query<ScanDiffForm>,
+) -> Result<HttpResponse, AppError> {
+    let project_id = Uuid::parse_str(&path.into_inner()).map_err(|_| AppError::BadRequest)?;
+    let diff = web::block(move || {
+        let mut conn = pool.get().map_err(|e| {
+            error!("Failed to get database connection: {}", e);
+            AppError::InternalServerError
+        })?;
+        diff_scans(&mut conn, &scanner_service, project_id, &query)
+    })
+    .await??;
+    Ok(HttpResponse::Ok().json(diff))
+}
+
+#[get("/{project_id}/schedules")]
+pub async fn get_scan_schedules_handler(
+    pool: web::Data<Pool<ConnectionManager<PgConnection>>>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let project_id = Uuid::parse_str(&path.into_inner()).map_err(|_| AppError::BadRequest)?;
+    let schedules = web::block(move || {
+        let mut conn = pool.get().map_err(|e| {
+            error!("Failed to get database connection: {}", e);
+            AppError::InternalServerError
+        })?;
+        ScanSchedule::get_by_project(&mut conn, project_id).map_err(|e| {
+            error!("Failed to get scan schedules: {}", e);
+            AppError::DatabaseError
+        })
+    })
+    .await??;
+    Ok(HttpResponse::Ok().json(schedules))
+}
+
+#[post("/{project_id}/schedules")]
+pub async fn create_scan_schedule_handler(
+    pool: web::Data<Pool<ConnectionManager<PgConnection>>>,
+    session: Session,
+    scanner_service: web::Data<Arc<ScannerService>>,
+    path: web::Path<String>,
+    data: web::Json<ScanScheduleForm>,
+) -> Result<HttpResponse, AppError> {
+    let project_id = Uuid::parse_str(&path.into_inner()).map_err(|_| AppError::BadRequest)?;
+    let form = data.into_inner();
+    let scanner = scanner_service.get(&form.r#type).ok_or(AppError::BadRequest)?;
+    let targets = parse_targets(&form.target).map_err(|e| {
+        error!("Invalid scan schedule: {}", e);
+        AppError::BadRequest
+    })?;
+    let user = session.get::<UserSession>("user_data").ok().flatten();
+
+    let schedule = web::block(move || {
+        let mut conn = pool.get().map_err(|e| {
+            error!("Failed to get database connection: {}", e);
+            AppError::InternalServerError
+        })?;
+        let project = Project::find_by_id(&mut conn, project_id)?.ok_or(AppError::NotFound)?;
+        let next_run_at = match (form.run_at, form.cron.as_deref()) {
+            (Some(run_at), None) => Some(run_at).filter(|run_at| project.in_engagement_window(*run_at)),
+            (None, Some(expression)) => {
+                let cron = parse_cron(expression).map_err(|e| {
+                    error!("Invalid scan schedule: {}", e);
+                    AppError::BadRequest
+                })?;
+                next_run(&cron, Utc::now().naive_utc(), &project)
+            },
+            _ => return Err(AppError::BadRequest),
+        };
+        let Some(next_run_at) = next_run_at else {
+            return Err(AppError::OutOfScope(format!(
+                "Schedule never runs within the engagement window {} to {}",
+                project.start_date, project.end_date
+            )));
+        };
+        enforce_scope(&mut conn, project_id, &form.r#type, &targets, user.as_ref(), None)?;
+        let options = scan_options(&mut conn, scanner.as_ref(), form.profile_id, form.options)?;
+
+        ScanSchedule::create_schedule(&mut conn, NewScanSchedule {
+            project_id,
+            scanner_type: form.r#type,
+            target: form.target,
+            profile_id: form.profile_id,
+            options: options.into_value(),
+            priority: form.priority,
+            run_at: form.run_at,
+            cron: form.cron,
+            next_run_at: Some(next_run_at),
+            created_by: user.map(|user| user.user_id),
+        })
+        .map_err(|e| {
+            error!("Failed to create scan schedule: {}", e);
+            AppError::DatabaseError
+        })
+    })
+    .await??;
+    Ok(HttpResponse::Created().json(schedule))
+}
+
+/// Pauses or resumes a schedule. Recurring schedules resume at their next
+/// occurrence; a one-off schedule whose time passed while paused runs at once.
+fn set_schedule_paused(
+    conn: &mut PgConnection,
+    project_id: Uuid,
+    schedule_id: i32,
+    paused: bool,
+) -> Result<ScanSchedule, AppError> {
+    let schedule = ScanSchedule::find_by_id(conn, project_id, schedule_id)?.ok_or(AppError::NotFound)?;
+    let next_run_at = match (paused, schedule.cron.as_deref()) {
+        (true, _) => schedule.next_run_at,
+        (false, Some(expression)) => {
+            let project = Project::find_by_id(conn, project_id)?.ok_or(AppError::NotFound)?;
+            parse_cron(expression)
+                .ok()
+                .and_then(|cron| next_run(&cron, Utc::now().naive_utc(), &project))
         },
-        _ => Err(AppError::BadRequest),
+        (false, None) => schedule.run_at.filter(|_| schedule.last_run_at.is_none()),
+    };
+    Ok(ScanSchedule::set_paused(conn, schedule_id, paused, next_run_at)?)
+}
+
+#[post("/{project_id}/schedules/{schedule_id}/pause")]
+pub async fn pause_scan_schedule_handler(
+    pool: web::Data<Pool<ConnectionManager<PgConnection>>>,
+    path: web::Path<(String, i32)>,
+) -> Result<HttpResponse, AppError> {
+    let (project_id, schedule_id) = path.into_inner();
+    let project_id = Uuid::parse_str(&project_id).map_err(|_| AppError::BadRequest)?;
+    let schedule = web::block(move || {
+        let mut conn = pool.get().map_err(|e| {
+            error!("Failed to get database connection: {}", e);
+            AppError::InternalServerError
+        })?;
+        set_schedule_paused(&mut conn, project_id, schedule_id, true)
+    })
+    .await??;
+    Ok(HttpResponse::Ok().json(schedule))
+}
+
+#[post("/{project_id}/schedules/{schedule_id}/resume")]
+pub async fn resume_scan_schedule_handler(
+    pool: web::Data<Pool<ConnectionManager<PgConnection>>>,
+    path: web::Path<(String, i32)>,
+) -> Result<HttpResponse, AppError> {
+    let (project_id, schedule_id) = path.into_inner();
+    let project_id = Uuid::parse_str(&project_id).map_err(|_| AppError::BadRequest)?;
+    let schedule = web::block(move || {
+        let mut conn = pool.get().map_err(|e| {
+            error!("Failed to get database connection: {}", e);
+            AppError::InternalServerError
+        })?;
+        set_schedule_paused(&mut conn, project_id, schedule_id, false)
+    })
+    .await??;
+    Ok(HttpResponse::Ok().json(schedule))
+}
+
+#[delete("/{project_id}/schedules/{schedule_id}")]
+pub async fn delete_scan_schedule_handler(
+    pool: web::Data<Pool<ConnectionManager<PgConnection>>>,
+    path: web::Path<(String, i32)>,
+) -> Result<HttpResponse, AppError> {
+    let (project_id, schedule_id) = path.into_inner();
+    let project_id = Uuid::parse_str(&project_id).map_err(|_| AppError::BadRequest)?;
+    let count = web::block(move || {
+        let mut conn = pool.get().map_err(|e| {
+            error!("Failed to get database connection: {}", e);
+            AppError::InternalServerError
+        })?;
+        ScanSchedule::delete_schedule(&mut conn, project_id, schedule_id).map_err(|e| {
+            error!("Failed to delete scan schedule: {}", e);
+            AppError::DatabaseError
+        })
+    })
+    .await??;
+    match count {
+        0 => Err(AppError::NotFound),
+        _ => Ok(HttpResponse::NoContent().finish()),
     }
 }
 
@@ -703,3 +1446,69 @@ pub async fn get_scan_all_handler(
     .await??;
     Ok(HttpResponse::Ok().json(scans))
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::services::scanner::nmap::NmapService;
+    use crate::services::scanner::nuclei::NucleiService;
+    use crate::services::scanner::process::ProcessRegistry;
+
+    use super::*;
+
+    fn scan(scanner_type: &str, fixture: &str) -> Scan {
+        Scan {
+            id: Uuid::new_v4(),
+            project_id: Uuid::nil(),
+            scanner_type: scanner_type.to_string(),
+            status: ScanStatus::Completed.into(),
+            result_path: Some(format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), fixture)),
+            target: "10.0.0.0/24".to_string(),
+            started_at: None,
+            finished_at: None,
+            error: None,
+            profile_id: None,
+            options: ScanOptions::default().into_value(),
+            priority: 0,
+            queued_at: Utc::now().naive_utc(),
+            schedule_id: None,
+        }
+    }
+
+    #[test]
+    fn test_compare_imported_and_native_scans() {
+        let mut scanner_service = ScannerService::default();
+        let processes = ProcessRegistry::default();
+        scanner_service.register(Arc::new(NmapService::new("/tmp/scans", processes.clone())));
+        scanner_service.register(Arc::new(NucleiService::new(
+            "/tmp/scans",
+            "/tmp/nuclei-templates",
+            processes,
+        )));
+
+        let imported = scan("nmap-import", "nmap/scan.gnmap");
+        let native = scan("nmap", "nmap/scan.xml");
+        let changes = compare_scans(&scanner_service, &imported, &native).unwrap();
+        assert!(matches!(changes, ScanChanges::Hosts(_)));
+
+        let imported = scan("nuclei-import", "nuclei/scan.jsonl");
+        let native = scan("nuclei", "nuclei/scan.json");
+        let changes = compare_scans(&scanner_service, &imported, &native).unwrap();
+        let ScanChanges::Issues(findings) = changes else {
+            panic!("nuclei scans should compare findings");
+        };
+        assert!(findings.new.is_empty());
+        assert!(findings.fixed.is_empty());
+        assert_eq!(findings.persisting.len(), 4);
+
+        let hosts = scan("nmap-import", "nmap/scan.gnmap");
+        assert!(matches!(
+            compare_scans(&scanner_service, &hosts, &native),
+            Err(AppError::BadRequest)
+        ));
+        let unknown = scan("ffuf-import", "ffuf/scan.json");
+        assert!(matches!(
+            compare_scans(&scanner_service, &unknown, &native),
+            Err(AppError::BadRequest)
+        ));
+    }
+}
@@ -0,0 +1,169 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use actix_multipart::form::MultipartForm;
+use actix_web::{delete, get, post, put, web, HttpResponse};
+use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::PgConnection;
+use log::error;
+
+use crate::dtos::handlers::{ScanProfileForm, UploadWordlistForm};
+use crate::models::scan_profile::{NewScanProfile, ScanProfile};
+use crate::services::scanner::types::ScanOptions;
+use crate::services::scanner::ScannerService;
+use crate::services::wordlists;
+use crate::utils::config::CONFIG;
+use crate::utils::errors::AppError;
+
+#[get("")]
+pub async fn get_scanners_handler(
+    scanner_service: web::Data<Arc<ScannerService>>,
+) -> Result<HttpResponse, AppError> {
+    Ok(HttpResponse::Ok().json(scanner_service.list()))
+}
+
+/// Checks the profile against the option schema of the scanner it targets.
+fn validate_profile(
+    scanner_service: &ScannerService,
+    form: ScanProfileForm,
+) -> Result<NewScanProfile, AppError> {
+    let scanner = scanner_service.get(&form.scanner_type).ok_or(AppError::BadRequest)?;
+    let options = ScanOptions::validate(&scanner.options(), form.options).map_err(|e| {
+        error!("Invalid scan profile: {}", e);
+        AppError::BadRequest
+    })?;
+    Ok(NewScanProfile {
+        name: form.name,
+        scanner_type: form.scanner_type,
+        options: options.into_value(),
+    })
+}
+
+#[get("/profiles")]
+pub async fn get_scan_profiles_handler(
+    pool: web::Data<Pool<ConnectionManager<PgConnection>>>,
+) -> Result<HttpResponse, AppError> {
+    let profiles = web::block(move || {
+        let mut conn = pool.get().map_err(|e| {
+            error!("Failed to get database connection: {}", e);
+            AppError::InternalServerError
+        })?;
+        ScanProfile::get_profiles(&mut conn).map_err(|e| {
+            error!("Failed to get scan profiles: {}", e);
+            AppError::DatabaseError
+        })
+    })
+    .await??;
+    Ok(HttpResponse::Ok().json(profiles))
+}
+
+#[post("/profiles")]
+pub async fn create_scan_profile_handler(
+    pool: web::Data<Pool<ConnectionManager<PgConnection>>>,
+    scanner_service: web::Data<Arc<ScannerService>>,
+    data: web::Json<ScanProfileForm>,
+) -> Result<HttpResponse, AppError> {
+    let profile = validate_profile(&scanner_service, data.into_inner())?;
+    let profile = web::block(move || {
+        let mut conn = pool.get().map_err(|e| {
+            error!("Failed to get database connection: {}", e);
+            AppError::InternalServerError
+        })?;
+        ScanProfile::create_profile(&mut conn, profile).map_err(|e| {
+            error!("Failed to create scan profile: {}", e);
+            AppError::DatabaseError
+        })
+    })
+    .await??;
+    Ok(HttpResponse::Created().json(profile))
+}
+
+#[put("/profiles/{profile_id}")]
+pub async fn update_scan_profile_handler(
+    pool: web::Data<Pool<ConnectionManager<PgConnection>>>,
+    scanner_service: web::Data<Arc<ScannerService>>,
+    path: web::Path<i32>,
+    data: web::Json<ScanProfileForm>,
+) -> Result<HttpResponse, AppError> {
+    let profile_id = path.into_inner();
+    let profile = validate_profile(&scanner_service, data.into_inner())?;
+    let profile = web::block(move || {
+        let mut conn = pool.get().map_err(|e| {
+            error!("Failed to get database connection: {}", e);
+            AppError::InternalServerError
+        })?;
+        ScanProfile::update_profile(&mut conn, profile_id, profile).map_err(|e| {
+            error!("Failed to update scan profile: {}", e);
+            AppError::DatabaseError
+        })
+    })
+    .await??;
+    match profile {
+        Some(profile) => Ok(HttpResponse::Ok().json(profile)),
+        None => Err(AppError::NotFound),
+    }
+}
+
+#[delete("/profiles/{profile_id}")]
+pub async fn delete_scan_profile_handler(
+    pool: web::Data<Pool<ConnectionManager<PgConnection>>>,
+    path: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let profile_id = path.into_inner();
+    let count = web::block(move || {
+        let mut conn = pool.get().map_err(|e| {
+            error!("Failed to get database connection: {}", e);
+            AppError::InternalServerError
+        })?;
+        ScanProfile::delete_profile(&mut conn, profile_id).map_err(|e| {
+            error!("Failed to delete scan profile: {}", e);
+            AppError::DatabaseError
+        })
+    })
+    .await??;
+    match count {
+        0 => Err(AppError::NotFound),
+        _ => Ok(HttpResponse::NoContent().finish()),
+    }
+}
+
+#[get("/wordlists")]
+pub async fn get_wordlists_handler() -> Result<HttpResponse, AppError> {
+    let wordlists = web::block(|| {
+        wordlists::list_wordlists(Path::new(&CONFIG.wordlists_path)).map_err(|e| {
+            error!("Failed to list wordlists: {}", e);
+            AppError::InternalServerError
+        })
+    })
+    .await??;
+    Ok(HttpResponse::Ok().json(wordlists))
+}
+
+#[post("/wordlists")]
+pub async fn upload_wordlist_handler(
+    MultipartForm(form): MultipartForm<UploadWordlistForm>,
+) -> Result<HttpResponse, AppError> {
+    let file = form.file.ok_or(AppError::BadRequest)?;
+    let name = form
+        .name
+        .map(|name| name.into_inner())
+        .or(file.file_name.clone())
+        .ok_or(AppError::BadRequest)?;
+    if !wordlists::is_valid_name(&name) {
+        error!("Invalid wordlist name: {}", name);
+        return Err(AppError::BadRequest);
+    }
+
+    let wordlist = web::block(move || {
+        let data = std::fs::read(file.file.path()).map_err(|e| {
+            error!("Failed to read uploaded file: {}", e);
+            AppError::BadRequest
+        })?;
+        wordlists::save_wordlist(Path::new(&CONFIG.wordlists_path), &name, &data).map_err(|e| {
+            error!("Failed to save wordlist: {}", e);
+            AppError::InternalServerError
+        })
+    })
+    .await??;
+    Ok(HttpResponse::Created().json(wordlist))
+}
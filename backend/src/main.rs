@@ -72,7 +72,10 @@ async fn main() -> std::io::Result<()> {
         .init();
 
     let pool = db::establish_connection();
-    let scanner_service = Arc::new(scanner::ScannerService::new(&CONFIG, pool.clone()));
+    let scanner_service = Arc::new(scanner::ScannerService::new(&CONFIG));
+    let scan_queue = Arc::new(scanner::queue::ScanQueue::new(pool.clone(), scanner_service.clone(), &CONFIG));
+    scan_queue.clone().start();
+    Arc::new(scanner::scheduler::ScanScheduler::new(pool.clone(), scan_queue.clone())).start();
 
     create_admin_user(&mut pool.get().unwrap()).unwrap();
 
@@ -87,6 +90,7 @@ async fn main() -> std::io::Result<()> {
             .wrap(cors)
             .app_data(Data::new(pool.clone()))
             .app_data(Data::new(scanner_service.clone()))
+            .app_data(Data::new(scan_queue.clone()))
             .wrap(Logger::new(
                 "%a %t \"%r\" %s %b \"%{Referer}i\" \"%{User-Agent}i\" %T",
             ))
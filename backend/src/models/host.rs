@@ -1,9 +1,13 @@
+use diesel::dsl::case_when;
 use diesel::prelude::*;
+use diesel::sql_types::{Nullable, SingleValue};
+use diesel::upsert::excluded;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::db::schema::{hosts, projects};
 use crate::dtos::handlers::HostForm;
+use crate::models::host_service::HostService;
 use crate::models::project::Project;
 
 #[derive(Queryable, Identifiable, Serialize, Selectable, Associations, PartialEq, Debug)]
@@ -16,6 +20,13 @@ pub struct Host {
     pub hostname: Option<String>,
     pub ip_address: String,
     pub project_id: Uuid,
+    pub os: Option<String>,
+    pub os_accuracy: Option<i32>,
+    /// Where the host was found, e.g. "subfinder:crtsh".
+    pub sources: Vec<String>,
+    /// Every name scans found the address under, e.g. subdomains behind a
+    /// shared CDN address.
+    pub hostnames: Vec<String>,
 }
 
 #[derive(Insertable, Deserialize, AsChangeset, Debug)]
@@ -24,6 +35,15 @@ pub struct NewHost {
     pub(crate) hostname: Option<String>,
     pub(crate) ip_address: String,
     pub(crate) project_id: Uuid,
+    pub(crate) os: Option<String>,
+    pub(crate) os_accuracy: Option<i32>,
+}
+
+/// Number of hosts a scan added to the project and merged into known ones.
+#[derive(Serialize, Default, Debug, Clone, Copy, PartialEq)]
+pub struct SavedHosts {
+    pub created: usize,
+    pub updated: usize,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -33,13 +53,25 @@ pub struct HostResponse {
     pub ip_address: String,
 }
 
+#[derive(Serialize, Debug)]
+pub struct HostFullResponse {
+    pub id: i32,
+    pub hostname: Option<String>,
+    pub ip_address: String,
+    pub os: Option<String>,
+    pub os_accuracy: Option<i32>,
+    pub sources: Vec<String>,
+    pub hostnames: Vec<String>,
+    pub services: Vec<HostService>,
+}
+
+define_sql_function!(fn coalesce<T: SingleValue>(x: Nullable<T>, y: Nullable<T>) -> Nullable<T>);
+
 impl Host {
     pub fn get_hosts_by_project_id(
         conn: &mut PgConnection,
         id_project: Uuid,
-    ) -> QueryResult<Vec<HostResponse>> {
-        use crate::db::schema::hosts::dsl::*;
-
+    ) -> QueryResult<Vec<HostFullResponse>> {
         let project = match projects::table
             .find(id_project)
             .select(Project::as_select())
@@ -51,15 +83,26 @@ impl Host {
         };
 
         let selected_hosts = Host::belonging_to(&project)
-            .select((id, hostname, ip_address))
-            .load::<(i32, Option<String>, String)>(conn)?;
+            .select(Host::as_select())
+            .load::<Host>(conn)?;
+
+        let services = HostService::belonging_to(&selected_hosts)
+            .select(HostService::as_select())
+            .load::<HostService>(conn)?
+            .grouped_by(&selected_hosts);
 
         let mut result = Vec::with_capacity(selected_hosts.len());
-        for (host_id, host, ip) in selected_hosts {
-            result.push(HostResponse {
-                id: host_id,
-                hostname: host,
-                ip_address: ip,
+        for (host, mut host_services) in selected_hosts.into_iter().zip(services) {
+            host_services.sort_by_key(|service| service.port);
+            result.push(HostFullResponse {
+                id: host.id,
+                hostname: host.hostname,
+                ip_address: host.ip_address,
+                os: host.os,
+                os_accuracy: host.os_accuracy,
+                sources: host.sources,
+                hostnames: host.hostnames,
+                services: host_services,
             });
         }
 
@@ -76,10 +119,16 @@ impl Host {
             hostname: form.hostname.clone(),
             ip_address: form.ip_address.clone(),
             project_id: id_project,
+            os: form.os.clone(),
+            os_accuracy: form.os_accuracy,
         };
-        diesel::insert_into(hosts)
-            .values(new_host)
-            .get_result::<Host>(conn)
+        conn.transaction(|conn| {
+            let host = diesel::insert_into(hosts)
+                .values(new_host)
+                .get_result::<Host>(conn)?;
+            HostService::create_services(conn, &form.services, host.id)?;
+            Ok(host)
+        })
     }
 
     pub fn get_host(conn: &mut PgConnection, host_id: i32) -> QueryResult<Option<Host>> {
@@ -91,6 +140,19 @@ impl Host {
             .optional()
     }
 
+    pub fn to_full_response(&self, conn: &mut PgConnection) -> QueryResult<HostFullResponse> {
+        Ok(HostFullResponse {
+            id: self.id,
+            hostname: self.hostname.clone(),
+            ip_address: self.ip_address.clone(),
+            os: self.os.clone(),
+            os_accuracy: self.os_accuracy,
+            sources: self.sources.clone(),
+            hostnames: self.hostnames.clone(),
+            services: HostService::get_services_by_host_id(conn, self.id)?,
+        })
+    }
+
     pub fn get_host_by_ip(conn: &mut PgConnection, ip: String) -> QueryResult<Option<Host>> {
         use crate::db::schema::hosts::dsl::*;
         hosts
@@ -116,28 +178,190 @@ impl Host {
             hostname: form.hostname.clone(),
             ip_address: form.ip_address.clone(),
             project_id: id_project,
+            os: form.os.clone(),
+            os_accuracy: form.os_accuracy,
         };
         diesel::update(hosts.filter(id.eq(host_id)))
             .set(&new_host)
             .execute(conn)
     }
 
+    /// Saves scanned hosts, merging them into the hosts already known under
+    /// the same IP address in the project. Hostname and OS are only replaced
+    /// when the scan found them, every name is kept in `hostnames` and
+    /// services are merged by port and protocol.
     pub fn create_hosts(
         conn: &mut PgConnection,
         forms: Vec<HostForm>,
         id_project: Uuid,
-    ) -> QueryResult<Vec<Host>> {
+    ) -> QueryResult<SavedHosts> {
         use crate::db::schema::hosts::dsl::*;
-        let mut new_hosts_vec: Vec<NewHost> = Vec::new();
-        for form in forms {
-            new_hosts_vec.push(NewHost {
-                hostname: form.hostname,
-                ip_address: form.ip_address,
+        let forms = merge_forms(forms);
+        if forms.is_empty() {
+            return Ok(SavedHosts::default());
+        }
+        let new_hosts_vec: Vec<NewHost> = forms
+            .iter()
+            .map(|form| NewHost {
+                hostname: form.hostname.clone(),
+                ip_address: form.ip_address.clone(),
                 project_id: id_project,
-            });
+                os: form.os.clone(),
+                os_accuracy: form.os_accuracy,
+            })
+            .collect();
+        conn.transaction(|conn| {
+            let addresses: Vec<&String> = forms.iter().map(|form| &form.ip_address).collect();
+            let updated = hosts
+                .filter(project_id.eq(id_project))
+                .filter(ip_address.eq_any(addresses))
+                .count()
+                .get_result::<i64>(conn)? as usize;
+
+            let saved_hosts = diesel::insert_into(hosts)
+                .values(new_hosts_vec)
+                .on_conflict((project_id, ip_address))
+                .do_update()
+                .set((
+                    hostname.eq(coalesce(excluded(hostname), hostname)),
+                    os_accuracy.eq(case_when(excluded(os).is_null(), os_accuracy)
+                        .otherwise(excluded(os_accuracy))),
+                    os.eq(coalesce(excluded(os), os)),
+                ))
+                .get_results::<Host>(conn)?;
+            for host in &saved_hosts {
+                if let Some(form) = forms.iter().find(|form| form.ip_address == host.ip_address) {
+                    HostService::create_services(conn, &form.services, host.id)?;
+                    let mut host_sources = host.sources.clone();
+                    for source in &form.sources {
+                        if !host_sources.contains(source) {
+                            host_sources.push(source.clone());
+                        }
+                    }
+                    let mut host_names = host.hostnames.clone();
+                    for name in form.hostname.iter().chain(&form.hostnames) {
+                        if !host_names.contains(name) {
+                            host_names.push(name.clone());
+                        }
+                    }
+                    if host_sources != host.sources || host_names != host.hostnames {
+                        diesel::update(hosts.find(host.id))
+                            .set((sources.eq(host_sources), hostnames.eq(host_names)))
+                            .execute(conn)?;
+                    }
+                }
+            }
+            Ok(SavedHosts {
+                created: saved_hosts.len() - updated,
+                updated,
+            })
+        })
+    }
+
+    /// Creates hosts whose IP address is not yet known in the project and
+    /// leaves existing ones untouched.
+    pub fn create_missing_hosts(
+        conn: &mut PgConnection,
+        forms: Vec<HostForm>,
+        id_project: Uuid,
+    ) -> QueryResult<SavedHosts> {
+        use crate::db::schema::hosts::dsl::*;
+        let addresses: Vec<&String> = forms.iter().map(|form| &form.ip_address).collect();
+        let existing = hosts
+            .filter(project_id.eq(id_project))
+            .filter(ip_address.eq_any(addresses))
+            .select(ip_address)
+            .load::<String>(conn)?;
+
+        let missing: Vec<HostForm> = forms
+            .into_iter()
+            .filter(|form| !existing.contains(&form.ip_address))
+            .collect();
+        Self::create_hosts(conn, missing, id_project)
+    }
+}
+
+/// Merges forms of the same IP address, as a single insert may not touch a
+/// row twice. Later forms win, services are combined.
+fn merge_forms(forms: Vec<HostForm>) -> Vec<HostForm> {
+    let mut merged: Vec<HostForm> = Vec::with_capacity(forms.len());
+    for form in forms {
+        match merged.iter_mut().find(|host| host.ip_address == form.ip_address) {
+            Some(host) => {
+                if form.os.is_some() {
+                    host.os = form.os;
+                    host.os_accuracy = form.os_accuracy;
+                }
+                for source in form.sources {
+                    if !host.sources.contains(&source) {
+                        host.sources.push(source);
+                    }
+                }
+                // The hostname that loses is kept among the other names.
+                let names: Vec<String> =
+                    host.hostname.iter().chain(&form.hostname).chain(&form.hostnames).cloned().collect();
+                for name in names {
+                    if !host.hostnames.contains(&name) {
+                        host.hostnames.push(name);
+                    }
+                }
+                host.hostname = form.hostname.or(host.hostname.take());
+                for service in form.services {
+                    host.services
+                        .retain(|known| (known.port, &known.protocol) != (service.port, &service.protocol));
+                    host.services.push(service);
+                }
+            },
+            None => merged.push(form),
         }
-        diesel::insert_into(hosts)
-            .values(new_hosts_vec)
-            .get_results::<Host>(conn)
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dtos::handlers::ServiceForm;
+
+    fn form(ip: &str, hostname: Option<&str>, ports: &[(i32, &str)]) -> HostForm {
+        HostForm {
+            hostname: hostname.map(str::to_string),
+            ip_address: ip.to_string(),
+            os: None,
+            os_accuracy: None,
+            services: ports
+                .iter()
+                .map(|(port, state)| ServiceForm {
+                    port: *port,
+                    protocol: "tcp".to_string(),
+                    state: state.to_string(),
+                    service_name: None,
+                    product: None,
+                    version: None,
+                    extra_info: None,
+                    cpe: None,
+                })
+                .collect(),
+            sources: Vec::new(),
+            hostnames: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_merge_forms() {
+        let merged = merge_forms(vec![
+            form("10.0.0.1", Some("a.local"), &[(22, "open"), (80, "open")]),
+            form("10.0.0.2", None, &[]),
+            form("10.0.0.1", None, &[(80, "closed"), (443, "open")]),
+        ]);
+
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].hostname.as_deref(), Some("a.local"));
+        let ports: Vec<(i32, &str)> = merged[0]
+            .services
+            .iter()
+            .map(|service| (service.port, service.state.as_str()))
+            .collect();
+        assert_eq!(ports, vec![(22, "open"), (80, "closed"), (443, "open")]);
     }
 }
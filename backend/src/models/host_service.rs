@@ -0,0 +1,95 @@
+use diesel::prelude::*;
+use diesel::upsert::excluded;
+use serde::{Deserialize, Serialize};
+
+use crate::db::schema::host_services;
+use crate::dtos::handlers::ServiceForm;
+use crate::models::host::Host;
+
+#[derive(Queryable, Identifiable, Selectable, Associations, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[diesel(table_name = host_services)]
+#[diesel(primary_key(id))]
+#[diesel(belongs_to(Host))]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct HostService {
+    pub id: i32,
+    pub host_id: i32,
+    pub port: i32,
+    pub protocol: String,
+    pub state: String,
+    pub service_name: Option<String>,
+    pub product: Option<String>,
+    pub version: Option<String>,
+    pub extra_info: Option<String>,
+    pub cpe: Option<String>,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = host_services)]
+pub struct NewHostService {
+    host_id: i32,
+    port: i32,
+    protocol: String,
+    state: String,
+    service_name: Option<String>,
+    product: Option<String>,
+    version: Option<String>,
+    extra_info: Option<String>,
+    cpe: Option<String>,
+}
+
+impl HostService {
+    pub fn get_services_by_host_id(
+        conn: &mut PgConnection,
+        id_host: i32,
+    ) -> QueryResult<Vec<HostService>> {
+        use crate::db::schema::host_services::dsl::*;
+        host_services
+            .filter(host_id.eq(id_host))
+            .order((port.asc(), protocol.asc()))
+            .select(HostService::as_select())
+            .load(conn)
+    }
+
+    /// Inserts services for a host. A service that is already known for the same
+    /// port and protocol is overwritten with the fresh scan data.
+    pub fn create_services(
+        conn: &mut PgConnection,
+        forms: &[ServiceForm],
+        id_host: i32,
+    ) -> QueryResult<usize> {
+        use crate::db::schema::host_services::dsl::*;
+        if forms.is_empty() {
+            return Ok(0);
+        }
+
+        let new_services: Vec<NewHostService> = forms
+            .iter()
+            .map(|form| NewHostService {
+                host_id: id_host,
+                port: form.port,
+                protocol: form.protocol.clone(),
+                state: form.state.clone(),
+                service_name: form.service_name.clone(),
+                product: form.product.clone(),
+                version: form.version.clone(),
+                extra_info: form.extra_info.clone(),
+                cpe: form.cpe.clone(),
+            })
+            .collect();
+
+        diesel::insert_into(host_services)
+            .values(&new_services)
+            .on_conflict((host_id, port, protocol))
+            .do_update()
+            .set((
+                state.eq(excluded(state)),
+                service_name.eq(excluded(service_name)),
+                product.eq(excluded(product)),
+                version.eq(excluded(version)),
+                extra_info.eq(excluded(extra_info)),
+                cpe.eq(excluded(cpe)),
+            ))
+            .execute(conn)
+    }
+}
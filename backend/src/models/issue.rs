@@ -4,9 +4,11 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::db::schema::{issues, projects, *};
-use crate::dtos::handlers::{CreateIssueForm, IssueForm};
+use crate::dtos::handlers::{ClassificationForm, CreateIssueForm, IssueForm, ProofOfConceptForm};
 use crate::models::host::HostResponse;
+use crate::models::issue_endpoint::IssueEndpoint;
 use crate::models::project::Project;
+use crate::models::proof_of_concept::ProofOfConcept;
 
 #[derive(Queryable, Selectable, Serialize, Identifiable, Associations, PartialEq, Debug)]
 #[diesel(table_name = issues)]
@@ -20,6 +22,15 @@ pub struct Issue {
     pub mitigation: Option<String>,
     pub cvss: f64,
     pub project_id: Uuid, // TODO: remove
+    /// Identifies the scanner finding the issue was created from.
+    pub fingerprint: Option<String>,
+    /// Set once the issue is edited by hand, so reruns keep the edits.
+    pub manually_edited: bool,
+    pub cve_ids: Vec<String>,
+    pub cwe_ids: Vec<String>,
+    pub reference_urls: Vec<String>,
+    pub tags: Vec<String>,
+    pub cvss_vector: Option<String>,
 }
 
 #[derive(Insertable, Deserialize, AsChangeset, Debug)]
@@ -30,6 +41,12 @@ pub struct NewIssue {
     mitigation: Option<String>,
     cvss: f64,
     project_id: Uuid,
+    fingerprint: Option<String>,
+    cve_ids: Vec<String>,
+    cwe_ids: Vec<String>,
+    reference_urls: Vec<String>,
+    tags: Vec<String>,
+    cvss_vector: Option<String>,
 }
 
 #[derive(Serialize, Debug)]
@@ -39,7 +56,13 @@ pub struct IssueFullResponse {
     description: Option<String>,
     mitigation: Option<String>,
     cvss: f64,
+    cve_ids: Vec<String>,
+    cwe_ids: Vec<String>,
+    reference_urls: Vec<String>,
+    tags: Vec<String>,
+    cvss_vector: Option<String>,
     hosts: Vec<HostResponse>,
+    endpoints: Vec<IssueEndpoint>,
     // pocs: Vec<ProofOfConcept>,
 }
 
@@ -73,12 +96,21 @@ impl Issue {
             mitigation: None,
             cvss: 0.0,
             project_id: id_project,
+            fingerprint: None,
+            cve_ids: Vec::new(),
+            cwe_ids: Vec::new(),
+            reference_urls: Vec::new(),
+            tags: Vec::new(),
+            cvss_vector: None,
         };
         diesel::insert_into(issues::table)
             .values(new_issue)
             .get_result::<Issue>(conn)
     }
 
+    /// Saves scanner findings. Findings sharing a fingerprint become one issue
+    /// listing every affected host and endpoint, and a rerun updates the issue
+    /// it created before. Issues edited by hand keep their text and score.
     pub fn create_issues(
         conn: &mut PgConnection,
         forms: Vec<IssueForm>,
@@ -86,55 +118,121 @@ impl Issue {
     ) -> QueryResult<()> {
         debug!("Create issues with data {:?}", forms);
 
+        let mut groups: Vec<Vec<IssueForm>> = Vec::new();
+        for form in forms {
+            let position = form.fingerprint.as_ref().and_then(|fingerprint| {
+                groups
+                    .iter()
+                    .position(|group| group[0].fingerprint.as_ref() == Some(fingerprint))
+            });
+            match position {
+                Some(position) => groups[position].push(form),
+                None => groups.push(vec![form]),
+            }
+        }
+
         conn.transaction(|conn| {
-            let new_issues: Vec<NewIssue> = forms.iter()
-                .map(|form| NewIssue {
-                    name: form.name.clone(),
-                    description: form.description.clone(),
-                    mitigation: form.mitigation.clone(),
-                    cvss: form.cvss.unwrap_or(0.0),
-                    project_id: id_project,
-                })
-                .collect();
-
-            let created_issues = diesel::insert_into(issues::table)
-                .values(new_issues)
-                .get_results::<Issue>(conn)?;
-
-
-            for (issue, form) in created_issues.iter().zip(forms.iter()) {
-                if !form.hosts.is_empty() {
-                    let mut host_ids = Vec::new();
-
-                    for host in &form.hosts {
-                        let mut query = hosts::table.into_boxed()
-                            .filter(hosts::ip_address.eq(&host.ip_address));
-
-                        if let Some(hostname) = &host.hostname {
-                            query = query.filter(hosts::hostname.eq(hostname));
+            for group in &groups {
+                let form = &group[0];
+                let existing = match &form.fingerprint {
+                    Some(fingerprint) => issues::table
+                        .filter(issues::project_id.eq(id_project))
+                        .filter(issues::fingerprint.eq(fingerprint))
+                        .select(Issue::as_select())
+                        .first(conn)
+                        .optional()?,
+                    None => None,
+                };
+                let (cvss, classification) = merge_scores(group);
+                let classification = &classification;
+                let issue_id = match existing {
+                    Some(issue) => {
+                        // Classification can't be edited by hand and always follows the scanner.
+                        diesel::update(issues::table.find(issue.id))
+                            .set((
+                                issues::cve_ids.eq(&classification.cve_ids),
+                                issues::cwe_ids.eq(&classification.cwe_ids),
+                                issues::reference_urls.eq(&classification.reference_urls),
+                                issues::tags.eq(&classification.tags),
+                                issues::cvss_vector.eq(&classification.cvss_vector),
+                            ))
+                            .execute(conn)?;
+                        if !issue.manually_edited {
+                            diesel::update(issues::table.find(issue.id))
+                                .set((
+                                    issues::name.eq(&form.name),
+                                    issues::description.eq(&form.description),
+                                    issues::mitigation.eq(&form.mitigation),
+                                    issues::cvss.eq(cvss.unwrap_or(0.0)),
+                                ))
+                                .execute(conn)?;
                         }
-
-                        if let Ok(host_id) = query.select(hosts::id).first::<i32>(conn) {
-                            host_ids.push(host_id);
+                        issue.id
+                    },
+                    None => diesel::insert_into(issues::table)
+                        .values(NewIssue {
+                            name: form.name.clone(),
+                            description: form.description.clone(),
+                            mitigation: form.mitigation.clone(),
+                            cvss: cvss.unwrap_or(0.0),
+                            project_id: id_project,
+                            fingerprint: form.fingerprint.clone(),
+                            cve_ids: classification.cve_ids.clone(),
+                            cwe_ids: classification.cwe_ids.clone(),
+                            reference_urls: classification.reference_urls.clone(),
+                            tags: classification.tags.clone(),
+                            cvss_vector: classification.cvss_vector.clone(),
+                        })
+                        .returning(issues::id)
+                        .get_result::<Uuid>(conn)?,
+                };
+
+                let mut host_ids: Vec<i32> = Vec::new();
+                let mut endpoints: Vec<(Option<i32>, String)> = Vec::new();
+                let mut pocs: Vec<ProofOfConceptForm> = Vec::new();
+                for finding in group {
+                    pocs.extend(finding.evidence.iter().map(|evidence| ProofOfConceptForm {
+                        description: evidence.description.clone(),
+                        data: evidence.content.clone().into_bytes(),
+                        content_type: "text/plain; charset=utf-8".to_string(),
+                        host: evidence.host.clone(),
+                    }));
+                    let mut finding_host_ids = Vec::new();
+                    for host in &finding.hosts {
+                        // IP addresses are unique within a project.
+                        let host_id = hosts::table
+                            .filter(hosts::project_id.eq(id_project))
+                            .filter(hosts::ip_address.eq(&host.ip_address))
+                            .select(hosts::id)
+                            .first::<i32>(conn)
+                            .optional()?;
+                        finding_host_ids.extend(host_id);
+                    }
+                    if let Some(location) = &finding.matched_at {
+                        if !endpoints.iter().any(|(_, known)| known == location) {
+                            endpoints.push((finding_host_ids.first().copied(), location.clone()));
                         }
                     }
-
-                    if !host_ids.is_empty() {
-                        let new_relations: Vec<_> = host_ids.iter()
-                            .map(|&host_id| (issue.id, host_id))
-                            .collect();
-
-                        diesel::insert_into(issue_hosts::table)
-                            .values(&new_relations.iter()
-                                .map(|&(issue_id, host_id)| (
-                                    issue_hosts::issue_id.eq(issue_id),
-                                    issue_hosts::host_id.eq(host_id)
-                                ))
-                                .collect::<Vec<_>>()
-                            )
-                            .execute(conn)?;
+                    for host_id in finding_host_ids {
+                        if !host_ids.contains(&host_id) {
+                            host_ids.push(host_id);
+                        }
                     }
                 }
+
+                diesel::insert_into(issue_hosts::table)
+                    .values(
+                        host_ids
+                            .iter()
+                            .map(|&host_id| {
+                                (issue_hosts::issue_id.eq(issue_id), issue_hosts::host_id.eq(host_id))
+                            })
+                            .collect::<Vec<_>>(),
+                    )
+                    .on_conflict_do_nothing()
+                    .execute(conn)?;
+                IssueEndpoint::create_endpoints(conn, issue_id, &endpoints)?;
+                ProofOfConcept::create_missing_pocs(conn, &pocs, issue_id)?;
             }
 
             Ok(())
@@ -156,6 +254,7 @@ impl Issue {
                     issues::description.eq(&form.description),
                     issues::mitigation.eq(&form.mitigation),
                     issues::cvss.eq(form.cvss.unwrap_or(0.0)),
+                    issues::manually_edited.eq(true),
                 ))
                 .execute(conn)?;
 
@@ -168,7 +267,9 @@ impl Issue {
                 for host in &form.hosts {
                     let mut query = hosts::table.into_boxed();
 
-                    query = query.filter(hosts::ip_address.eq(&host.ip_address));
+                    query = query
+                        .filter(hosts::project_id.eq(id_project))
+                        .filter(hosts::ip_address.eq(&host.ip_address));
 
                     if let Some(hostname) = &host.hostname {
                         query = query.filter(hosts::hostname.eq(hostname));
@@ -213,6 +314,10 @@ impl Issue {
                 issue_hosts::table
                     .filter(issue_hosts::issue_id.eq(issue_id)))
                     .execute(conn)?;
+            diesel::delete(
+                proof_of_concepts::table
+                    .filter(proof_of_concepts::issue_id.eq(issue_id)))
+                    .execute(conn)?;
             diesel::delete(issues.filter(id.eq(issue_id))).execute(conn)
         })
     }
@@ -260,8 +365,74 @@ impl Issue {
             description: self.description.clone(),
             mitigation: self.mitigation.clone(),
             cvss: self.cvss.clone(),
+            cve_ids: self.cve_ids.clone(),
+            cwe_ids: self.cwe_ids.clone(),
+            reference_urls: self.reference_urls.clone(),
+            tags: self.tags.clone(),
+            cvss_vector: self.cvss_vector.clone(),
             hosts: related_hosts,
+            endpoints: IssueEndpoint::get_by_issue(conn, self.id)?,
         })
     }
+}
+
+/// Findings of one fingerprint may differ per endpoint, e.g. testssl.sh rates
+/// the same check by what each service offers. The issue gets the worst
+/// score and every id reported, and keeps the CVSS vector of the first one.
+fn merge_scores(group: &[IssueForm]) -> (Option<f64>, ClassificationForm) {
+    let cvss = group.iter().filter_map(|form| form.cvss).reduce(f64::max);
+    let mut classification = group[0].classification.clone();
+    for other in &group[1..] {
+        for (ids, others) in [
+            (&mut classification.cve_ids, &other.classification.cve_ids),
+            (&mut classification.cwe_ids, &other.classification.cwe_ids),
+            (&mut classification.reference_urls, &other.classification.reference_urls),
+            (&mut classification.tags, &other.classification.tags),
+        ] {
+            for id in others {
+                if !ids.contains(id) {
+                    ids.push(id.clone());
+                }
+            }
+        }
+    }
+    (cvss, classification)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn finding(cvss: Option<f64>, cve_ids: &[&str]) -> IssueForm {
+        IssueForm {
+            name: "Outdated SSL/TLS protocol versions supported".to_string(),
+            description: None,
+            mitigation: None,
+            cvss,
+            hosts: Vec::new(),
+            template_id: Some("testssl:protocols".to_string()),
+            matched_at: None,
+            fingerprint: Some("testssl:protocols".to_string()),
+            evidence: Vec::new(),
+            classification: ClassificationForm {
+                cve_ids: cve_ids.iter().map(|id| id.to_string()).collect(),
+                tags: vec!["tls".to_string()],
+                ..Default::default()
+            },
+        }
+    }
 
+    #[test]
+    fn test_merge_scores() {
+        let (cvss, classification) = merge_scores(&[
+            finding(Some(3.1), &[]),
+            finding(Some(5.3), &["CVE-2014-3566"]),
+            finding(None, &["CVE-2014-3566", "CVE-2011-3389"]),
+        ]);
+        assert_eq!(cvss, Some(5.3));
+        assert_eq!(classification.cve_ids, vec!["CVE-2014-3566", "CVE-2011-3389"]);
+        assert_eq!(classification.tags, vec!["tls"]);
+
+        assert_eq!(merge_scores(&[finding(None, &[])]).0, None);
+    }
 }
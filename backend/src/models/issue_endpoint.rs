@@ -0,0 +1,71 @@
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use diesel::upsert::excluded;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::db::schema::issue_endpoints;
+use crate::models::issue::Issue;
+
+/// A location a scanner matched an issue at, e.g. a URL nuclei reported.
+#[derive(Queryable, Selectable, Identifiable, Associations, Serialize, Debug, Clone, PartialEq)]
+#[diesel(table_name = issue_endpoints)]
+#[diesel(belongs_to(Issue))]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct IssueEndpoint {
+    pub id: i32,
+    pub issue_id: Uuid,
+    pub host_id: Option<i32>,
+    pub matched_at: String,
+    pub first_seen_at: NaiveDateTime,
+    pub last_seen_at: NaiveDateTime,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = issue_endpoints)]
+pub struct NewIssueEndpoint {
+    pub issue_id: Uuid,
+    pub host_id: Option<i32>,
+    pub matched_at: String,
+    pub last_seen_at: NaiveDateTime,
+}
+
+impl IssueEndpoint {
+    pub fn get_by_issue(conn: &mut PgConnection, id_issue: Uuid) -> QueryResult<Vec<IssueEndpoint>> {
+        use crate::db::schema::issue_endpoints::dsl::*;
+        issue_endpoints
+            .filter(issue_id.eq(id_issue))
+            .order(matched_at.asc())
+            .select(IssueEndpoint::as_select())
+            .load(conn)
+    }
+
+    /// Records endpoints of an issue. Endpoints seen before keep their first
+    /// sighting and get the current time as the last one.
+    pub fn create_endpoints(
+        conn: &mut PgConnection,
+        id_issue: Uuid,
+        endpoints: &[(Option<i32>, String)],
+    ) -> QueryResult<usize> {
+        use crate::db::schema::issue_endpoints::dsl::*;
+        if endpoints.is_empty() {
+            return Ok(0);
+        }
+        let now = Utc::now().naive_utc();
+        let new_endpoints: Vec<NewIssueEndpoint> = endpoints
+            .iter()
+            .map(|(id_host, location)| NewIssueEndpoint {
+                issue_id: id_issue,
+                host_id: *id_host,
+                matched_at: location.clone(),
+                last_seen_at: now,
+            })
+            .collect();
+        diesel::insert_into(issue_endpoints)
+            .values(&new_endpoints)
+            .on_conflict((issue_id, matched_at))
+            .do_update()
+            .set((host_id.eq(excluded(host_id)), last_seen_at.eq(excluded(last_seen_at))))
+            .execute(conn)
+    }
+}
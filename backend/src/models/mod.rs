@@ -1,5 +1,7 @@
 pub mod host;
+pub mod host_service;
 pub mod issue;
+pub mod issue_endpoint;
 pub mod m_to_m_tables;
 pub mod project;
 pub mod proof_of_concept;
@@ -7,5 +9,12 @@ pub mod report;
 pub mod report_template;
 pub mod team;
 pub mod user;
+pub mod web_endpoint;
+pub mod web_service;
 
 pub mod scan;
+pub mod scan_artifact;
+pub mod scan_profile;
+pub mod scan_schedule;
+pub mod scope_audit;
+pub mod scope_entry;
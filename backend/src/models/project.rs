@@ -1,4 +1,4 @@
-use chrono::NaiveDate;
+use chrono::{NaiveDate, NaiveDateTime};
 use diesel::associations::HasTable;
 use diesel::prelude::*;
 use log::debug;
@@ -6,8 +6,11 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::dtos::handlers::ProjectForm;
-use crate::models::host::{Host, HostResponse};
+use crate::models::host::{Host, HostFullResponse};
 use crate::models::issue::{Issue, IssueFullResponse};
+use crate::models::scope_entry::{NewScopeEntry, ScopeEntry};
+use crate::services::scanner::diff::ScanDiff;
+use crate::services::scope::split_text;
 
 #[derive(Queryable, Selectable, Serialize, Identifiable, Deserialize, Debug)]
 #[diesel(table_name = crate::db::schema::projects)]
@@ -18,7 +21,6 @@ pub struct Project {
     pub id: Uuid,
     pub name: String,
     pub description: Option<String>,
-    pub scope: Option<String>,
     pub start_date: NaiveDate,
     pub end_date: NaiveDate,
     pub folder: String,
@@ -30,7 +32,6 @@ pub struct Project {
 struct NewProject {
     name: String,
     description: Option<String>,
-    scope: Option<String>,
     start_date: Option<NaiveDate>,
     end_date: Option<NaiveDate>,
     folder: String,
@@ -42,20 +43,21 @@ pub struct ProjectFullResponse {
     id: Uuid,
     name: String,
     description: String,
-    scope: String,
+    scope_entries: Vec<ScopeEntry>,
     start_date: NaiveDate,
     end_date: NaiveDate,
     folder: String,
     team_id: Uuid,
     issues: Vec<IssueFullResponse>,
-    hosts: Vec<HostResponse>,
+    hosts: Vec<HostFullResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scan_diff: Option<ScanDiff>,
 }
 
 #[derive(Serialize)]
 pub struct ProjectOverviewResponse {
     pub id: Uuid,
     pub name: String,
-    pub scope: Option<String>,
 }
 
 impl Project {
@@ -75,6 +77,22 @@ impl Project {
         }
     }
 
+    pub fn find_by_id(conn: &mut PgConnection, project_id: Uuid) -> QueryResult<Option<Project>> {
+        use crate::db::schema::projects::dsl::*;
+        projects
+            .filter(id.eq(project_id))
+            .select(Project::as_select())
+            .first(conn)
+            .optional()
+    }
+
+    /// Whether `at` falls on a day between the start and end dates, inclusive.
+    /// The dates carry no timezone and are read as UTC days, so `at` must be
+    /// UTC too and the window opens and closes at midnight UTC.
+    pub fn in_engagement_window(&self, at: NaiveDateTime) -> bool {
+        (self.start_date..=self.end_date).contains(&at.date())
+    }
+
     pub fn get_projects(conn: &mut PgConnection) -> QueryResult<Vec<ProjectOverviewResponse>> {
         use crate::db::schema::projects::dsl::*;
         let all_projects = projects::table().load::<Project>(conn)?;
@@ -83,7 +101,6 @@ impl Project {
             result.push(ProjectOverviewResponse {
                 id: project.id,
                 name: project.name.clone(),
-                scope: project.scope,
             });
         }
         Ok(result)
@@ -96,16 +113,30 @@ impl Project {
         let new_project = NewProject {
             name: form.name.clone(),
             description: form.description.clone(),
-            scope: form.scope.clone(),
             start_date: form.start_date,
             end_date: form.end_date,
             folder: form.folder.clone(),
             team_id: form.team_id,
         };
-        diesel::insert_into(projects)
-            .values(new_project)
-            .get_result::<Project>(conn) // TODO: change this method to add work
-                                         // with team id
+        conn.transaction(|conn| {
+            let project = diesel::insert_into(projects)
+                .values(new_project)
+                .get_result::<Project>(conn)?; // TODO: change this method to add work
+                                               // with team id
+            if let Some(scope) = &form.scope {
+                let entries = split_text(scope)
+                    .into_iter()
+                    .map(|(kind, value)| NewScopeEntry {
+                        project_id: project.id,
+                        kind: kind.into(),
+                        value: value.to_string(),
+                        note: None,
+                    })
+                    .collect();
+                ScopeEntry::create_entries(conn, entries)?;
+            }
+            Ok(project)
+        })
     }
 
     pub fn update_project(
@@ -117,7 +148,6 @@ impl Project {
         let new_project = NewProject {
             name: form.name.clone(),
             description: form.description.clone(),
-            scope: form.scope.clone(),
             start_date: form.start_date,
             end_date: form.end_date,
             folder: form.folder.clone(),
@@ -149,13 +179,14 @@ impl Project {
             id: self.id,
             name: self.name.clone(),
             description: self.description.clone().unwrap_or_else(|| String::new()), /* TODO: maybe change after to Option */
-            scope: self.scope.clone().unwrap_or_else(|| String::new()),
+            scope_entries: ScopeEntry::get_by_project(conn, self.id)?,
             start_date: self.start_date,
             end_date: self.end_date,
             folder: self.folder.clone(),
             team_id: self.team_id,
             issues: full_issues,
             hosts: Host::get_hosts_by_project_id(conn, self.id)?,
+            scan_diff: None,
         })
     }
 }
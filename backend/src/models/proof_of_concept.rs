@@ -88,6 +88,45 @@ impl ProofOfConcept {
             .get_result(conn)
     }
 
+    /// Creates the proofs of concept an issue does not have yet. A proof with
+    /// the same description and host is considered the same evidence.
+    pub fn create_missing_pocs(
+        conn: &mut PgConnection,
+        forms: &[ProofOfConceptForm],
+        id_issue: Uuid,
+    ) -> QueryResult<usize> {
+        use crate::db::schema::proof_of_concepts::dsl::*;
+        if forms.is_empty() {
+            return Ok(0);
+        }
+        let existing = proof_of_concepts
+            .filter(issue_id.eq(id_issue))
+            .select((description, host))
+            .load::<(String, String)>(conn)?;
+
+        let mut new_pocs: Vec<NewProofOfConcept> = Vec::new();
+        for form in forms {
+            let known = existing.iter().any(|(known_description, known_host)| {
+                *known_description == form.description && *known_host == form.host
+            });
+            let queued = new_pocs
+                .iter()
+                .any(|poc| poc.description == form.description && poc.host == form.host);
+            if !known && !queued {
+                new_pocs.push(NewProofOfConcept {
+                    description: form.description.clone(),
+                    data: form.data.clone(),
+                    issue_id: id_issue,
+                    content_type: form.content_type.clone(),
+                    host: form.host.clone(),
+                });
+            }
+        }
+        diesel::insert_into(proof_of_concepts)
+            .values(&new_pocs)
+            .execute(conn)
+    }
+
     pub fn get_poc_data(conn: &mut PgConnection, poc_id: i32) -> QueryResult<PocData> {
         use crate::db::schema::proof_of_concepts::dsl::*;
         let (poc_data, poc_content_type) = proof_of_concepts
@@ -1,9 +1,12 @@
+use chrono::{NaiveDate, NaiveDateTime, Utc};
 use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use uuid::Uuid;
 
-use crate::db::schema::scans;
+use crate::db::schema::{projects, scans};
 use crate::models::project::Project;
+use crate::services::scanner::types::ScanStatus;
 
 #[derive(Debug, Clone, Serialize, Deserialize, Queryable, Identifiable, Associations, Selectable)]
 #[diesel(belongs_to(Project))]
@@ -14,7 +17,15 @@ pub struct Scan {
     pub scanner_type: String,
     pub status: String,
     pub result_path: Option<String>,
-    pub target: String
+    pub target: String,
+    pub started_at: Option<NaiveDateTime>,
+    pub finished_at: Option<NaiveDateTime>,
+    pub error: Option<String>,
+    pub profile_id: Option<i32>,
+    pub options: Value,
+    pub priority: i32,
+    pub queued_at: NaiveDateTime,
+    pub schedule_id: Option<i32>,
 }
 
 #[derive(Debug, Insertable)]
@@ -24,14 +35,21 @@ pub struct NewScan {
     pub scanner_type: String,
     pub status: String,
     pub result_path: Option<String>,
-    pub target: String
+    pub target: String,
+    pub profile_id: Option<i32>,
+    pub options: Value,
+    pub priority: i32,
+    pub schedule_id: Option<i32>,
 }
 
-#[derive(Debug, AsChangeset)]
+#[derive(Debug, AsChangeset, Default)]
 #[diesel(table_name = scans)]
 pub struct UpdateScan {
     pub status: String,
     pub result_path: Option<String>,
+    pub started_at: Option<NaiveDateTime>,
+    pub finished_at: Option<NaiveDateTime>,
+    pub error: Option<String>,
 }
 
 impl Scan {
@@ -61,6 +79,129 @@ impl Scan {
             .get_result::<Scan>(conn)
     }
 
+    /// Takes the next queued scan for the scanner, highest priority first and
+    /// oldest first within a priority, and marks it running. Rows locked by
+    /// another worker are skipped.
+    pub fn claim_next(conn: &mut PgConnection, scanner: &str) -> QueryResult<Option<Scan>> {
+        use crate::db::schema::scans::dsl::*;
+        conn.transaction(|conn| {
+            let next_id = scans
+                .filter(status.eq(String::from(ScanStatus::Queued)))
+                .filter(scanner_type.eq(scanner))
+                .order((priority.desc(), queued_at.asc()))
+                .select(id)
+                .for_update()
+                .skip_locked()
+                .first::<Uuid>(conn)
+                .optional()?;
+            match next_id {
+                Some(next_id) => Self::mark_running(conn, next_id).map(Some),
+                None => Ok(None),
+            }
+        })
+    }
+
+    /// Puts scans that were running when the backend stopped back in the queue.
+    pub fn requeue_interrupted(conn: &mut PgConnection, scanners: &[&str]) -> QueryResult<usize> {
+        use crate::db::schema::scans::dsl::*;
+        diesel::update(
+            scans
+                .filter(status.eq(String::from(ScanStatus::Running)))
+                .filter(scanner_type.eq_any(scanners)),
+        )
+        .set((status.eq(String::from(ScanStatus::Queued)), started_at.eq(None::<NaiveDateTime>)))
+        .execute(conn)
+    }
+
+    pub fn mark_running(conn: &mut PgConnection, scan_id: Uuid) -> QueryResult<Scan> {
+        Self::update_scan(conn, scan_id, UpdateScan {
+            status: ScanStatus::Running.into(),
+            started_at: Some(Utc::now().naive_utc()),
+            ..Default::default()
+        })
+    }
+
+    /// Applies the final state of a scan that is still in one of `from`, so
+    /// a scan cancelled while its process was exiting stays cancelled.
+    fn finish(
+        conn: &mut PgConnection,
+        scan_id: Uuid,
+        from: Vec<ScanStatus>,
+        scan: UpdateScan,
+    ) -> QueryResult<Option<Scan>> {
+        use crate::db::schema::scans::dsl::*;
+        let from: Vec<String> = from.into_iter().map(String::from).collect();
+        diesel::update(scans.find(scan_id).filter(status.eq_any(from)))
+            .set(scan)
+            .get_result::<Scan>(conn)
+            .optional()
+    }
+
+    pub fn mark_completed(
+        conn: &mut PgConnection,
+        scan_id: Uuid,
+        output_file: String,
+    ) -> QueryResult<Option<Scan>> {
+        Self::finish(conn, scan_id, vec![ScanStatus::Running], UpdateScan {
+            status: ScanStatus::Completed.into(),
+            result_path: Some(output_file),
+            finished_at: Some(Utc::now().naive_utc()),
+            ..Default::default()
+        })
+    }
+
+    pub fn mark_failed(
+        conn: &mut PgConnection,
+        scan_id: Uuid,
+        reason: String,
+    ) -> QueryResult<Option<Scan>> {
+        Self::finish(conn, scan_id, vec![ScanStatus::Running], UpdateScan {
+            status: ScanStatus::Failed.into(),
+            finished_at: Some(Utc::now().naive_utc()),
+            error: Some(reason),
+            ..Default::default()
+        })
+    }
+
+    /// Cancels a queued or running scan. The queue cancels a scan the user
+    /// cancelled once more when its process has exited, to record the
+    /// partial output.
+    pub fn mark_cancelled(
+        conn: &mut PgConnection,
+        scan_id: Uuid,
+        output_file: Option<String>,
+    ) -> QueryResult<Option<Scan>> {
+        let from = vec![ScanStatus::Queued, ScanStatus::Running, ScanStatus::Cancelled];
+        Self::finish(conn, scan_id, from, UpdateScan {
+            status: ScanStatus::Cancelled.into(),
+            result_path: output_file,
+            finished_at: Some(Utc::now().naive_utc()),
+            ..Default::default()
+        })
+    }
+
+    /// Running scans of projects whose engagement window does not include
+    /// `today`, a UTC date like the window bounds.
+    pub fn find_running_outside_window(conn: &mut PgConnection, today: NaiveDate) -> QueryResult<Vec<Uuid>> {
+        scans::table
+            .inner_join(projects::table)
+            .filter(scans::status.eq(String::from(ScanStatus::Running)))
+            .filter(projects::end_date.lt(today).or(projects::start_date.gt(today)))
+            .select(scans::id)
+            .load(conn)
+    }
+
+    /// Cancels a running scan whose engagement window closed, leaving the
+    /// reason in its error.
+    pub fn mark_outside_window(conn: &mut PgConnection, scan_id: Uuid) -> QueryResult<Option<Scan>> {
+        Self::finish(conn, scan_id, vec![ScanStatus::Running], UpdateScan {
+            status: ScanStatus::Cancelled.into(),
+            finished_at: Some(Utc::now().naive_utc()),
+            error: Some("The engagement window of the project closed".to_string()),
+            ..Default::default()
+        })
+    }
+
     pub fn delete_scan(conn: &mut PgConnection, scan_id: Uuid) -> QueryResult<usize> {
         use crate::db::schema::scans::dsl::*;
         diesel::delete(scans.filter(id.eq(scan_id))).execute(conn)
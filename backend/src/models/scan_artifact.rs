@@ -0,0 +1,137 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use log::error;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::db::schema::scan_artifacts;
+use crate::models::scan::Scan;
+use crate::services::scanner::process::{COMMAND_FILE, STDERR_LOG};
+
+/// A file a scan left in its directory.
+#[derive(Debug, Queryable, Selectable, Identifiable, Associations, Serialize, Clone, PartialEq)]
+#[diesel(table_name = scan_artifacts)]
+#[diesel(belongs_to(Scan))]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ScanArtifact {
+    pub id: i32,
+    pub scan_id: Uuid,
+    /// "output", "stderr", "command" or "other", e.g. the target list.
+    pub kind: String,
+    pub file_name: String,
+    #[serde(skip_serializing)]
+    pub path: String,
+    pub size: i64,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = scan_artifacts)]
+pub struct NewScanArtifact {
+    pub scan_id: Uuid,
+    pub kind: String,
+    pub file_name: String,
+    pub path: String,
+    pub size: i64,
+}
+
+/// Scanners name their raw output `scan.<format>`, runs of scanner binaries
+/// also leave their command line and stderr.
+pub fn artifact_kind(file_name: &str) -> &'static str {
+    match file_name {
+        COMMAND_FILE => "command",
+        STDERR_LOG => "stderr",
+        name if name.starts_with("scan.") => "output",
+        _ => "other",
+    }
+}
+
+/// Files directly in the scan directory, which may not exist if the scan
+/// failed before writing anything.
+fn list_files(id_scan: Uuid, scan_dir: &Path) -> io::Result<Vec<NewScanArtifact>> {
+    let entries = match fs::read_dir(scan_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut artifacts = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if !metadata.is_file() {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().to_string();
+        artifacts.push(NewScanArtifact {
+            scan_id: id_scan,
+            kind: artifact_kind(&name).to_string(),
+            file_name: name,
+            path: entry.path().display().to_string(),
+            size: metadata.len() as i64,
+        });
+    }
+    artifacts.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+    Ok(artifacts)
+}
+
+impl ScanArtifact {
+    pub fn get_by_scan(conn: &mut PgConnection, id_scan: Uuid) -> QueryResult<Vec<ScanArtifact>> {
+        use crate::db::schema::scan_artifacts::dsl::*;
+        scan_artifacts
+            .filter(scan_id.eq(id_scan))
+            .order(file_name.asc())
+            .select(ScanArtifact::as_select())
+            .load(conn)
+    }
+
+    pub fn find_by_id(
+        conn: &mut PgConnection,
+        id_scan: Uuid,
+        artifact_id: i32,
+    ) -> QueryResult<Option<ScanArtifact>> {
+        use crate::db::schema::scan_artifacts::dsl::*;
+        scan_artifacts
+            .filter(scan_id.eq(id_scan))
+            .filter(id.eq(artifact_id))
+            .select(ScanArtifact::as_select())
+            .first(conn)
+            .optional()
+    }
+
+    /// Replaces the artifacts of the scan with the files now in its
+    /// directory. A directory that can't be read leaves the records as they
+    /// are.
+    pub fn record(conn: &mut PgConnection, id_scan: Uuid, scan_dir: &Path) -> QueryResult<usize> {
+        use crate::db::schema::scan_artifacts::dsl::*;
+        let artifacts = match list_files(id_scan, scan_dir) {
+            Ok(artifacts) => artifacts,
+            Err(e) => {
+                error!("Failed to list artifacts of scan {}: {}", id_scan, e);
+                return Ok(0);
+            },
+        };
+        conn.transaction(|conn| {
+            diesel::delete(scan_artifacts.filter(scan_id.eq(id_scan))).execute(conn)?;
+            diesel::insert_into(scan_artifacts).values(&artifacts).execute(conn)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_artifact_kind() {
+        assert_eq!(artifact_kind("scan.xml"), "output");
+        assert_eq!(artifact_kind("scan.jsonl"), "output");
+        assert_eq!(artifact_kind("command.txt"), "command");
+        assert_eq!(artifact_kind("stderr.log"), "stderr");
+        assert_eq!(artifact_kind("targets.txt"), "other");
+        assert_eq!(artifact_kind("resolved.json"), "other");
+    }
+}
@@ -0,0 +1,63 @@
+use diesel::prelude::*;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::db::schema::scan_profiles;
+
+#[derive(Debug, Clone, Queryable, Selectable, Identifiable, Serialize)]
+#[diesel(table_name = scan_profiles)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ScanProfile {
+    pub id: i32,
+    pub name: String,
+    pub scanner_type: String,
+    pub options: Value,
+}
+
+#[derive(Debug, Insertable, AsChangeset)]
+#[diesel(table_name = scan_profiles)]
+pub struct NewScanProfile {
+    pub name: String,
+    pub scanner_type: String,
+    pub options: Value,
+}
+
+impl ScanProfile {
+    pub fn get_profiles(conn: &mut PgConnection) -> QueryResult<Vec<ScanProfile>> {
+        use crate::db::schema::scan_profiles::dsl::*;
+        scan_profiles.order(name.asc()).load::<ScanProfile>(conn)
+    }
+
+    pub fn find_by_id(conn: &mut PgConnection, profile_id: i32) -> QueryResult<Option<ScanProfile>> {
+        use crate::db::schema::scan_profiles::dsl::*;
+        scan_profiles.find(profile_id).first(conn).optional()
+    }
+
+    pub fn create_profile(conn: &mut PgConnection, profile: NewScanProfile) -> QueryResult<ScanProfile> {
+        use crate::db::schema::scan_profiles::dsl::*;
+        diesel::insert_into(scan_profiles)
+            .values(profile)
+            .get_result::<ScanProfile>(conn)
+    }
+
+    pub fn update_profile(
+        conn: &mut PgConnection,
+        profile_id: i32,
+        profile: NewScanProfile,
+    ) -> QueryResult<Option<ScanProfile>> {
+        use crate::db::schema::scan_profiles::dsl::*;
+        diesel::update(scan_profiles.find(profile_id))
+            .set(profile)
+            .get_result::<ScanProfile>(conn)
+            .optional()
+    }
+
+    pub fn delete_profile(conn: &mut PgConnection, profile_id: i32) -> QueryResult<usize> {
+        use crate::db::schema::scan_profiles::dsl::*;
+        diesel::delete(scan_profiles.find(profile_id)).execute(conn)
+    }
+
+    pub fn options_map(&self) -> Map<String, Value> {
+        self.options.as_object().cloned().unwrap_or_default()
+    }
+}
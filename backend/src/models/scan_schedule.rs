@@ -0,0 +1,124 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::Serialize;
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::db::schema::scan_schedules;
+
+/// A scan that is queued once at `run_at` or repeatedly following `cron`.
+#[derive(Debug, Clone, Queryable, Selectable, Identifiable, Serialize)]
+#[diesel(table_name = scan_schedules)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ScanSchedule {
+    pub id: i32,
+    pub project_id: Uuid,
+    pub scanner_type: String,
+    pub target: String,
+    pub profile_id: Option<i32>,
+    pub options: Value,
+    pub priority: i32,
+    pub run_at: Option<NaiveDateTime>,
+    pub cron: Option<String>,
+    /// `None` once a one-off schedule ran or the engagement window is over.
+    pub next_run_at: Option<NaiveDateTime>,
+    pub last_run_at: Option<NaiveDateTime>,
+    pub paused: bool,
+    pub created_by: Option<Uuid>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = scan_schedules)]
+pub struct NewScanSchedule {
+    pub project_id: Uuid,
+    pub scanner_type: String,
+    pub target: String,
+    pub profile_id: Option<i32>,
+    pub options: Value,
+    pub priority: i32,
+    pub run_at: Option<NaiveDateTime>,
+    pub cron: Option<String>,
+    pub next_run_at: Option<NaiveDateTime>,
+    pub created_by: Option<Uuid>,
+}
+
+impl ScanSchedule {
+    pub fn get_by_project(conn: &mut PgConnection, prjct_id: Uuid) -> QueryResult<Vec<ScanSchedule>> {
+        use crate::db::schema::scan_schedules::dsl::*;
+        scan_schedules
+            .filter(project_id.eq(prjct_id))
+            .order(id.asc())
+            .load::<ScanSchedule>(conn)
+    }
+
+    pub fn find_by_id(
+        conn: &mut PgConnection,
+        prjct_id: Uuid,
+        schedule_id: i32,
+    ) -> QueryResult<Option<ScanSchedule>> {
+        use crate::db::schema::scan_schedules::dsl::*;
+        scan_schedules
+            .find(schedule_id)
+            .filter(project_id.eq(prjct_id))
+            .first(conn)
+            .optional()
+    }
+
+    pub fn create_schedule(
+        conn: &mut PgConnection,
+        schedule: NewScanSchedule,
+    ) -> QueryResult<ScanSchedule> {
+        use crate::db::schema::scan_schedules::dsl::*;
+        diesel::insert_into(scan_schedules)
+            .values(schedule)
+            .get_result::<ScanSchedule>(conn)
+    }
+
+    pub fn set_paused(
+        conn: &mut PgConnection,
+        schedule_id: i32,
+        is_paused: bool,
+        next_run: Option<NaiveDateTime>,
+    ) -> QueryResult<ScanSchedule> {
+        use crate::db::schema::scan_schedules::dsl::*;
+        diesel::update(scan_schedules.find(schedule_id))
+            .set((paused.eq(is_paused), next_run_at.eq(next_run)))
+            .get_result::<ScanSchedule>(conn)
+    }
+
+    pub fn delete_schedule(
+        conn: &mut PgConnection,
+        prjct_id: Uuid,
+        schedule_id: i32,
+    ) -> QueryResult<usize> {
+        use crate::db::schema::scan_schedules::dsl::*;
+        diesel::delete(scan_schedules.find(schedule_id).filter(project_id.eq(prjct_id)))
+            .execute(conn)
+    }
+
+    /// Locks the active schedules whose next run is due. Rows locked by
+    /// another scheduler are skipped.
+    pub fn lock_due(conn: &mut PgConnection, now: NaiveDateTime) -> QueryResult<Vec<ScanSchedule>> {
+        use crate::db::schema::scan_schedules::dsl::*;
+        scan_schedules
+            .filter(paused.eq(false))
+            .filter(next_run_at.le(now))
+            .order(next_run_at.asc())
+            .for_update()
+            .skip_locked()
+            .load::<ScanSchedule>(conn)
+    }
+
+    pub fn mark_run(
+        conn: &mut PgConnection,
+        schedule_id: i32,
+        ran_at: NaiveDateTime,
+        next_run: Option<NaiveDateTime>,
+    ) -> QueryResult<usize> {
+        use crate::db::schema::scan_schedules::dsl::*;
+        diesel::update(scan_schedules.find(schedule_id))
+            .set((last_run_at.eq(ran_at), next_run_at.eq(next_run)))
+            .execute(conn)
+    }
+}
@@ -0,0 +1,54 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::db::schema::scope_audit_log;
+
+/// A scan target that was outside the project scope, either rejected or
+/// launched anyway by an admin.
+#[derive(Debug, Queryable, Selectable, Identifiable, Serialize)]
+#[diesel(table_name = scope_audit_log)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ScopeAuditRecord {
+    pub id: i32,
+    pub project_id: Uuid,
+    pub user_id: Option<Uuid>,
+    pub scanner_type: String,
+    pub target: String,
+    pub overridden: bool,
+    pub justification: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = scope_audit_log)]
+pub struct NewScopeAuditRecord {
+    pub project_id: Uuid,
+    pub user_id: Option<Uuid>,
+    pub scanner_type: String,
+    pub target: String,
+    pub overridden: bool,
+    pub justification: Option<String>,
+}
+
+impl ScopeAuditRecord {
+    pub fn create_records(
+        conn: &mut PgConnection,
+        records: Vec<NewScopeAuditRecord>,
+    ) -> QueryResult<usize> {
+        use crate::db::schema::scope_audit_log::dsl::*;
+        diesel::insert_into(scope_audit_log).values(records).execute(conn)
+    }
+
+    pub fn get_by_project(
+        conn: &mut PgConnection,
+        prjct_id: Uuid,
+    ) -> QueryResult<Vec<ScopeAuditRecord>> {
+        use crate::db::schema::scope_audit_log::dsl::*;
+        scope_audit_log
+            .filter(project_id.eq(prjct_id))
+            .order(created_at.desc())
+            .load::<ScopeAuditRecord>(conn)
+    }
+}
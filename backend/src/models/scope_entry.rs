@@ -0,0 +1,74 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::db::schema::scope_entries;
+use crate::services::scope::{Scope, ScopeKind};
+
+#[derive(Debug, Clone, Queryable, Selectable, Identifiable, Serialize)]
+#[diesel(table_name = scope_entries)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ScopeEntry {
+    pub id: i32,
+    pub project_id: Uuid,
+    pub kind: String,
+    pub value: String,
+    pub note: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable, AsChangeset)]
+#[diesel(table_name = scope_entries)]
+#[diesel(treat_none_as_null = true)]
+pub struct NewScopeEntry {
+    pub project_id: Uuid,
+    pub kind: String,
+    pub value: String,
+    pub note: Option<String>,
+}
+
+impl ScopeEntry {
+    pub fn get_by_project(conn: &mut PgConnection, prjct_id: Uuid) -> QueryResult<Vec<ScopeEntry>> {
+        use crate::db::schema::scope_entries::dsl::*;
+        scope_entries
+            .filter(project_id.eq(prjct_id))
+            .order(id.asc())
+            .load::<ScopeEntry>(conn)
+    }
+
+    pub fn create_entries(
+        conn: &mut PgConnection,
+        entries: Vec<NewScopeEntry>,
+    ) -> QueryResult<Vec<ScopeEntry>> {
+        use crate::db::schema::scope_entries::dsl::*;
+        diesel::insert_into(scope_entries)
+            .values(entries)
+            .get_results::<ScopeEntry>(conn)
+    }
+
+    pub fn update_entry(
+        conn: &mut PgConnection,
+        entry_id: i32,
+        entry: NewScopeEntry,
+    ) -> QueryResult<Option<ScopeEntry>> {
+        use crate::db::schema::scope_entries::dsl::*;
+        diesel::update(scope_entries.find(entry_id).filter(project_id.eq(entry.project_id)))
+            .set(&entry)
+            .get_result::<ScopeEntry>(conn)
+            .optional()
+    }
+
+    pub fn delete_entry(conn: &mut PgConnection, prjct_id: Uuid, entry_id: i32) -> QueryResult<usize> {
+        use crate::db::schema::scope_entries::dsl::*;
+        diesel::delete(scope_entries.find(entry_id).filter(project_id.eq(prjct_id))).execute(conn)
+    }
+
+    /// Builds the scope of the project to check targets against.
+    pub fn get_scope(conn: &mut PgConnection, prjct_id: Uuid) -> QueryResult<Scope> {
+        let entries = Self::get_by_project(conn, prjct_id)?;
+        Ok(Scope::new(entries.iter().filter_map(|entry| {
+            ScopeKind::parse(&entry.kind).map(|kind| (kind, entry.value.as_str()))
+        })))
+    }
+}
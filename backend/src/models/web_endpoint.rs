@@ -0,0 +1,356 @@
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use diesel::upsert::excluded;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::db::schema::{hosts, issues, web_endpoints};
+use crate::dtos::handlers::{ClassificationForm, EvidenceForm, HostForm, IssueForm, WebEndpointForm};
+use crate::models::host::Host;
+use crate::models::issue::Issue;
+use crate::models::project::Project;
+use crate::services::scope::host_of;
+
+/// A path content discovery found on a web server.
+#[derive(Queryable, Selectable, Identifiable, Associations, Serialize, Debug, Clone, PartialEq)]
+#[diesel(table_name = web_endpoints)]
+#[diesel(belongs_to(Project))]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct WebEndpoint {
+    pub id: i32,
+    pub project_id: Uuid,
+    pub host_id: Option<i32>,
+    pub url: String,
+    pub status_code: i32,
+    pub content_length: i64,
+    pub content_type: Option<String>,
+    pub redirect_location: Option<String>,
+    /// Kind of sensitive resource the path looks like, e.g. "vcs" or "backup".
+    pub category: Option<String>,
+    /// Issue the endpoint was promoted to.
+    pub issue_id: Option<Uuid>,
+    pub first_seen_at: NaiveDateTime,
+    pub last_seen_at: NaiveDateTime,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = web_endpoints)]
+pub struct NewWebEndpoint {
+    pub project_id: Uuid,
+    pub host_id: Option<i32>,
+    pub url: String,
+    pub status_code: i32,
+    pub content_length: i64,
+    pub content_type: Option<String>,
+    pub redirect_location: Option<String>,
+    pub category: Option<String>,
+    pub last_seen_at: NaiveDateTime,
+}
+
+/// Kind of sensitive resource and the issue its endpoints are promoted to.
+struct EndpointCategory {
+    key: &'static str,
+    name: &'static str,
+    description: &'static str,
+    mitigation: &'static str,
+    cvss: f64,
+}
+
+const VCS: EndpointCategory = EndpointCategory {
+    key: "vcs",
+    name: "Version control repository exposed",
+    description: "The web server serves the metadata of a version control repository. It is \
+        usually enough to download the source code with its history, including secrets once \
+        committed.",
+    mitigation: "Remove the repository metadata from the web root or deny access to it.",
+    cvss: 7.5,
+};
+
+const CONFIG: EndpointCategory = EndpointCategory {
+    key: "config",
+    name: "Configuration file exposed",
+    description: "The web server serves a configuration file that may contain credentials, keys \
+        and details of the internal setup.",
+    mitigation: "Move configuration files out of the web root or deny access to them, then \
+        rotate the secrets they contain.",
+    cvss: 7.5,
+};
+
+const BACKUP: EndpointCategory = EndpointCategory {
+    key: "backup",
+    name: "Backup file exposed",
+    description: "The web server serves backups, archives or database dumps, which may contain \
+        source code, credentials or personal data.",
+    mitigation: "Remove backups from the web root and store them where the web server can't \
+        reach them.",
+    cvss: 7.5,
+};
+
+const DEBUG: EndpointCategory = EndpointCategory {
+    key: "debug",
+    name: "Debug or status page exposed",
+    description: "The web server exposes diagnostic pages that disclose versions, configuration, \
+        environment variables or live requests.",
+    mitigation: "Disable the diagnostic pages or restrict them to administrators.",
+    cvss: 5.3,
+};
+
+const ADMIN: EndpointCategory = EndpointCategory {
+    key: "admin",
+    name: "Administration interface exposed",
+    description: "An administration interface is reachable, which makes it a target for \
+        password guessing and attacks on the management software.",
+    mitigation: "Restrict the administration interface to trusted networks or put it behind \
+        additional authentication.",
+    cvss: 5.3,
+};
+
+const OTHER: EndpointCategory = EndpointCategory {
+    key: "other",
+    name: "Sensitive web resource exposed",
+    description: "Content discovery found a web resource that should not be public.",
+    mitigation: "Remove the resource from the web root or restrict access to it.",
+    cvss: 5.3,
+};
+
+const CATEGORIES: [&EndpointCategory; 5] = [&VCS, &CONFIG, &BACKUP, &DEBUG, &ADMIN];
+
+const VCS_DIRECTORIES: &[&str] = &[".git", ".svn", ".hg", ".bzr", "cvs"];
+const CONFIG_FILES: &[&str] = &[
+    ".env", ".htaccess", ".htpasswd", "web.config", "wp-config.php", "config.php", "config.json",
+    "config.yml", "config.yaml", "settings.py", ".ds_store", ".npmrc", ".dockerenv",
+];
+const BACKUP_EXTENSIONS: &[&str] = &[
+    ".bak", ".old", ".orig", ".backup", ".save", ".swp", "~", ".zip", ".tar", ".gz", ".tgz",
+    ".7z", ".rar", ".sql", ".dump",
+];
+const DEBUG_PATHS: &[&str] = &[
+    "phpinfo.php", "info.php", "server-status", "server-info", "actuator", "debug", "trace.axd",
+    "elmah.axd", "_profiler",
+];
+const ADMIN_PATHS: &[&str] = &[
+    "admin", "administrator", "admin.php", "wp-admin", "wp-login.php", "phpmyadmin",
+    "adminer.php", "manager", "console", "cpanel",
+];
+
+/// Tells which kind of sensitive resource a URL looks like, `None` for
+/// ordinary content.
+pub fn categorize(url: &str) -> Option<&'static str> {
+    let path = match url.split_once("://") {
+        Some((_, rest)) => rest.find('/').map_or("", |start| &rest[start..]),
+        None => url,
+    };
+    let path = path.split(['?', '#']).next().unwrap_or(path).to_lowercase();
+    let segments: Vec<&str> = path.split('/').filter(|segment| !segment.is_empty()).collect();
+    let last = segments.last().copied().unwrap_or("");
+
+    let category = if segments.iter().any(|segment| VCS_DIRECTORIES.contains(segment)) {
+        &VCS
+    } else if CONFIG_FILES.contains(&last) || last.starts_with(".env.") {
+        &CONFIG
+    } else if BACKUP_EXTENSIONS.iter().any(|extension| last.ends_with(extension)) {
+        &BACKUP
+    } else if segments.iter().any(|segment| DEBUG_PATHS.contains(segment)) {
+        &DEBUG
+    } else if segments.iter().any(|segment| ADMIN_PATHS.contains(segment)) {
+        &ADMIN
+    } else {
+        return None;
+    };
+    Some(category.key)
+}
+
+impl WebEndpoint {
+    pub fn get_by_project(
+        conn: &mut PgConnection,
+        id_project: Uuid,
+        id_host: Option<i32>,
+    ) -> QueryResult<Vec<WebEndpoint>> {
+        use crate::db::schema::web_endpoints::dsl::*;
+        let mut query = web_endpoints
+            .filter(project_id.eq(id_project))
+            .select(WebEndpoint::as_select())
+            .into_boxed();
+        if let Some(id_host) = id_host {
+            query = query.filter(host_id.eq(id_host));
+        }
+        query.order(url.asc()).load(conn)
+    }
+
+    pub fn find_by_id(
+        conn: &mut PgConnection,
+        id_project: Uuid,
+        endpoint_id: i32,
+    ) -> QueryResult<Option<WebEndpoint>> {
+        use crate::db::schema::web_endpoints::dsl::*;
+        web_endpoints
+            .filter(project_id.eq(id_project))
+            .filter(id.eq(endpoint_id))
+            .select(WebEndpoint::as_select())
+            .first(conn)
+            .optional()
+    }
+
+    /// Records endpoints found by a scan. Endpoints seen before keep their
+    /// first sighting and the issue they were promoted to, everything else
+    /// follows the latest scan.
+    pub fn create_endpoints(
+        conn: &mut PgConnection,
+        forms: &[WebEndpointForm],
+        id_project: Uuid,
+    ) -> QueryResult<usize> {
+        use crate::db::schema::web_endpoints::dsl::*;
+        let now = Utc::now().naive_utc();
+        let mut new_endpoints: Vec<NewWebEndpoint> = Vec::new();
+        for form in forms {
+            // Postgres rejects an upsert that hits the same row twice.
+            if new_endpoints.iter().any(|endpoint| endpoint.url == form.url) {
+                continue;
+            }
+            let id_host = hosts::table
+                .filter(hosts::project_id.eq(id_project))
+                .filter(hosts::ip_address.eq(&form.host).or(hosts::hostname.eq(&form.host)))
+                .order(hosts::id.asc())
+                .select(hosts::id)
+                .first::<i32>(conn)
+                .optional()?;
+            new_endpoints.push(NewWebEndpoint {
+                project_id: id_project,
+                host_id: id_host,
+                url: form.url.clone(),
+                status_code: form.status_code,
+                content_length: form.content_length,
+                content_type: form.content_type.clone(),
+                redirect_location: form.redirect_location.clone(),
+                category: categorize(&form.url).map(str::to_string),
+                last_seen_at: now,
+            });
+        }
+        if new_endpoints.is_empty() {
+            return Ok(0);
+        }
+        diesel::insert_into(web_endpoints)
+            .values(&new_endpoints)
+            .on_conflict((project_id, url))
+            .do_update()
+            .set((
+                host_id.eq(excluded(host_id)),
+                status_code.eq(excluded(status_code)),
+                content_length.eq(excluded(content_length)),
+                content_type.eq(excluded(content_type)),
+                redirect_location.eq(excluded(redirect_location)),
+                category.eq(excluded(category)),
+                last_seen_at.eq(excluded(last_seen_at)),
+            ))
+            .execute(conn)
+    }
+
+    /// Issue for the category of the endpoint. Endpoints of one category are
+    /// merged into one issue, each listed as an affected location.
+    fn to_issue_form(&self, host: Option<Host>) -> IssueForm {
+        let category = CATEGORIES
+            .into_iter()
+            .find(|category| self.category.as_deref() == Some(category.key))
+            .unwrap_or(&OTHER);
+
+        let mut response = format!("HTTP {}\nContent-Length: {}", self.status_code, self.content_length);
+        if let Some(content_type) = &self.content_type {
+            response.push_str(&format!("\nContent-Type: {}", content_type));
+        }
+        if let Some(location) = &self.redirect_location {
+            response.push_str(&format!("\nLocation: {}", location));
+        }
+        let evidence_host = match &host {
+            Some(host) => host.ip_address.clone(),
+            None => host_of(&self.url).to_string(),
+        };
+
+        IssueForm {
+            name: category.name.to_string(),
+            description: Some(category.description.to_string()),
+            mitigation: Some(category.mitigation.to_string()),
+            cvss: Some(category.cvss),
+            hosts: host
+                .into_iter()
+                .map(|host| HostForm {
+                    hostname: host.hostname,
+                    ip_address: host.ip_address,
+                    os: None,
+                    os_accuracy: None,
+                    services: Vec::new(),
+                    sources: Vec::new(),
+                    hostnames: Vec::new(),
+                })
+                .collect(),
+            template_id: None,
+            matched_at: Some(self.url.clone()),
+            fingerprint: Some(format!("web-endpoint:{}", category.key)),
+            evidence: vec![EvidenceForm {
+                description: format!("Response of {}", self.url),
+                content: response,
+                host: evidence_host,
+            }],
+            classification: ClassificationForm {
+                tags: vec!["content-discovery".to_string(), category.key.to_string()],
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Turns the endpoint into an issue, or adds it to the issue of its
+    /// category, and remembers the issue on the endpoint.
+    pub fn promote(
+        conn: &mut PgConnection,
+        id_project: Uuid,
+        endpoint_id: i32,
+    ) -> QueryResult<Option<WebEndpoint>> {
+        conn.transaction(|conn| {
+            let Some(endpoint) = Self::find_by_id(conn, id_project, endpoint_id)? else {
+                return Ok(None);
+            };
+            let host = match endpoint.host_id {
+                Some(id_host) => Host::get_host(conn, id_host)?,
+                None => None,
+            };
+            let form = endpoint.to_issue_form(host);
+            let fingerprint = form.fingerprint.clone();
+            Issue::create_issues(conn, vec![form], id_project)?;
+
+            let id_issue = issues::table
+                .filter(issues::project_id.eq(id_project))
+                .filter(issues::fingerprint.eq(fingerprint))
+                .select(issues::id)
+                .first::<Uuid>(conn)?;
+            diesel::update(web_endpoints::table.find(endpoint.id))
+                .set(web_endpoints::issue_id.eq(id_issue))
+                .returning(WebEndpoint::as_returning())
+                .get_result(conn)
+                .map(Some)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_categorize() {
+        let cases = [
+            ("http://10.0.0.6/.git/HEAD", Some("vcs")),
+            ("http://10.0.0.6/app/.svn/entries", Some("vcs")),
+            ("https://example.com/.env", Some("config")),
+            ("https://example.com/.env.production", Some("config")),
+            ("https://example.com/backup.zip", Some("backup")),
+            ("https://example.com/index.php.bak?x=1", Some("backup")),
+            ("https://example.com/server-status", Some("debug")),
+            ("https://example.com/Admin/", Some("admin")),
+            ("https://example.com/images", None),
+            ("https://example.com/", None),
+            ("https://example.com", None),
+        ];
+        for (url, expected) in cases {
+            assert_eq!(categorize(url), expected, "{}", url);
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use diesel::upsert::excluded;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::db::schema::{hosts, web_services};
+use crate::dtos::handlers::WebServiceForm;
+use crate::models::project::Project;
+
+/// A web server found by probing an open port.
+#[derive(Queryable, Selectable, Identifiable, Associations, Serialize, Debug, Clone, PartialEq)]
+#[diesel(table_name = web_services)]
+#[diesel(belongs_to(Project))]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct WebService {
+    pub id: i32,
+    pub project_id: Uuid,
+    pub host_id: Option<i32>,
+    pub url: String,
+    pub status_code: i32,
+    pub title: Option<String>,
+    pub server: Option<String>,
+    pub content_type: Option<String>,
+    pub redirect_location: Option<String>,
+    /// Subject of the certificate served on the port.
+    pub tls_subject: Option<String>,
+    /// Subject alternative names of the certificate.
+    pub tls_sans: Vec<String>,
+    /// Software the response hints at, e.g. "nginx/1.25.3" or "WordPress".
+    pub technologies: Vec<String>,
+    pub first_seen_at: NaiveDateTime,
+    pub last_seen_at: NaiveDateTime,
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = web_services)]
+pub struct NewWebService {
+    pub project_id: Uuid,
+    pub host_id: Option<i32>,
+    pub url: String,
+    pub status_code: i32,
+    pub title: Option<String>,
+    pub server: Option<String>,
+    pub content_type: Option<String>,
+    pub redirect_location: Option<String>,
+    pub tls_subject: Option<String>,
+    pub tls_sans: Vec<String>,
+    pub technologies: Vec<String>,
+    pub last_seen_at: NaiveDateTime,
+}
+
+impl WebService {
+    pub fn get_by_project(
+        conn: &mut PgConnection,
+        id_project: Uuid,
+        id_host: Option<i32>,
+    ) -> QueryResult<Vec<WebService>> {
+        use crate::db::schema::web_services::dsl::*;
+        let mut query = web_services
+            .filter(project_id.eq(id_project))
+            .select(WebService::as_select())
+            .into_boxed();
+        if let Some(id_host) = id_host {
+            query = query.filter(host_id.eq(id_host));
+        }
+        query.order(url.asc()).load(conn)
+    }
+
+    /// Records web servers found by a probe. Servers seen before keep their
+    /// first sighting, everything else follows the latest probe.
+    pub fn create_services(
+        conn: &mut PgConnection,
+        forms: &[WebServiceForm],
+        id_project: Uuid,
+    ) -> QueryResult<usize> {
+        use crate::db::schema::web_services::dsl::*;
+        let now = Utc::now().naive_utc();
+        let mut new_services: Vec<NewWebService> = Vec::new();
+        for form in forms {
+            // Postgres rejects an upsert that hits the same row twice.
+            if new_services.iter().any(|service| service.url == form.url) {
+                continue;
+            }
+            let id_host = hosts::table
+                .filter(hosts::project_id.eq(id_project))
+                .filter(hosts::ip_address.eq(&form.ip_address))
+                .order(hosts::id.asc())
+                .select(hosts::id)
+                .first::<i32>(conn)
+                .optional()?;
+            new_services.push(NewWebService {
+                project_id: id_project,
+                host_id: id_host,
+                url: form.url.clone(),
+                status_code: form.status_code,
+                title: form.title.clone(),
+                server: form.server.clone(),
+                content_type: form.content_type.clone(),
+                redirect_location: form.redirect_location.clone(),
+                tls_subject: form.tls_subject.clone(),
+                tls_sans: form.tls_sans.clone(),
+                technologies: form.technologies.clone(),
+                last_seen_at: now,
+            });
+        }
+        if new_services.is_empty() {
+            return Ok(0);
+        }
+        diesel::insert_into(web_services)
+            .values(&new_services)
+            .on_conflict((project_id, url))
+            .do_update()
+            .set((
+                host_id.eq(excluded(host_id)),
+                status_code.eq(excluded(status_code)),
+                title.eq(excluded(title)),
+                server.eq(excluded(server)),
+                content_type.eq(excluded(content_type)),
+                redirect_location.eq(excluded(redirect_location)),
+                tls_subject.eq(excluded(tls_subject)),
+                tls_sans.eq(excluded(tls_sans)),
+                technologies.eq(excluded(technologies)),
+                last_seen_at.eq(excluded(last_seen_at)),
+            ))
+            .execute(conn)
+    }
+}
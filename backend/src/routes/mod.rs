@@ -2,8 +2,8 @@ use actix_web::middleware::from_fn;
 use actix_web::web;
 
 use crate::handlers::{
-    auth_handlers, project_handlers, team_handlers, template_handlers,
-    user_handlers,
+    auth_handlers, import_handlers, project_handlers, scanner_handlers, team_handlers,
+    template_handlers, user_handlers,
 };
 use crate::middleware::auth::auth_middleware;
 
@@ -28,8 +28,30 @@ fn init_project_routes(cfg: &mut web::ServiceConfig) {
             .service(project_handlers::create_report_handler)
             .service(project_handlers::get_report_previews_for_project_handler)
             .service(project_handlers::get_report_handler)
+            .service(project_handlers::get_scan_all_handler)
+            .service(project_handlers::get_scan_diff_handler)
+            .service(project_handlers::scan_events_handler)
+            .service(project_handlers::get_scan_artifacts_handler)
+            .service(project_handlers::download_scan_artifact_handler)
             .service(project_handlers::get_scan_result_handler)
-            .service(project_handlers::start_scan_handler),
+            .service(project_handlers::start_scan_handler)
+            .service(project_handlers::cancel_scan_handler)
+            .service(project_handlers::get_scan_schedules_handler)
+            .service(project_handlers::create_scan_schedule_handler)
+            .service(project_handlers::pause_scan_schedule_handler)
+            .service(project_handlers::resume_scan_schedule_handler)
+            .service(project_handlers::delete_scan_schedule_handler)
+            .service(project_handlers::get_scope_audit_handler)
+            .service(project_handlers::check_scope_handler)
+            .service(project_handlers::get_scope_handler)
+            .service(project_handlers::create_scope_entry_handler)
+            .service(project_handlers::update_scope_entry_handler)
+            .service(project_handlers::delete_scope_entry_handler)
+            .service(project_handlers::get_web_endpoints_handler)
+            .service(project_handlers::promote_web_endpoint_handler)
+            .service(project_handlers::get_web_services_handler)
+            .service(import_handlers::import_nmap_handler)
+            .service(import_handlers::import_nuclei_handler),
     );
 }
 
@@ -76,6 +98,20 @@ fn init_team_routes(cfg: &mut web::ServiceConfig) {
     );
 }
 
+fn init_scanner_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/scanners")
+            // .wrap(from_fn(auth_middleware))
+            .service(scanner_handlers::get_scanners_handler)
+            .service(scanner_handlers::get_scan_profiles_handler)
+            .service(scanner_handlers::create_scan_profile_handler)
+            .service(scanner_handlers::update_scan_profile_handler)
+            .service(scanner_handlers::delete_scan_profile_handler)
+            .service(scanner_handlers::get_wordlists_handler)
+            .service(scanner_handlers::upload_wordlist_handler),
+    );
+}
+
 pub fn init_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/api")
@@ -83,6 +119,7 @@ pub fn init_routes(cfg: &mut web::ServiceConfig) {
             .configure(init_project_routes)
             .configure(init_auth_routes)
             .configure(init_team_routes)
-            .configure(init_template_routes),
+            .configure(init_template_routes)
+            .configure(init_scanner_routes),
     );
 }
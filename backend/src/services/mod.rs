@@ -1,2 +1,4 @@
 pub mod report;
 pub mod scanner;
+pub mod scope;
+pub mod wordlists;
@@ -86,6 +86,33 @@ impl MarkdownService {
             ),
         );
 
+        handlebars.register_helper(
+            "scopeKind",
+            Box::new(
+                |h: &handlebars::Helper,
+                 _: &Handlebars,
+                 _: &handlebars::Context,
+                 _: &mut handlebars::RenderContext,
+                 out: &mut dyn handlebars::Output| {
+                    let kind = h.param(0).and_then(|v| v.value().as_str()).ok_or(
+                        handlebars::RenderErrorReason::ParamNotFoundForIndex("scopeKind", 0),
+                    )?;
+
+                    let label = match kind {
+                        "cidr" => "Подсеть",
+                        "ip_range" => "Диапазон IP",
+                        "domain" => "Домен",
+                        "url_prefix" => "URL",
+                        "exclusion" => "Исключение",
+                        other => other,
+                    };
+
+                    out.write(label)?;
+                    Ok(())
+                },
+            ),
+        );
+
         handlebars.register_helper(
             "countBySeverity",
             Box::new(
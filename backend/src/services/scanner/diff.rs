@@ -0,0 +1,322 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::dtos::handlers::{HostForm, IssueForm, ServiceForm};
+use crate::services::scanner::types::{AnyScanResult, Error};
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PortChange {
+    pub host: String,
+    pub port: i32,
+    pub protocol: String,
+    pub service: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ServiceChange {
+    pub host: String,
+    pub port: i32,
+    pub protocol: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct HostsDiff {
+    pub new_hosts: Vec<String>,
+    pub disappeared_hosts: Vec<String>,
+    pub opened_ports: Vec<PortChange>,
+    pub closed_ports: Vec<PortChange>,
+    pub changed_services: Vec<ServiceChange>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FindingChange {
+    pub template_id: String,
+    pub name: String,
+    pub host: String,
+    pub matched_at: String,
+    pub cvss: Option<f64>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct FindingsDiff {
+    pub new: Vec<FindingChange>,
+    pub fixed: Vec<FindingChange>,
+    pub persisting: Vec<FindingChange>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ScanChanges {
+    Hosts(HostsDiff),
+    Issues(FindingsDiff),
+}
+
+/// Changes between an earlier and a later scan of the same project.
+#[derive(Debug, Serialize)]
+pub struct ScanDiff {
+    pub scanner_type: String,
+    pub from: Uuid,
+    pub to: Uuid,
+    #[serde(flatten)]
+    pub changes: ScanChanges,
+}
+
+impl ScanChanges {
+    pub fn compare(before: &AnyScanResult, after: &AnyScanResult) -> Result<Self, Error> {
+        match (before, after) {
+            (AnyScanResult::Hosts(before), AnyScanResult::Hosts(after)) => {
+                Ok(ScanChanges::Hosts(compare_hosts(&before.hosts, &after.hosts)))
+            },
+            (AnyScanResult::Issues(before), AnyScanResult::Issues(after)) => {
+                Ok(ScanChanges::Issues(compare_findings(&before.findings, &after.findings)))
+            },
+            (AnyScanResult::Endpoints(_), AnyScanResult::Endpoints(_)) => {
+                Err(Error::ParseError("Endpoint scans can't be compared".to_string()))
+            },
+            (AnyScanResult::WebServices(_), AnyScanResult::WebServices(_)) => {
+                Err(Error::ParseError("Web service scans can't be compared".to_string()))
+            },
+            _ => Err(Error::ParseError("Scan results have different kinds".to_string())),
+        }
+    }
+}
+
+type PortKey = (String, i32, String);
+
+fn service_label(service: &ServiceForm) -> Option<String> {
+    let parts: Vec<&str> = [&service.service_name, &service.product, &service.version]
+        .into_iter()
+        .filter_map(|part| part.as_deref())
+        .collect();
+    (!parts.is_empty()).then(|| parts.join(" "))
+}
+
+/// Open ports of every host with the service detected on them.
+fn open_ports(hosts: &[HostForm]) -> BTreeMap<PortKey, Option<String>> {
+    hosts
+        .iter()
+        .flat_map(|host| {
+            host.services
+                .iter()
+                .filter(|service| service.state.starts_with("open"))
+                .map(|service| {
+                    let key = (host.ip_address.clone(), service.port, service.protocol.clone());
+                    (key, service_label(service))
+                })
+        })
+        .collect()
+}
+
+fn port_change((host, port, protocol): &PortKey, service: &Option<String>) -> PortChange {
+    PortChange {
+        host: host.clone(),
+        port: *port,
+        protocol: protocol.clone(),
+        service: service.clone(),
+    }
+}
+
+pub fn compare_hosts(before: &[HostForm], after: &[HostForm]) -> HostsDiff {
+    let before_hosts: BTreeSet<&str> = before.iter().map(|host| host.ip_address.as_str()).collect();
+    let after_hosts: BTreeSet<&str> = after.iter().map(|host| host.ip_address.as_str()).collect();
+    let before_ports = open_ports(before);
+    let after_ports = open_ports(after);
+
+    let mut diff = HostsDiff {
+        new_hosts: after_hosts.difference(&before_hosts).map(|host| host.to_string()).collect(),
+        disappeared_hosts: before_hosts
+            .difference(&after_hosts)
+            .map(|host| host.to_string())
+            .collect(),
+        ..HostsDiff::default()
+    };
+    for (key, service) in &after_ports {
+        match before_ports.get(key) {
+            None => diff.opened_ports.push(port_change(key, service)),
+            // A service that was not fingerprinted in the later scan is not a change.
+            Some(previous) if service.is_some() && previous != service => {
+                diff.changed_services.push(ServiceChange {
+                    host: key.0.clone(),
+                    port: key.1,
+                    protocol: key.2.clone(),
+                    before: previous.clone(),
+                    after: service.clone(),
+                })
+            },
+            Some(_) => {},
+        }
+    }
+    for (key, service) in &before_ports {
+        if !after_ports.contains_key(key) {
+            diff.closed_ports.push(port_change(key, service));
+        }
+    }
+    diff
+}
+
+type FindingKey = (String, String, String);
+
+fn findings_by_key(findings: &[IssueForm]) -> BTreeMap<FindingKey, FindingChange> {
+    findings
+        .iter()
+        .map(|finding| {
+            let change = FindingChange {
+                template_id: finding.template_id.clone().unwrap_or_else(|| finding.name.clone()),
+                name: finding.name.clone(),
+                host: finding
+                    .hosts
+                    .first()
+                    .map(|host| host.ip_address.clone())
+                    .unwrap_or_default(),
+                matched_at: finding.matched_at.clone().unwrap_or_default(),
+                cvss: finding.cvss,
+            };
+            // Templates with several matchers report each of them at the same
+            // location, which the fingerprint or the name tells apart.
+            let identity = finding
+                .fingerprint
+                .clone()
+                .unwrap_or_else(|| format!("{}:{}", change.template_id, change.name));
+            let key = (identity, change.host.clone(), change.matched_at.clone());
+            (key, change)
+        })
+        .collect()
+}
+
+/// Matches findings by fingerprint, host and matched-at.
+pub fn compare_findings(before: &[IssueForm], after: &[IssueForm]) -> FindingsDiff {
+    let before = findings_by_key(before);
+    let mut after = findings_by_key(after);
+
+    let mut diff = FindingsDiff::default();
+    for (key, finding) in before {
+        match after.remove(&key) {
+            Some(finding) => diff.persisting.push(finding),
+            None => diff.fixed.push(finding),
+        }
+    }
+    diff.new = after.into_values().collect();
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dtos::handlers::ClassificationForm;
+
+    fn service(port: i32, state: &str, product: Option<&str>) -> ServiceForm {
+        ServiceForm {
+            port,
+            protocol: "tcp".to_string(),
+            state: state.to_string(),
+            service_name: Some("http".to_string()),
+            product: product.map(str::to_string),
+            version: None,
+            extra_info: None,
+            cpe: None,
+        }
+    }
+
+    fn host(ip: &str, services: Vec<ServiceForm>) -> HostForm {
+        HostForm {
+            hostname: None,
+            ip_address: ip.to_string(),
+            os: None,
+            os_accuracy: None,
+            services,
+            sources: Vec::new(),
+            hostnames: Vec::new(),
+        }
+    }
+
+    fn finding(template_id: &str, ip: &str, matched_at: &str) -> IssueForm {
+        IssueForm {
+            name: template_id.to_string(),
+            description: None,
+            mitigation: None,
+            cvss: Some(5.0),
+            hosts: vec![host(ip, vec![])],
+            template_id: Some(template_id.to_string()),
+            matched_at: Some(matched_at.to_string()),
+            fingerprint: Some(template_id.to_string()),
+            evidence: Vec::new(),
+            classification: ClassificationForm::default(),
+        }
+    }
+
+    #[test]
+    fn test_compare_hosts() {
+        let before = vec![
+            host("10.0.0.1", vec![service(80, "open", Some("nginx")), service(22, "open", None)]),
+            host("10.0.0.2", vec![service(80, "open", None)]),
+        ];
+        let after = vec![
+            host("10.0.0.1", vec![
+                service(80, "open", Some("Apache httpd")),
+                service(22, "closed", None),
+                service(443, "open", None),
+            ]),
+            host("10.0.0.3", vec![]),
+        ];
+
+        let diff = compare_hosts(&before, &after);
+        assert_eq!(diff.new_hosts, vec!["10.0.0.3"]);
+        assert_eq!(diff.disappeared_hosts, vec!["10.0.0.2"]);
+        let ports = |changes: &[PortChange]| -> Vec<(String, i32)> {
+            changes.iter().map(|change| (change.host.clone(), change.port)).collect()
+        };
+        assert_eq!(ports(&diff.opened_ports), vec![("10.0.0.1".to_string(), 443)]);
+        assert_eq!(ports(&diff.closed_ports), vec![
+            ("10.0.0.1".to_string(), 22),
+            ("10.0.0.2".to_string(), 80),
+        ]);
+        assert_eq!(diff.changed_services.len(), 1);
+        assert_eq!(diff.changed_services[0].after.as_deref(), Some("http Apache httpd"));
+    }
+
+    #[test]
+    fn test_compare_findings() {
+        let before = vec![
+            finding("git-config", "10.0.0.1", "http://10.0.0.1/.git/config"),
+            finding("tech-detect", "10.0.0.1", "http://10.0.0.1/"),
+        ];
+        let after = vec![
+            finding("tech-detect", "10.0.0.1", "http://10.0.0.1/"),
+            finding("tech-detect", "10.0.0.1", "http://10.0.0.1:8080/"),
+        ];
+
+        let diff = compare_findings(&before, &after);
+        assert_eq!(diff.fixed.len(), 1);
+        assert_eq!(diff.fixed[0].template_id, "git-config");
+        assert_eq!(diff.persisting.len(), 1);
+        assert_eq!(diff.new.len(), 1);
+        assert_eq!(diff.new[0].matched_at, "http://10.0.0.1:8080/");
+    }
+
+    #[test]
+    fn test_compare_findings_of_several_matchers() {
+        let matcher = |name: &str| IssueForm {
+            name: format!("HTTP Missing Security Headers: {}", name),
+            fingerprint: Some(format!("http-missing-security-headers:{}", name)),
+            ..finding("http-missing-security-headers", "10.0.0.1", "https://10.0.0.1/")
+        };
+        let before = vec![matcher("x-frame-options"), matcher("content-security-policy")];
+        let after = vec![matcher("content-security-policy")];
+
+        let diff = compare_findings(&before, &after);
+        assert_eq!(diff.fixed.len(), 1);
+        assert_eq!(diff.fixed[0].name, "HTTP Missing Security Headers: x-frame-options");
+        assert_eq!(diff.persisting.len(), 1);
+        assert!(diff.new.is_empty());
+
+        let without_fingerprints: Vec<IssueForm> = before
+            .into_iter()
+            .map(|finding| IssueForm { fingerprint: None, ..finding })
+            .collect();
+        assert_eq!(compare_findings(&[], &without_fingerprints).new.len(), 2);
+    }
+}
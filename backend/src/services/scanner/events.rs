@@ -0,0 +1,75 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use serde::Serialize;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+/// Events buffered per subscriber before slow clients start missing some.
+const CHANNEL_CAPACITY: usize = 256;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ScanProgress {
+    pub percent: Option<f64>,
+    pub hosts: Option<u64>,
+    pub findings: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ScanEvent {
+    Status { status: String },
+    Progress(ScanProgress),
+    Log { line: String },
+}
+
+impl ScanEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ScanEvent::Status { .. } => "status",
+            ScanEvent::Progress(_) => "progress",
+            ScanEvent::Log { .. } => "log",
+        }
+    }
+}
+
+/// Broadcasts events of running scans to their subscribers.
+#[derive(Clone, Default)]
+pub struct ScanEvents {
+    channels: Arc<Mutex<HashMap<Uuid, broadcast::Sender<ScanEvent>>>>,
+}
+
+impl ScanEvents {
+    fn lock(&self) -> MutexGuard<'_, HashMap<Uuid, broadcast::Sender<ScanEvent>>> {
+        self.channels.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn subscribe(&self, scan_id: Uuid) -> broadcast::Receiver<ScanEvent> {
+        self.lock()
+            .entry(scan_id)
+            .or_insert_with(|| broadcast::channel(CHANNEL_CAPACITY).0)
+            .subscribe()
+    }
+
+    /// Sends the event to current subscribers; it is dropped if nobody listens.
+    pub fn publish(&self, scan_id: Uuid, event: ScanEvent) {
+        if let Some(sender) = self.lock().get(&scan_id) {
+            let _ = sender.send(event);
+        }
+    }
+
+    /// Drops the channel of the scan if nobody is subscribed to it anymore.
+    pub fn release(&self, scan_id: Uuid) {
+        let mut channels = self.lock();
+        if channels.get(&scan_id).is_some_and(|sender| sender.receiver_count() == 0) {
+            channels.remove(&scan_id);
+        }
+    }
+
+    /// Sends the final status and closes the streams of all subscribers.
+    pub fn finish(&self, scan_id: Uuid, status: String) {
+        if let Some(sender) = self.lock().remove(&scan_id) {
+            let _ = sender.send(ScanEvent::Status { status });
+        }
+    }
+}
@@ -0,0 +1,314 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use async_trait::async_trait;
+use log::{debug, error};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::dtos::handlers::WebEndpointForm;
+use crate::services::scanner::types::{
+    resolve_under, AnyScanResult, EndpointsScanResult, Error, ScanOptions, ScannerCapability,
+    ScannerOption, ScannerOptionKind,
+};
+use crate::services::scanner::events::ScanProgress;
+use crate::services::scanner::process::ProcessRegistry;
+use crate::services::scanner::{is_binary_installed, parse_targets, VulnerabilityScanner};
+use crate::services::scope::host_of;
+use crate::services::wordlists;
+
+/// `-of json` report of ffuf.
+#[derive(Debug, Deserialize)]
+struct FfufOutput {
+    #[serde(default)]
+    results: Vec<FfufResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FfufResult {
+    url: String,
+    status: i32,
+    length: i64,
+    #[serde(rename = "content-type", default)]
+    content_type: String,
+    #[serde(rename = "redirectlocation", default)]
+    redirect_location: String,
+}
+
+#[derive(Clone)]
+pub struct FfufService {
+    scans_dir: PathBuf,
+    wordlists_dir: PathBuf,
+    processes: ProcessRegistry,
+}
+
+impl FfufService {
+    pub fn new(
+        scans_dir: impl AsRef<Path>,
+        wordlists_dir: impl AsRef<Path>,
+        processes: ProcessRegistry,
+    ) -> Self {
+        Self {
+            scans_dir: scans_dir.as_ref().to_path_buf(),
+            wordlists_dir: wordlists_dir.as_ref().to_path_buf(),
+            processes,
+        }
+    }
+
+    /// ffuf fuzzes one base URL per run.
+    fn check_target(targets: &[String]) -> Result<&str, Error> {
+        match targets {
+            [target] if target.starts_with("http://") || target.starts_with("https://") => Ok(target),
+            [target] => Err(Error::InvalidTarget(format!("{}: expected an http(s) URL", target))),
+            _ => Err(Error::InvalidTarget("ffuf accepts a single base URL".to_string())),
+        }
+    }
+
+    /// Path of the wordlist picked in the options, which must exist in the
+    /// wordlists directory.
+    fn wordlist_path(&self, options: &ScanOptions) -> Result<PathBuf, Error> {
+        let name = options
+            .string("wordlist")
+            .ok_or_else(|| Error::InvalidOption("wordlist: required".to_string()))?;
+        match resolve_under(&self.wordlists_dir, name) {
+            Some(path) if wordlists::is_valid_name(name) && path.is_file() => Ok(path),
+            _ => Err(Error::InvalidOption(format!("wordlist: {} not found", name))),
+        }
+    }
+
+    /// Translates validated scan options into ffuf arguments.
+    fn build_args(options: &ScanOptions, output_file: &str, base_url: &str, wordlist: &str) -> Vec<String> {
+        let mut args = vec![
+            "-u".to_string(),
+            format!("{}/FUZZ", base_url.trim_end_matches('/')),
+            "-w".to_string(),
+            wordlist.to_string(),
+            "-o".to_string(),
+            output_file.to_string(),
+            "-of".to_string(),
+            "json".to_string(),
+            "-noninteractive".to_string(),
+            "-s".to_string(),
+        ];
+        let extensions: Vec<String> = options
+            .list("extensions")
+            .into_iter()
+            .map(|extension| format!(".{}", extension.trim_start_matches('.')))
+            .collect();
+        if !extensions.is_empty() {
+            args.extend(["-e".to_string(), extensions.join(",")]);
+        }
+        if let Some(codes) = options.string("match_codes") {
+            args.extend(["-mc".to_string(), codes.to_string()]);
+        }
+        if let Some(threads) = options.integer("threads") {
+            args.extend(["-t".to_string(), threads.to_string()]);
+        }
+        if let Some(rate_limit) = options.integer("rate_limit") {
+            args.extend(["-rate".to_string(), rate_limit.to_string()]);
+        }
+        if options.flag("recursion") {
+            args.push("-recursion".to_string());
+        }
+        args
+    }
+
+    /// ffuf only reports progress on an interactive terminal.
+    fn parse_progress(_line: &str) -> Option<ScanProgress> {
+        None
+    }
+
+    pub fn parse_output(content: &str) -> Result<Vec<WebEndpointForm>, Error> {
+        if content.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+        let output: FfufOutput =
+            serde_json::from_str(content).map_err(|e| Error::ParseError(e.to_string()))?;
+        let non_empty = |value: String| Some(value).filter(|value| !value.is_empty());
+        Ok(output
+            .results
+            .into_iter()
+            .map(|result| WebEndpointForm {
+                host: host_of(&result.url).to_string(),
+                url: result.url,
+                status_code: result.status,
+                content_length: result.length,
+                content_type: non_empty(result.content_type),
+                redirect_location: non_empty(result.redirect_location),
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl VulnerabilityScanner for FfufService {
+    fn name(&self) -> &'static str {
+        "ffuf"
+    }
+
+    fn capabilities(&self) -> Vec<ScannerCapability> {
+        vec![ScannerCapability::ContentDiscovery]
+    }
+
+    fn options(&self) -> Vec<ScannerOption> {
+        vec![
+            ScannerOption {
+                name: "wordlist",
+                description: "Name of a wordlist from the wordlist store",
+                kind: ScannerOptionKind::Text,
+            },
+            ScannerOption {
+                name: "extensions",
+                description: "File extensions appended to every word, e.g. php or bak",
+                kind: ScannerOptionKind::List,
+            },
+            ScannerOption {
+                name: "match_codes",
+                description: "HTTP status codes to keep, e.g. 200-299,301,403",
+                kind: ScannerOptionKind::StatusCodes,
+            },
+            ScannerOption {
+                name: "threads",
+                description: "Number of concurrent requests",
+                kind: ScannerOptionKind::Integer { min: 1, max: 200 },
+            },
+            ScannerOption {
+                name: "rate_limit",
+                description: "Maximum number of requests per second",
+                kind: ScannerOptionKind::Integer { min: 1, max: 10000 },
+            },
+            ScannerOption {
+                name: "recursion",
+                description: "Descend into found directories",
+                kind: ScannerOptionKind::Boolean,
+            },
+        ]
+    }
+
+    fn is_installed(&self) -> bool {
+        is_binary_installed("ffuf")
+    }
+
+    async fn get_scan_result(&self, scan_id: Uuid) -> Result<AnyScanResult, Error> {
+        self.load_result(&self.scans_dir.join(scan_id.to_string()).join("scan.json"))
+    }
+
+    fn load_result(&self, output_file: &Path) -> Result<AnyScanResult, Error> {
+        let content = fs::read_to_string(output_file)
+            .map_err(|e| Error::IoError(format!("Failed to read result file: {}", e)))?;
+
+        Ok(AnyScanResult::Endpoints(EndpointsScanResult {
+            output_file: output_file.display().to_string(),
+            endpoints: Self::parse_output(&content)?,
+        }))
+    }
+
+    async fn start_scan(
+        &self,
+        scan_id: Uuid,
+        target: &str,
+        options: &ScanOptions,
+    ) -> Result<AnyScanResult, Error> {
+        let targets = parse_targets(target)?;
+        let base_url = Self::check_target(&targets)?;
+        let wordlist = self.wordlist_path(options)?;
+        let scan_path = self.scans_dir.join(scan_id.to_string());
+        fs::create_dir_all(&scan_path).map_err(|e| {
+            error!("Failed to create scan directory: {}", e);
+            Error::IoError(format!("Failed to create scan directory: {}", e))
+        })?;
+
+        let output_file = scan_path.join("scan.json").display().to_string();
+        let args = Self::build_args(options, &output_file, base_url, &wordlist.display().to_string());
+        debug!("Run command ffuf {}", args.join(" "));
+        let mut command = tokio::process::Command::new("ffuf");
+        command.args(args);
+        self.processes
+            .run(scan_id, command, &output_file, Self::parse_progress)
+            .await?
+            .check("ffuf")?;
+
+        let content = match fs::read_to_string(&output_file) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => {
+                error!("Failed to read result file: {}", e);
+                return Err(Error::IoError(format!("Failed to read result file: {}", e)));
+            },
+        };
+        let endpoints = Self::parse_output(&content).map_err(|e| {
+            error!("Can't parse ffuf output file: {}", e);
+            e
+        })?;
+
+        Ok(AnyScanResult::Endpoints(EndpointsScanResult {
+            output_file,
+            endpoints,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> String {
+        fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/ffuf/scan.json")).unwrap()
+    }
+
+    #[test]
+    fn test_parse_output() {
+        let endpoints = FfufService::parse_output(&fixture()).unwrap();
+        assert_eq!(endpoints.len(), 8);
+
+        let git = &endpoints[0];
+        assert_eq!(git.url, "http://10.0.0.6:8080/.git/HEAD");
+        assert_eq!(git.host, "10.0.0.6");
+        assert_eq!(git.status_code, 200);
+        assert_eq!(git.content_length, 23);
+        assert_eq!(git.content_type.as_deref(), Some("text/plain"));
+        assert_eq!(git.redirect_location, None);
+
+        let admin = &endpoints[1];
+        assert_eq!(admin.status_code, 301);
+        assert_eq!(admin.redirect_location.as_deref(), Some("http://10.0.0.6:8080/admin/"));
+
+        assert!(FfufService::parse_output("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_build_args() {
+        let options = ScanOptions::validate(
+            &FfufService::new("/tmp", "/tmp", ProcessRegistry::default()).options(),
+            serde_json::json!({
+                "wordlist": "common.txt",
+                "extensions": ["php", ".bak"],
+                "match_codes": "200-299,403",
+                "recursion": true,
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        )
+        .unwrap();
+
+        let args = FfufService::build_args(
+            &options,
+            "/tmp/scan.json",
+            "http://10.0.0.6:8080/",
+            "/app/wordlists/common.txt",
+        );
+        assert_eq!(args, vec![
+            "-u", "http://10.0.0.6:8080/FUZZ", "-w", "/app/wordlists/common.txt", "-o",
+            "/tmp/scan.json", "-of", "json", "-noninteractive", "-s", "-e", ".php,.bak", "-mc",
+            "200-299,403", "-recursion",
+        ]);
+    }
+
+    #[test]
+    fn test_check_target() {
+        let targets = parse_targets("https://example.com/app").unwrap();
+        assert_eq!(FfufService::check_target(&targets).unwrap(), "https://example.com/app");
+        assert!(FfufService::check_target(&parse_targets("example.com").unwrap()).is_err());
+        assert!(FfufService::check_target(&parse_targets("http://a.com http://b.com").unwrap()).is_err());
+    }
+}
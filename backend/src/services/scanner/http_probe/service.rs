@@ -0,0 +1,911 @@
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use async_trait::async_trait;
+use futures::{stream, StreamExt};
+use http_body_util::{BodyExt, Empty};
+use hyper::body::Bytes;
+use hyper::client::conn::http1;
+use hyper::header::{HOST, USER_AGENT};
+use hyper::http::response::Parts;
+use hyper::{HeaderMap, Request};
+use hyper_util::rt::TokioIo;
+use log::{debug, error};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+use tokio_rustls::rustls::client::danger::{
+    HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier,
+};
+use tokio_rustls::rustls::crypto::{self, CryptoProvider};
+use tokio_rustls::rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use tokio_rustls::rustls::{ClientConfig, DigitallySignedStruct, SignatureScheme};
+use tokio_rustls::TlsConnector;
+use uuid::Uuid;
+use x509_parser::extensions::GeneralName;
+
+use crate::dtos::handlers::{ClassificationForm, EvidenceForm, HostForm, IssueForm, WebServiceForm};
+use crate::services::scanner::types::{
+    tcp_ports, AnyScanResult, Error, ScanOptions, ScannerCapability, ScannerOption,
+    ScannerOptionKind, WebServicesScanResult,
+};
+use crate::services::scanner::events::ScanProgress;
+use crate::services::scanner::process::ProcessRegistry;
+use crate::services::scanner::{parse_targets, VulnerabilityScanner};
+use crate::services::scope::IpRange;
+
+const DEFAULT_PORTS: [u16; 5] = [80, 443, 8000, 8080, 8443];
+const DEFAULT_CONCURRENCY: i64 = 50;
+const DEFAULT_TIMEOUT_MS: i64 = 5000;
+/// Keeps a mistyped CIDR block from turning into millions of requests.
+const MAX_PROBES: usize = 65536;
+/// Enough of the page for the title and technology hints.
+const MAX_BODY_LENGTH: usize = 256 * 1024;
+const MAX_TITLE_LENGTH: usize = 200;
+/// Bounded by the columns of web services.
+const MAX_HEADER_LENGTH: usize = 255;
+const PROBE_USER_AGENT: &str = "Mozilla/5.0 (compatible; http-probe)";
+
+/// A security header whose absence is reported as an issue.
+struct SecurityHeader {
+    /// Header name in lower case.
+    name: &'static str,
+    title: &'static str,
+    description: &'static str,
+    mitigation: &'static str,
+    reference: &'static str,
+    cvss: f64,
+    /// Only meaningful on responses served over TLS.
+    https_only: bool,
+}
+
+const SECURITY_HEADERS: [SecurityHeader; 6] = [
+    SecurityHeader {
+        name: "strict-transport-security",
+        title: "Strict-Transport-Security",
+        description: "The \"Strict-Transport-Security\" header is not set, so browsers keep \
+            connecting over plain HTTP first, where the connection can be intercepted and \
+            downgraded.",
+        mitigation: "Send \"Strict-Transport-Security: max-age=31536000; includeSubDomains\" on \
+            every HTTPS response.",
+        reference: "https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Strict-Transport-Security",
+        cvss: 3.1,
+        https_only: true,
+    },
+    SecurityHeader {
+        name: "content-security-policy",
+        title: "Content-Security-Policy",
+        description: "The \"Content-Security-Policy\" header is not set, so nothing limits the \
+            scripts and resources a page loads if an attacker manages to inject markup.",
+        mitigation: "Define a Content-Security-Policy that allows only the sources the \
+            application needs.",
+        reference: "https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Content-Security-Policy",
+        cvss: 3.1,
+        https_only: false,
+    },
+    SecurityHeader {
+        name: "x-frame-options",
+        title: "X-Frame-Options",
+        description: "Neither the \"X-Frame-Options\" header nor the \"frame-ancestors\" \
+            directive is set, so other sites can frame the pages for clickjacking.",
+        mitigation: "Send \"X-Frame-Options: DENY\" or a Content-Security-Policy with a \
+            \"frame-ancestors\" directive.",
+        reference: "https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/X-Frame-Options",
+        cvss: 3.1,
+        https_only: false,
+    },
+    SecurityHeader {
+        name: "x-content-type-options",
+        title: "X-Content-Type-Options",
+        description: "The \"X-Content-Type-Options\" header is not set, so browsers may guess \
+            the type of responses and run uploaded content as script.",
+        mitigation: "Send \"X-Content-Type-Options: nosniff\".",
+        reference: "https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/X-Content-Type-Options",
+        cvss: 0.0,
+        https_only: false,
+    },
+    SecurityHeader {
+        name: "referrer-policy",
+        title: "Referrer-Policy",
+        description: "The \"Referrer-Policy\" header is not set, so full URLs, including tokens \
+            in them, may leak to other sites through the Referer header.",
+        mitigation: "Send \"Referrer-Policy: strict-origin-when-cross-origin\" or stricter.",
+        reference: "https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Referrer-Policy",
+        cvss: 0.0,
+        https_only: false,
+    },
+    SecurityHeader {
+        name: "permissions-policy",
+        title: "Permissions-Policy",
+        description: "The \"Permissions-Policy\" header is not set, so embedded content may use \
+            browser features such as the camera or geolocation.",
+        mitigation: "Send a Permissions-Policy that disables the features the application \
+            doesn't use.",
+        reference: "https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/Permissions-Policy",
+        cvss: 0.0,
+        https_only: false,
+    },
+];
+
+/// Evidence of a technology in a response.
+enum Signature {
+    /// The header is present.
+    Header(&'static str),
+    /// A cookie with this name is set.
+    Cookie(&'static str),
+    /// The body contains the text.
+    Body(&'static str),
+}
+
+const TECHNOLOGIES: &[(&str, Signature)] = &[
+    ("PHP", Signature::Cookie("PHPSESSID")),
+    ("Java", Signature::Cookie("JSESSIONID")),
+    ("ASP.NET", Signature::Cookie("ASP.NET_SessionId")),
+    ("ASP.NET", Signature::Header("x-aspnet-version")),
+    ("Laravel", Signature::Cookie("laravel_session")),
+    ("Django", Signature::Cookie("csrftoken")),
+    ("Cloudflare", Signature::Header("cf-ray")),
+    ("Drupal", Signature::Header("x-drupal-cache")),
+    ("Jenkins", Signature::Header("x-jenkins")),
+    ("WordPress", Signature::Body("/wp-content/")),
+    ("Drupal", Signature::Body("Drupal.settings")),
+    ("Joomla", Signature::Body("/media/jui/")),
+    ("Next.js", Signature::Body("/_next/static/")),
+    ("Angular", Signature::Body("ng-version=")),
+    ("React", Signature::Body("data-reactroot")),
+    ("jQuery", Signature::Body("jquery")),
+];
+
+/// A port to probe, over the schemes in the order they are tried.
+#[derive(Debug, Clone, PartialEq)]
+struct ProbeTarget {
+    host: String,
+    port: u16,
+    schemes: Vec<&'static str>,
+    path: String,
+}
+
+impl ProbeTarget {
+    fn url(&self, scheme: &str) -> String {
+        format!("{}://{}{}", scheme, self.authority(scheme), self.path)
+    }
+
+    /// Host and, unless it is the default of the scheme, port.
+    fn authority(&self, scheme: &str) -> String {
+        let host = if self.host.contains(':') {
+            format!("[{}]", self.host)
+        } else {
+            self.host.clone()
+        };
+        match (scheme, self.port) {
+            ("http", 80) | ("https", 443) => host,
+            _ => format!("{}:{}", host, self.port),
+        }
+    }
+}
+
+/// Probes have to see self-signed and expired certificates, which are
+/// recorded rather than trusted.
+#[derive(Debug)]
+struct AcceptAnyCertificate(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for AcceptAnyCertificate {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, tokio_rustls::rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, tokio_rustls::rustls::Error> {
+        crypto::verify_tls12_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, tokio_rustls::rustls::Error> {
+        crypto::verify_tls13_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+#[derive(Clone)]
+pub struct HttpProbeService {
+    scans_dir: PathBuf,
+    processes: ProcessRegistry,
+}
+
+impl HttpProbeService {
+    pub fn new(scans_dir: impl AsRef<Path>, processes: ProcessRegistry) -> Self {
+        Self {
+            scans_dir: scans_dir.as_ref().to_path_buf(),
+            processes,
+        }
+    }
+
+    /// Splits `host:port` and `[v6]:port`, the port being optional.
+    fn split_host_port(value: &str) -> Option<(String, Option<u16>)> {
+        let (host, port) = match value.strip_prefix('[') {
+            Some(rest) => match rest.split_once(']')? {
+                (host, "") => (host, None),
+                (host, port) => (host, Some(port.strip_prefix(':')?)),
+            },
+            None => match value.split_once(':') {
+                Some((host, port)) if !port.contains(':') => (host, Some(port)),
+                _ => (value, None),
+            },
+        };
+        if host.is_empty() {
+            return None;
+        }
+        let port = match port {
+            Some(port) => Some(port.parse().ok()?),
+            None => None,
+        };
+        Some((host.to_string(), port))
+    }
+
+    /// URLs are probed as given. Hosts with a port are tried over HTTPS, then
+    /// HTTP, and so are the `ports` of hosts, addresses and ranges without one.
+    fn expand_targets(targets: &[String], ports: &[u16]) -> Result<Vec<ProbeTarget>, Error> {
+        let mut probes = Vec::new();
+        for target in targets {
+            let invalid = || {
+                Error::InvalidTarget(format!(
+                    "{}: expected a URL, host:port, host or address range",
+                    target
+                ))
+            };
+            if let Some((scheme, rest)) = target.split_once("://") {
+                let (scheme, default_port) = match scheme {
+                    "http" => ("http", 80),
+                    "https" => ("https", 443),
+                    _ => return Err(invalid()),
+                };
+                let (authority, path) = match rest.find('/') {
+                    Some(start) => (&rest[..start], &rest[start..]),
+                    None => (rest, "/"),
+                };
+                let (host, port) = Self::split_host_port(authority).ok_or_else(invalid)?;
+                probes.push(ProbeTarget {
+                    host,
+                    port: port.unwrap_or(default_port),
+                    schemes: vec![scheme],
+                    path: path.to_string(),
+                });
+                continue;
+            }
+            let probe = |host: String, port: u16| ProbeTarget {
+                host,
+                port,
+                schemes: vec!["https", "http"],
+                path: "/".to_string(),
+            };
+            match Self::split_host_port(target).ok_or_else(invalid)? {
+                (host, Some(port)) => probes.push(probe(host, port)),
+                (host, None) => match IpRange::parse(&host) {
+                    Some(range) if range.size() * ports.len() as u128 > MAX_PROBES as u128 => {
+                        return Err(Error::InvalidTarget(format!(
+                            "{}: at most {} ports can be probed at once",
+                            target, MAX_PROBES
+                        )));
+                    },
+                    Some(range) => {
+                        for ip in range.addresses() {
+                            probes.extend(ports.iter().map(|port| probe(ip.to_string(), *port)));
+                        }
+                    },
+                    None => probes.extend(ports.iter().map(|port| probe(host.clone(), *port))),
+                },
+            }
+            if probes.len() > MAX_PROBES {
+                return Err(Error::InvalidTarget(format!(
+                    "at most {} ports can be probed at once",
+                    MAX_PROBES
+                )));
+            }
+        }
+        Ok(probes)
+    }
+
+    fn tls_connector() -> Result<TlsConnector, Error> {
+        let provider = Arc::new(crypto::ring::default_provider());
+        let mut config = ClientConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .map_err(|e| Error::ExecutionError(format!("Failed to set up TLS: {}", e)))?
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(AcceptAnyCertificate(provider)))
+            .with_no_client_auth();
+        config.alpn_protocols = vec![b"http/1.1".to_vec()];
+        Ok(TlsConnector::from(Arc::new(config)))
+    }
+
+    /// Subject and subject alternative names of a DER certificate.
+    fn parse_certificate(der: &[u8]) -> Option<(String, Vec<String>)> {
+        let (_, certificate) = x509_parser::parse_x509_certificate(der).ok()?;
+        let sans = match certificate.subject_alternative_name() {
+            Ok(Some(extension)) => extension
+                .value
+                .general_names
+                .iter()
+                .filter_map(|name| match name {
+                    GeneralName::DNSName(name) => Some(name.to_string()),
+                    GeneralName::IPAddress(&[a, b, c, d]) => Some(IpAddr::from([a, b, c, d]).to_string()),
+                    GeneralName::IPAddress(bytes) => {
+                        <[u8; 16]>::try_from(*bytes).ok().map(|bytes| IpAddr::from(bytes).to_string())
+                    },
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        Some((certificate.subject().to_string(), sans))
+    }
+
+    /// Tries the schemes of the target in turn, returning the first answer.
+    async fn probe(target: ProbeTarget, connector: &TlsConnector, wait: Duration) -> Option<WebServiceForm> {
+        for scheme in &target.schemes {
+            match Self::fetch(&target, scheme, connector, wait).await {
+                Ok(service) => return Some(service),
+                Err(e) => debug!("No answer from {}: {}", target.url(scheme), e),
+            }
+        }
+        None
+    }
+
+    async fn fetch(
+        target: &ProbeTarget,
+        scheme: &str,
+        connector: &TlsConnector,
+        wait: Duration,
+    ) -> Result<WebServiceForm, String> {
+        let stream = timeout(wait, TcpStream::connect((target.host.as_str(), target.port)))
+            .await
+            .map_err(|_| "connection timed out".to_string())?
+            .map_err(|e| e.to_string())?;
+        let ip = stream.peer_addr().map_err(|e| e.to_string())?.ip();
+        if scheme != "https" {
+            let (parts, body) = Self::request(stream, target, scheme, wait).await?;
+            return Ok(Self::describe(target, scheme, ip, &parts, &body));
+        }
+
+        let server_name = ServerName::try_from(target.host.clone()).map_err(|e| e.to_string())?;
+        let stream = timeout(wait, connector.connect(server_name, stream))
+            .await
+            .map_err(|_| "TLS handshake timed out".to_string())?
+            .map_err(|e| e.to_string())?;
+        let certificate = stream
+            .get_ref()
+            .1
+            .peer_certificates()
+            .and_then(|certificates| certificates.first())
+            .and_then(|certificate| Self::parse_certificate(certificate));
+        let (parts, body) = Self::request(stream, target, scheme, wait).await?;
+        let mut service = Self::describe(target, scheme, ip, &parts, &body);
+        if let Some((subject, sans)) = certificate {
+            service.tls_subject = Some(subject).filter(|subject| !subject.is_empty());
+            service.tls_sans = sans;
+        }
+        Ok(service)
+    }
+
+    /// Sends a GET request and reads the response, the body only up to
+    /// `MAX_BODY_LENGTH` bytes.
+    async fn request<S>(
+        stream: S,
+        target: &ProbeTarget,
+        scheme: &str,
+        wait: Duration,
+    ) -> Result<(Parts, Vec<u8>), String>
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let (mut sender, connection) = timeout(wait, http1::handshake(TokioIo::new(stream)))
+            .await
+            .map_err(|_| "HTTP handshake timed out".to_string())?
+            .map_err(|e| e.to_string())?;
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                debug!("HTTP connection closed: {}", e);
+            }
+        });
+        let request = Request::get(target.path.as_str())
+            .header(HOST, target.authority(scheme))
+            .header(USER_AGENT, PROBE_USER_AGENT)
+            .body(Empty::<Bytes>::new())
+            .map_err(|e| e.to_string())?;
+        let response = timeout(wait, sender.send_request(request))
+            .await
+            .map_err(|_| "request timed out".to_string())?
+            .map_err(|e| e.to_string())?;
+
+        let (parts, mut body) = response.into_parts();
+        let mut content = Vec::new();
+        let read_body = async {
+            while content.len() < MAX_BODY_LENGTH {
+                match body.frame().await {
+                    Some(Ok(frame)) => {
+                        if let Some(data) = frame.data_ref() {
+                            content.extend_from_slice(data);
+                        }
+                    },
+                    _ => break,
+                }
+            }
+        };
+        // A slow body still leaves the headers to report.
+        let _ = timeout(wait, read_body).await;
+        content.truncate(MAX_BODY_LENGTH);
+        Ok((parts, content))
+    }
+
+    fn describe(target: &ProbeTarget, scheme: &str, ip: IpAddr, parts: &Parts, body: &[u8]) -> WebServiceForm {
+        let body = String::from_utf8_lossy(body);
+        let header = |name: &str| {
+            parts
+                .headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.chars().take(MAX_HEADER_LENGTH).collect::<String>())
+        };
+        let mut response_head = format!("{:?} {}", parts.version, parts.status);
+        for (name, value) in &parts.headers {
+            response_head.push_str(&format!("\n{}: {}", name, String::from_utf8_lossy(value.as_bytes())));
+        }
+        let status = parts.status.as_u16();
+
+        WebServiceForm {
+            url: target.url(scheme),
+            host: target.host.clone(),
+            ip_address: ip.to_string(),
+            status_code: status as i32,
+            title: Self::parse_title(&body),
+            server: header("server"),
+            content_type: header("content-type"),
+            redirect_location: header("location").filter(|_| parts.status.is_redirection()),
+            tls_subject: None,
+            tls_sans: Vec::new(),
+            technologies: Self::technologies(&parts.headers, &body),
+            missing_headers: Self::missing_headers(&parts.headers, scheme == "https", status),
+            response_head,
+        }
+    }
+
+    fn parse_title(body: &str) -> Option<String> {
+        // ASCII lowercasing keeps the byte offsets of the original.
+        let lower = body.to_ascii_lowercase();
+        let start = lower.find("<title")?;
+        let start = start + lower[start..].find('>')? + 1;
+        let end = start + lower[start..].find("</title")?;
+        let title: String = body[start..end]
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .chars()
+            .take(MAX_TITLE_LENGTH)
+            .collect();
+        Some(title).filter(|title| !title.is_empty())
+    }
+
+    /// Software named by the Server and X-Powered-By headers and the generator
+    /// meta tag, then known signatures.
+    fn technologies(headers: &HeaderMap, body: &str) -> Vec<String> {
+        let mut found: Vec<String> = Vec::new();
+        for name in ["server", "x-powered-by"] {
+            for value in headers.get_all(name).iter().filter_map(|value| value.to_str().ok()) {
+                // Apache/2.4.57 (Debian) OpenSSL/3.0.11
+                found.extend(
+                    value
+                        .split_whitespace()
+                        .filter(|token| !token.starts_with('(') && !token.ends_with(')'))
+                        .map(|token| token.trim_end_matches(',').to_string()),
+                );
+            }
+        }
+        found.extend(Self::generator(body));
+
+        let sets_cookie = |cookie: &str| {
+            headers
+                .get_all("set-cookie")
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .any(|value| value.starts_with(&format!("{}=", cookie)))
+        };
+        for (name, signature) in TECHNOLOGIES {
+            let matches = match signature {
+                Signature::Header(header) => headers.contains_key(*header),
+                Signature::Cookie(cookie) => sets_cookie(cookie),
+                Signature::Body(text) => body.contains(text),
+            };
+            if matches {
+                found.push(name.to_string());
+            }
+        }
+
+        let mut technologies: Vec<String> = Vec::new();
+        for technology in found {
+            if !technology.is_empty() && !technologies.contains(&technology) {
+                technologies.push(technology);
+            }
+        }
+        technologies
+    }
+
+    /// Content of `<meta name="generator" content="WordPress 6.4.2">`.
+    fn generator(body: &str) -> Option<String> {
+        let lower = body.to_ascii_lowercase();
+        lower.match_indices("<meta").find_map(|(start, _)| {
+            let end = start + lower[start..].find('>')?;
+            let tag = &lower[start..end];
+            if !tag.contains("name=\"generator\"") {
+                return None;
+            }
+            let content = start + tag.find("content=\"")? + "content=\"".len();
+            let length = body[content..end].find('"')?;
+            Some(body[content..content + length].trim().to_string())
+        })
+    }
+
+    /// Security headers a response should have but lacks. Redirects are
+    /// skipped, the page they lead to is what users see.
+    fn missing_headers(headers: &HeaderMap, tls: bool, status: u16) -> Vec<String> {
+        if (300..400).contains(&status) {
+            return Vec::new();
+        }
+        let frame_ancestors = headers
+            .get_all("content-security-policy")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .any(|value| value.to_ascii_lowercase().contains("frame-ancestors"));
+        SECURITY_HEADERS
+            .iter()
+            .filter(|header| tls || !header.https_only)
+            .filter(|header| !headers.contains_key(header.name))
+            .filter(|header| !(header.name == "x-frame-options" && frame_ancestors))
+            .map(|header| header.name.to_string())
+            .collect()
+    }
+
+    /// One issue per missing header, listing every server that lacks it.
+    pub fn to_issues(services: &[WebServiceForm]) -> Vec<IssueForm> {
+        let mut issues = Vec::new();
+        for service in services {
+            for missing in &service.missing_headers {
+                let Some(header) = SECURITY_HEADERS.iter().find(|header| header.name == missing) else {
+                    continue;
+                };
+                issues.push(IssueForm {
+                    name: format!("Missing {} header", header.title),
+                    description: Some(header.description.to_string()),
+                    mitigation: Some(header.mitigation.to_string()),
+                    cvss: Some(header.cvss),
+                    hosts: vec![HostForm {
+                        hostname: (service.host != service.ip_address).then(|| service.host.clone()),
+                        ip_address: service.ip_address.clone(),
+                        os: None,
+                        os_accuracy: None,
+                        services: Vec::new(),
+                        sources: Vec::new(),
+                        hostnames: Vec::new(),
+                    }],
+                    template_id: None,
+                    matched_at: Some(service.url.clone()),
+                    fingerprint: Some(format!("http-probe:missing-header:{}", header.name)),
+                    evidence: vec![EvidenceForm {
+                        description: format!("Response headers of {}", service.url),
+                        content: service.response_head.clone(),
+                        host: service.ip_address.clone(),
+                    }],
+                    classification: ClassificationForm {
+                        cwe_ids: vec!["CWE-693".to_string()],
+                        reference_urls: vec![header.reference.to_string()],
+                        tags: vec!["headers".to_string(), "misconfig".to_string()],
+                        ..Default::default()
+                    },
+                });
+            }
+        }
+        issues
+    }
+
+    fn to_result(output_file: String, services: Vec<WebServiceForm>) -> AnyScanResult {
+        AnyScanResult::WebServices(WebServicesScanResult {
+            output_file,
+            findings: Self::to_issues(&services),
+            services,
+        })
+    }
+}
+
+#[async_trait]
+impl VulnerabilityScanner for HttpProbeService {
+    fn name(&self) -> &'static str {
+        "http-probe"
+    }
+
+    fn capabilities(&self) -> Vec<ScannerCapability> {
+        vec![ScannerCapability::ServiceDetection]
+    }
+
+    fn options(&self) -> Vec<ScannerOption> {
+        vec![
+            ScannerOption {
+                name: "ports",
+                description: "Ports to probe on targets given without one, defaults to 80,443,8000,8080,8443",
+                kind: ScannerOptionKind::Ports,
+            },
+            ScannerOption {
+                name: "concurrency",
+                description: "Number of ports probed at the same time",
+                kind: ScannerOptionKind::Integer { min: 1, max: 1000 },
+            },
+            ScannerOption {
+                name: "timeout_ms",
+                description: "Milliseconds to wait for each step of a request",
+                kind: ScannerOptionKind::Integer { min: 100, max: 60000 },
+            },
+        ]
+    }
+
+    /// Runs in process, so it works where no scanner binary is installed.
+    fn is_installed(&self) -> bool {
+        true
+    }
+
+    async fn get_scan_result(&self, scan_id: Uuid) -> Result<AnyScanResult, Error> {
+        self.load_result(&self.scans_dir.join(scan_id.to_string()).join("scan.json"))
+    }
+
+    fn load_result(&self, output_file: &Path) -> Result<AnyScanResult, Error> {
+        let content = std::fs::read_to_string(output_file).map_err(|e| {
+            Error::IoError(format!("Failed to read result file: {}", e))
+        })?;
+        let services = serde_json::from_str(&content).map_err(|e| Error::ParseError(e.to_string()))?;
+        Ok(Self::to_result(output_file.display().to_string(), services))
+    }
+
+    async fn start_scan(
+        &self,
+        scan_id: Uuid,
+        target: &str,
+        options: &ScanOptions,
+    ) -> Result<AnyScanResult, Error> {
+        let targets = parse_targets(target)?;
+        let ports = match options.string("ports") {
+            Some(spec) => tcp_ports(spec),
+            None => DEFAULT_PORTS.to_vec(),
+        };
+        let probes = Self::expand_targets(&targets, &ports)?;
+        let connector = Self::tls_connector()?;
+        let concurrency = options.integer("concurrency").unwrap_or(DEFAULT_CONCURRENCY) as usize;
+        let wait = Duration::from_millis(options.integer("timeout_ms").unwrap_or(DEFAULT_TIMEOUT_MS) as u64);
+
+        let scan_path = self.scans_dir.join(scan_id.to_string());
+        tokio::fs::create_dir_all(&scan_path).await.map_err(|e| {
+            error!("Failed to create scan directory: {}", e);
+            Error::IoError(format!("Failed to create scan directory: {}", e))
+        })?;
+        let output_file = scan_path.join("scan.json").display().to_string();
+
+        debug!("Probe {} ports with {} connections", probes.len(), concurrency);
+        let total = probes.len().max(1) as u64;
+        let mut results = stream::iter(probes)
+            .map(move |target| {
+                let connector = connector.clone();
+                async move { Self::probe(target, &connector, wait).await }
+            })
+            .buffer_unordered(concurrency);
+
+        let mut services: BTreeMap<String, WebServiceForm> = BTreeMap::new();
+        let (mut done, mut findings, mut reported) = (0u64, 0u64, 0u64);
+        while let Some(service) = results.next().await {
+            // Dropping the stream abandons the requests in flight.
+            if self.processes.is_cancelled(scan_id) {
+                break;
+            }
+            done += 1;
+            if let Some(service) = service {
+                findings += service.missing_headers.len() as u64;
+                services.insert(service.url.clone(), service);
+            }
+            let percent = done * 100 / total;
+            if percent > reported {
+                reported = percent;
+                self.processes.publish_progress(scan_id, ScanProgress {
+                    percent: Some(percent as f64),
+                    hosts: Some(services.len() as u64),
+                    findings: Some(findings),
+                });
+            }
+        }
+
+        let services: Vec<WebServiceForm> = services.into_values().collect();
+        let content = serde_json::to_string_pretty(&services).map_err(|e| Error::ParseError(e.to_string()))?;
+        std::fs::write(&output_file, content).map_err(|e| {
+            error!("Failed to write result file: {}", e);
+            Error::IoError(format!("Failed to write result file: {}", e))
+        })?;
+        if self.processes.take_cancelled(scan_id) {
+            return Err(Error::Cancelled(output_file));
+        }
+        Ok(Self::to_result(output_file, services))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+    use http_body_util::Full;
+    use hyper::server::conn::http1 as server;
+    use hyper::service::service_fn;
+    use hyper::Response;
+    use tokio::net::TcpListener;
+    use tokio_rustls::rustls::pki_types::PrivatePkcs8KeyDer;
+    use tokio_rustls::rustls::ServerConfig;
+    use tokio_rustls::TlsAcceptor;
+
+    use super::*;
+
+    const PAGE: &str = "<html><head><meta name=\"generator\" content=\"WordPress 6.4.2\">\
+        <TITLE>\n  Example   Blog </TITLE><link href=\"/wp-content/themes/a.css\"></head></html>";
+
+    /// Serves every request on 127.0.0.1 with `PAGE` and the given headers,
+    /// over TLS when an acceptor is given.
+    async fn serve(headers: &'static [(&'static str, &'static str)], tls: Option<TlsAcceptor>) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let tls = tls.clone();
+                tokio::spawn(async move {
+                    let service = service_fn(|_request| async {
+                        let mut response = Response::builder();
+                        for (name, value) in headers {
+                            response = response.header(*name, *value);
+                        }
+                        Ok::<_, Infallible>(response.body(Full::new(Bytes::from(PAGE))).unwrap())
+                    });
+                    let connection = server::Builder::new();
+                    let _ = match tls {
+                        Some(tls) => match tls.accept(stream).await {
+                            Ok(stream) => connection.serve_connection(TokioIo::new(stream), service).await,
+                            Err(_) => return,
+                        },
+                        None => connection.serve_connection(TokioIo::new(stream), service).await,
+                    };
+                });
+            }
+        });
+        port
+    }
+
+    fn tls_acceptor() -> TlsAcceptor {
+        let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let key = PrivatePkcs8KeyDer::from(certified.key_pair.serialize_der());
+        let config = ServerConfig::builder_with_provider(Arc::new(crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_no_client_auth()
+            .with_single_cert(vec![certified.cert.der().clone()], key.into())
+            .unwrap();
+        TlsAcceptor::from(Arc::new(config))
+    }
+
+    fn scan_options() -> ScanOptions {
+        ScanOptions::validate(
+            &HttpProbeService::new("/tmp", ProcessRegistry::default()).options(),
+            serde_json::json!({ "timeout_ms": 2000 }).as_object().unwrap().clone(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_expand_targets() {
+        let targets = parse_targets("https://example.com:8443/app 10.0.0.5:8080 [::1]:443 10.0.0.0/31").unwrap();
+        let probes = HttpProbeService::expand_targets(&targets, &[80, 443]).unwrap();
+        let urls: Vec<String> = probes.iter().map(|probe| probe.url(probe.schemes[0])).collect();
+        assert_eq!(urls, vec![
+            "https://example.com:8443/app",
+            "https://10.0.0.5:8080/",
+            "https://[::1]/",
+            "https://10.0.0.0:80/",
+            "https://10.0.0.0/",
+            "https://10.0.0.1:80/",
+            "https://10.0.0.1/",
+        ]);
+        assert_eq!(probes[0].schemes, vec!["https"]);
+        assert_eq!(probes[1].schemes, vec!["https", "http"]);
+        assert_eq!(probes[1].url("http"), "http://10.0.0.5:8080/");
+
+        assert!(HttpProbeService::expand_targets(&parse_targets("ftp://example.com").unwrap(), &[80]).is_err());
+        assert!(HttpProbeService::expand_targets(&parse_targets("example.com:http").unwrap(), &[80]).is_err());
+        assert!(HttpProbeService::expand_targets(&parse_targets("10.0.0.0/8").unwrap(), &[80]).is_err());
+    }
+
+    #[test]
+    fn test_page_details() {
+        assert_eq!(HttpProbeService::parse_title(PAGE).as_deref(), Some("Example Blog"));
+        assert_eq!(HttpProbeService::parse_title("<title></title>"), None);
+
+        let mut headers = HeaderMap::new();
+        headers.insert("server", "Apache/2.4.57 (Debian) OpenSSL/3.0.11".parse().unwrap());
+        headers.insert("set-cookie", "PHPSESSID=abc; path=/".parse().unwrap());
+        headers.insert("content-security-policy", "frame-ancestors 'none'".parse().unwrap());
+        assert_eq!(HttpProbeService::technologies(&headers, PAGE), vec![
+            "Apache/2.4.57", "OpenSSL/3.0.11", "WordPress 6.4.2", "PHP", "WordPress",
+        ]);
+
+        assert_eq!(HttpProbeService::missing_headers(&headers, false, 200), vec![
+            "x-content-type-options", "referrer-policy", "permissions-policy",
+        ]);
+        assert_eq!(HttpProbeService::missing_headers(&headers, true, 200)[0], "strict-transport-security");
+        assert!(HttpProbeService::missing_headers(&headers, true, 301).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_probe_local_servers() {
+        let http = serve(&[("server", "hyper"), ("x-frame-options", "DENY")], None).await;
+        let https = serve(&[("strict-transport-security", "max-age=31536000")], Some(tls_acceptor())).await;
+        let closed = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap().port();
+
+        let scans_dir = std::env::temp_dir().join(format!("http-probe-{}", Uuid::new_v4()));
+        let scanner = HttpProbeService::new(&scans_dir, ProcessRegistry::default());
+        let target = format!("127.0.0.1:{} localhost:{} 127.0.0.1:{}", http, https, closed);
+        let AnyScanResult::WebServices(result) =
+            scanner.start_scan(Uuid::new_v4(), &target, &scan_options()).await.unwrap()
+        else {
+            panic!("expected web services");
+        };
+        assert_eq!(result.services.len(), 2);
+
+        // The plain HTTP server fails the TLS handshake and is probed again.
+        let plain = result.services.iter().find(|service| service.host == "127.0.0.1").unwrap();
+        assert_eq!(plain.url, format!("http://127.0.0.1:{}/", http));
+        assert_eq!(plain.status_code, 200);
+        assert_eq!(plain.title.as_deref(), Some("Example Blog"));
+        assert_eq!(plain.server.as_deref(), Some("hyper"));
+        assert!(plain.technologies.contains(&"WordPress".to_string()));
+        assert_eq!(plain.tls_subject, None);
+        assert!(!plain.missing_headers.contains(&"x-frame-options".to_string()));
+        assert!(plain.response_head.starts_with("HTTP/1.1 200 OK"));
+
+        let tls = result.services.iter().find(|service| service.host == "localhost").unwrap();
+        assert_eq!(tls.url, format!("https://localhost:{}/", https));
+        assert_eq!(tls.ip_address, "127.0.0.1");
+        assert_eq!(tls.tls_subject.as_deref(), Some("CN=rcgen self signed cert"));
+        assert_eq!(tls.tls_sans, vec!["localhost"]);
+        assert!(!tls.missing_headers.contains(&"strict-transport-security".to_string()));
+        assert!(tls.missing_headers.contains(&"x-frame-options".to_string()));
+
+        let csp = result
+            .findings
+            .iter()
+            .filter(|issue| issue.fingerprint.as_deref() == Some("http-probe:missing-header:content-security-policy"))
+            .count();
+        assert_eq!(csp, 2);
+
+        let AnyScanResult::WebServices(loaded) = scanner.load_result(Path::new(&result.output_file)).unwrap() else {
+            panic!("expected web services");
+        };
+        assert_eq!(loaded.services.len(), 2);
+        assert_eq!(loaded.findings.len(), result.findings.len());
+        std::fs::remove_dir_all(scans_dir).unwrap();
+    }
+}
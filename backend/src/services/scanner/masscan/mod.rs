@@ -0,0 +1,2 @@
+pub mod service;
+pub use service::MasscanService;
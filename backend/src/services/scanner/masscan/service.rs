@@ -0,0 +1,432 @@
+use std::path::{Path, PathBuf};
+use async_trait::async_trait;
+use log::error;
+use quick_xml::de::from_str;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::dtos::handlers::{HostForm, ServiceForm};
+use crate::services::scanner::types::{
+    AnyScanResult, Error, HostsScanResult, ScanOptions, ScannerCapability, ScannerOption,
+    ScannerOptionKind,
+};
+use crate::services::scanner::events::ScanProgress;
+use crate::services::scanner::process::ProcessRegistry;
+use crate::services::scanner::{is_binary_installed, parse_targets, VulnerabilityScanner};
+use crate::services::scope::IpRange;
+
+/// Ports scanned when neither `ports` nor `top_ports` is given.
+const DEFAULT_TOP_PORTS: i64 = 1000;
+
+/// One line of `-oJ` output. masscan writes a record per open port and
+/// another one per grabbed banner.
+#[derive(Debug, Deserialize)]
+struct Record {
+    ip: String,
+    #[serde(default)]
+    ports: Vec<RecordPort>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RecordPort {
+    port: i32,
+    proto: String,
+    status: Option<String>,
+    service: Option<Banner>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Banner {
+    #[serde(rename(deserialize = "@name"), alias = "name")]
+    name: String,
+    #[serde(rename(deserialize = "@banner"), alias = "banner", default)]
+    banner: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct XmlRun {
+    #[serde(rename = "host", default)]
+    hosts: Vec<XmlHost>,
+}
+
+#[derive(Debug, Deserialize)]
+struct XmlHost {
+    address: XmlAddress,
+    ports: XmlPorts,
+}
+
+#[derive(Debug, Deserialize)]
+struct XmlAddress {
+    #[serde(rename = "@addr")]
+    addr: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct XmlPorts {
+    #[serde(rename = "port", default)]
+    ports: Vec<XmlPort>,
+}
+
+#[derive(Debug, Deserialize)]
+struct XmlPort {
+    #[serde(rename = "@protocol")]
+    protocol: String,
+    #[serde(rename = "@portid")]
+    port_id: i32,
+    state: XmlState,
+    service: Option<Banner>,
+}
+
+#[derive(Debug, Deserialize)]
+struct XmlState {
+    #[serde(rename = "@state")]
+    state: String,
+}
+
+#[derive(Clone)]
+pub struct MasscanService {
+    scans_dir: PathBuf,
+    processes: ProcessRegistry,
+}
+
+impl MasscanService {
+    pub fn new(scans_dir: impl AsRef<Path>, processes: ProcessRegistry) -> Self {
+        Self {
+            scans_dir: scans_dir.as_ref().to_path_buf(),
+            processes,
+        }
+    }
+
+    /// masscan only understands addresses, so host names are rejected here
+    /// instead of failing the scan later.
+    fn check_targets(targets: &[String]) -> Result<(), Error> {
+        match targets.iter().find(|target| IpRange::parse(target).is_none()) {
+            Some(target) => Err(Error::InvalidTarget(format!(
+                "{}: masscan accepts only IP addresses, ranges and CIDR blocks",
+                target
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Translates validated scan options into masscan arguments.
+    fn build_args(options: &ScanOptions, output_file: &str, targets: &[String]) -> Vec<String> {
+        let mut args = vec!["-oJ".to_string(), output_file.to_string(), "--ndjson-status".to_string()];
+        match (options.string("ports"), options.integer("top_ports")) {
+            (Some(ports), _) => args.extend(["-p".to_string(), ports.to_string()]),
+            (None, top_ports) => args.extend([
+                "--top-ports".to_string(),
+                top_ports.unwrap_or(DEFAULT_TOP_PORTS).to_string(),
+            ]),
+        }
+        if let Some(rate) = options.integer("rate") {
+            args.extend(["--rate".to_string(), rate.to_string()]);
+        }
+        if options.flag("banners") {
+            args.push("--banners".to_string());
+        }
+        args.extend(targets.iter().cloned());
+        args
+    }
+
+    /// Reads `--ndjson-status` lines such as
+    /// `{"state":"*","rate":{"kpps":1.00,"pps":1000.00},"progress":{"percent":45.23,"seconds":12,"found":3,...}}`.
+    fn parse_progress(line: &str) -> Option<ScanProgress> {
+        let status: serde_json::Value = serde_json::from_str(line).ok()?;
+        let progress = status.get("progress")?;
+        Some(ScanProgress {
+            percent: Some(progress.get("percent")?.as_f64()?),
+            hosts: None,
+            findings: progress.get("found").and_then(|found| found.as_u64()),
+        })
+    }
+
+    /// Parses masscan output written with `-oJ`, `-oD` (ndjson) or `-oX`.
+    pub fn parse_output(content: &str) -> Result<Vec<HostForm>, Error> {
+        let trimmed = content.trim_start();
+        if trimmed.starts_with("<?xml") || trimmed.starts_with("<nmaprun") {
+            return Self::parse_xml(content);
+        }
+
+        let mut hosts = Vec::new();
+        // Records are written one per line, separated by lines holding a
+        // single comma. Older versions also leave a comma before the closing
+        // bracket, so the array is not read as a whole.
+        for line in content.lines() {
+            let line = line.trim().trim_start_matches(',').trim_end_matches(',').trim();
+            if line.is_empty() || line == "[" || line == "]" {
+                continue;
+            }
+            let record: Record =
+                serde_json::from_str(line).map_err(|e| Error::ParseError(e.to_string()))?;
+            for port in record.ports {
+                let state = port.status.unwrap_or_else(|| "open".to_string());
+                Self::add_port(&mut hosts, &record.ip, port.port, &port.proto, state, port.service);
+            }
+        }
+        Ok(hosts)
+    }
+
+    fn parse_xml(content: &str) -> Result<Vec<HostForm>, Error> {
+        let run: XmlRun = from_str(content)?;
+        let mut hosts = Vec::new();
+        for host in run.hosts {
+            for port in host.ports.ports {
+                Self::add_port(
+                    &mut hosts,
+                    &host.address.addr,
+                    port.port_id,
+                    &port.protocol,
+                    port.state.state,
+                    port.service,
+                );
+            }
+        }
+        Ok(hosts)
+    }
+
+    /// Adds a port of a record to its host. Banner records only fill in the
+    /// service of a port reported before.
+    fn add_port(
+        hosts: &mut Vec<HostForm>,
+        ip: &str,
+        port: i32,
+        protocol: &str,
+        state: String,
+        banner: Option<Banner>,
+    ) {
+        let index = match hosts.iter().position(|host| host.ip_address == ip) {
+            Some(index) => index,
+            None => {
+                hosts.push(HostForm {
+                    hostname: None,
+                    ip_address: ip.to_string(),
+                    os: None,
+                    os_accuracy: None,
+                    services: Vec::new(),
+                    sources: Vec::new(),
+                    hostnames: Vec::new(),
+                });
+                hosts.len() - 1
+            },
+        };
+        let services = &mut hosts[index].services;
+        let index = match services
+            .iter()
+            .position(|service| service.port == port && service.protocol == protocol)
+        {
+            Some(index) => index,
+            None => {
+                services.push(ServiceForm {
+                    port,
+                    protocol: protocol.to_string(),
+                    state,
+                    service_name: None,
+                    product: None,
+                    version: None,
+                    extra_info: None,
+                    cpe: None,
+                });
+                services.len() - 1
+            },
+        };
+        let service = &mut services[index];
+        if let Some(banner) = banner {
+            // Banner names look like "http.server" or "ssh": the part before
+            // the dot is the protocol.
+            let (name, detail) = banner.name.split_once('.').unwrap_or((&banner.name, ""));
+            if name != "title" {
+                service.service_name.get_or_insert_with(|| name.to_string());
+            }
+            match (detail, banner.banner) {
+                ("server", Some(server)) => service.product = Some(server),
+                (_, Some(text)) if service.extra_info.is_none() => service.extra_info = Some(text),
+                _ => {},
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl VulnerabilityScanner for MasscanService {
+    fn name(&self) -> &'static str {
+        "masscan"
+    }
+
+    fn capabilities(&self) -> Vec<ScannerCapability> {
+        vec![ScannerCapability::PortScan]
+    }
+
+    fn options(&self) -> Vec<ScannerOption> {
+        vec![
+            ScannerOption {
+                name: "ports",
+                description: "Ports to scan, U: prefixes UDP ports",
+                kind: ScannerOptionKind::Ports,
+            },
+            ScannerOption {
+                name: "top_ports",
+                description: "Scan the N most common ports",
+                kind: ScannerOptionKind::Integer { min: 1, max: 65535 },
+            },
+            ScannerOption {
+                name: "rate",
+                description: "Packets per second to send",
+                kind: ScannerOptionKind::Integer { min: 1, max: 10_000_000 },
+            },
+            ScannerOption {
+                name: "banners",
+                description: "Grab banners of open TCP ports",
+                kind: ScannerOptionKind::Boolean,
+            },
+        ]
+    }
+
+    fn is_installed(&self) -> bool {
+        is_binary_installed("masscan")
+    }
+
+    async fn get_scan_result(&self, scan_id: Uuid) -> Result<AnyScanResult, Error> {
+        self.load_result(&self.scans_dir.join(scan_id.to_string()).join("scan.json"))
+    }
+
+    fn load_result(&self, output_file: &Path) -> Result<AnyScanResult, Error> {
+        let content = std::fs::read_to_string(output_file).map_err(|e| {
+            Error::IoError(format!("Failed to read result file: {}", e))
+        })?;
+
+        Ok(AnyScanResult::Hosts(HostsScanResult {
+            output_file: output_file.display().to_string(),
+            hosts: Self::parse_output(&content)?,
+        }))
+    }
+
+    async fn start_scan(
+        &self,
+        scan_id: Uuid,
+        target: &str,
+        options: &ScanOptions,
+    ) -> Result<AnyScanResult, Error> {
+        let targets = parse_targets(target)?;
+        Self::check_targets(&targets)?;
+        let scan_path = format!("{}/{}", self.scans_dir.display(), scan_id);
+        tokio::fs::create_dir_all(&scan_path).await.map_err(|e| {
+            error!("Failed to create scan directory: {}", e);
+            Error::IoError(format!("Failed to create scan directory: {}", e))
+        })?;
+
+        let output_file = format!("{}/scan.json", scan_path);
+
+        let mut command = tokio::process::Command::new("masscan");
+        command.args(Self::build_args(options, &output_file, &targets));
+        self.processes
+            .run(scan_id, command, &output_file, Self::parse_progress)
+            .await?
+            .check("masscan")?;
+
+        // masscan doesn't create the output file when nothing was found.
+        let content = match tokio::fs::read_to_string(&output_file).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => {
+                error!("Failed to read result file: {}", e);
+                return Err(Error::IoError(format!("Failed to read result file: {}", e)));
+            },
+        };
+
+        let hosts = Self::parse_output(&content).map_err(|e| {
+            error!("Failed to parse result file: {}", e);
+            e
+        })?;
+
+        Ok(AnyScanResult::Hosts(HostsScanResult { output_file, hosts }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn fixture(name: &str) -> String {
+        fs::read_to_string(format!("{}/tests/fixtures/masscan/{}", env!("CARGO_MANIFEST_DIR"), name))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_parse_json() {
+        let hosts = MasscanService::parse_output(&fixture("scan.json")).unwrap();
+        assert_eq!(hosts.len(), 2);
+
+        let router = hosts.iter().find(|host| host.ip_address == "192.168.1.254").unwrap();
+        let ports: Vec<(i32, &str)> = router
+            .services
+            .iter()
+            .map(|service| (service.port, service.protocol.as_str()))
+            .collect();
+        assert_eq!(ports, vec![(22, "tcp"), (80, "tcp"), (53, "udp")]);
+
+        let ssh = &router.services[0];
+        assert_eq!(ssh.state, "open");
+        assert_eq!(ssh.service_name.as_deref(), Some("ssh"));
+        assert_eq!(ssh.extra_info.as_deref(), Some("SSH-2.0-OpenSSH_8.9p1 Ubuntu-3ubuntu0.6"));
+
+        let http = &router.services[1];
+        assert_eq!(http.service_name.as_deref(), Some("http"));
+        assert_eq!(http.product.as_deref(), Some("nginx/1.18.0"));
+    }
+
+    #[test]
+    fn test_parse_xml_matches_json() {
+        let hosts = MasscanService::parse_output(&fixture("scan.xml")).unwrap();
+        assert_eq!(hosts.len(), 2);
+        assert_eq!(hosts[0].ip_address, "192.168.1.254");
+        assert_eq!(hosts[0].services.len(), 2);
+        assert_eq!(hosts[0].services[1].product.as_deref(), Some("nginx/1.18.0"));
+        assert_eq!(hosts[1].services[0].port, 443);
+    }
+
+    #[test]
+    fn test_parse_trailing_comma_and_empty_output() {
+        let content = "[\n{\"ip\": \"10.0.0.1\", \"ports\": [{\"port\": 80, \"proto\": \"tcp\", \"status\": \"open\"}]},\n]\n";
+        assert_eq!(MasscanService::parse_output(content).unwrap().len(), 1);
+        assert!(MasscanService::parse_output("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_build_args() {
+        let options = ScanOptions::validate(
+            &MasscanService::new("/tmp", ProcessRegistry::default()).options(),
+            serde_json::json!({ "ports": "1-65535,U:53", "rate": 10000 })
+                .as_object()
+                .unwrap()
+                .clone(),
+        )
+        .unwrap();
+        let targets = parse_targets("10.0.0.0/16").unwrap();
+
+        let args = MasscanService::build_args(&options, "/tmp/scan.json", &targets);
+        assert_eq!(args, vec![
+            "-oJ", "/tmp/scan.json", "--ndjson-status", "-p", "1-65535,U:53", "--rate", "10000",
+            "10.0.0.0/16",
+        ]);
+
+        let args = MasscanService::build_args(&ScanOptions::default(), "/tmp/scan.json", &targets);
+        assert_eq!(&args[3..5], ["--top-ports", "1000"]);
+
+        assert!(MasscanService::check_targets(&targets).is_ok());
+        assert!(MasscanService::check_targets(&parse_targets("10.0.0.1-10.0.0.9 example.com").unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_parse_progress() {
+        let status = r#"{"state":"*","rate":{"kpps":1.00,"pps":1000.00},"progress":{"percent":45.23,"seconds":12,"found":3,"syn":{"sent":4523,"total":10000,"remaining":5477}}}"#;
+        assert_eq!(MasscanService::parse_progress(status), Some(ScanProgress {
+            percent: Some(45.23),
+            hosts: None,
+            findings: Some(3),
+        }));
+        assert!(MasscanService::parse_progress("Starting masscan 1.3.2").is_none());
+    }
+}
@@ -1,64 +1,191 @@
+pub mod diff;
+pub mod events;
+pub mod ffuf;
+pub mod http_probe;
+pub mod masscan;
 pub mod nmap;
 pub mod nuclei;
+pub mod process;
+pub mod queue;
+pub mod scheduler;
+pub mod subfinder;
+pub mod tcp;
+pub mod testssl;
 pub mod traits;
 pub mod types;
 
+use std::collections::BTreeMap;
+use std::env;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use serde::Serialize;
+use tokio::sync::broadcast;
 use uuid::Uuid;
-use crate::db::Pool;
 use crate::utils::config::AppConfig;
 
 pub use traits::VulnerabilityScanner;
+use crate::services::scanner::ffuf::FfufService;
+use crate::services::scanner::http_probe::HttpProbeService;
+use crate::services::scanner::masscan::MasscanService;
 use crate::services::scanner::nmap::NmapService;
 use crate::services::scanner::nuclei::NucleiService;
-use crate::services::scanner::types::AnyScanResult;
-use crate::utils::errors::AppError;
+use crate::services::scanner::events::{ScanEvent, ScanEvents};
+use crate::services::scanner::process::ProcessRegistry;
+use crate::services::scanner::subfinder::SubfinderService;
+use crate::services::scanner::tcp::TcpConnectService;
+use crate::services::scanner::testssl::TestsslService;
+use crate::services::scanner::types::{Error, ScannerCapability, ScannerOption};
 
+/// Registry of the available scanners keyed by their name.
 pub struct ScannerService {
-    pub nmap: Arc<Mutex<nmap::NmapService>>,
-    pub nuclei: Arc<Mutex<nuclei::NucleiService>>,
+    scanners: BTreeMap<&'static str, Arc<dyn VulnerabilityScanner>>,
+    processes: ProcessRegistry,
+    events: ScanEvents,
 }
 
-pub enum Scanner {
-    Nmap(Arc<Mutex<NmapService>>),
-    Nuclei(Arc<Mutex<NucleiService>>),
+#[derive(Debug, Serialize)]
+pub struct ScannerInfo {
+    pub name: &'static str,
+    pub installed: bool,
+    pub capabilities: Vec<ScannerCapability>,
+    pub options: Vec<ScannerOption>,
 }
 
-impl Scanner {
-    pub async fn start_scan(&self, scan_id: Uuid, target: &str) -> Result<AnyScanResult, AppError> {
-        match self {
-            Self::Nmap(scanner) => {
-                let mut scanner = scanner.lock().await;
-                scanner.start_scan(scan_id, target).await
-            },
-            Self::Nuclei(scanner) => {
-                let mut scanner = scanner.lock().await;
-                scanner.start_scan(scan_id, target).await
-            }
+impl Default for ScannerService {
+    /// A registry without scanners.
+    fn default() -> Self {
+        let events = ScanEvents::default();
+        Self {
+            scanners: BTreeMap::new(),
+            processes: ProcessRegistry::new(events.clone()),
+            events,
         }
     }
 }
 
-
 impl ScannerService {
-    pub fn new(config: &AppConfig, pool: Pool) -> Self {
-        Self {
-            nmap: Arc::new(Mutex::new(nmap::NmapService::new(
-                config.scans_path.clone(),
-                pool,
-            ))),
-            nuclei: Arc::new(Mutex::new(nuclei::NucleiService::new(
-                config.scans_path.clone(),
-            ))),
+    pub fn new(config: &AppConfig) -> Self {
+        let mut service = Self::default();
+        let processes = service.processes.clone();
+        service.register(Arc::new(NmapService::new(
+            config.scans_path.clone(),
+            processes.clone(),
+        )));
+        service.register(Arc::new(MasscanService::new(
+            config.scans_path.clone(),
+            processes.clone(),
+        )));
+        service.register(Arc::new(TcpConnectService::new(
+            config.scans_path.clone(),
+            processes.clone(),
+        )));
+        service.register(Arc::new(SubfinderService::new(
+            config.scans_path.clone(),
+            processes.clone(),
+        )));
+        service.register(Arc::new(TestsslService::new(
+            config.scans_path.clone(),
+            processes.clone(),
+        )));
+        service.register(Arc::new(FfufService::new(
+            config.scans_path.clone(),
+            config.wordlists_path.clone(),
+            processes.clone(),
+        )));
+        service.register(Arc::new(HttpProbeService::new(
+            config.scans_path.clone(),
+            processes.clone(),
+        )));
+        service.register(Arc::new(NucleiService::new(
+            config.scans_path.clone(),
+            config.nuclei_templates_path.clone(),
+            processes,
+        )));
+        service
+    }
+
+    pub fn register(&mut self, scanner: Arc<dyn VulnerabilityScanner>) {
+        self.scanners.insert(scanner.name(), scanner);
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<dyn VulnerabilityScanner>> {
+        self.scanners.get(name).cloned()
+    }
+
+    /// Scanner that reads the results of scans of `scanner_type`. Imported
+    /// results are read by the scanner that produced them.
+    pub fn result_reader(&self, scanner_type: &str) -> Option<Arc<dyn VulnerabilityScanner>> {
+        match scanner_type {
+            "nmap-import" => self.get("nmap"),
+            "nuclei-import" => self.get("nuclei"),
+            name => self.get(name),
         }
     }
 
-    pub async fn get_nuclei(&self) -> Arc<Mutex<nuclei::NucleiService>> {
-        self.nuclei.clone()
+    pub fn names(&self) -> Vec<&'static str> {
+        self.scanners.keys().copied().collect()
+    }
+
+    pub fn cancel(&self, scan_id: Uuid) {
+        self.processes.cancel(scan_id);
     }
 
-    pub async fn get_nmap(&self) -> Arc<Mutex<nmap::NmapService>> {
-        self.nmap.clone()
+    /// Drops a cancellation that came in after the process of the scan exited.
+    pub fn forget_cancellation(&self, scan_id: Uuid) {
+        self.processes.take_cancelled(scan_id);
+    }
+
+    pub fn subscribe(&self, scan_id: Uuid) -> broadcast::Receiver<ScanEvent> {
+        self.events.subscribe(scan_id)
+    }
+
+    pub fn publish(&self, scan_id: Uuid, event: ScanEvent) {
+        self.events.publish(scan_id, event);
+    }
+
+    pub fn release(&self, scan_id: Uuid) {
+        self.events.release(scan_id);
+    }
+
+    pub fn finish(&self, scan_id: Uuid, status: String) {
+        self.events.finish(scan_id, status);
+    }
+
+    pub fn list(&self) -> Vec<ScannerInfo> {
+        self.scanners
+            .values()
+            .map(|scanner| ScannerInfo {
+                name: scanner.name(),
+                installed: scanner.is_installed(),
+                capabilities: scanner.capabilities(),
+                options: scanner.options(),
+            })
+            .collect()
+    }
+}
+
+/// Looks the binary up in `PATH` the same way the shell would.
+pub fn is_binary_installed(binary: &str) -> bool {
+    env::var_os("PATH")
+        .map(|paths| env::split_paths(&paths).any(|dir| dir.join(binary).is_file()))
+        .unwrap_or(false)
+}
+
+/// Splits a space or comma separated target list into separate process
+/// arguments, rejecting anything that could be read as a flag.
+pub fn parse_targets(target: &str) -> Result<Vec<String>, Error> {
+    let targets: Vec<String> = target
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|target| !target.is_empty())
+        .map(String::from)
+        .collect();
+    if targets.is_empty() {
+        return Err(Error::InvalidTarget("target is empty".to_string()));
+    }
+    match targets
+        .iter()
+        .find(|target| target.starts_with('-') || target.chars().any(char::is_control))
+    {
+        Some(target) => Err(Error::InvalidTarget(target.clone())),
+        None => Ok(targets),
     }
 }
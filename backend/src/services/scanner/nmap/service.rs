@@ -1,46 +1,21 @@
-use std::collections::HashMap;
-
+use std::borrow::Cow;
 use std::path::{Path, PathBuf};
-use std::process::Stdio;
-use std::sync::Arc;
 use async_trait::async_trait;
 use log::error;
 use quick_xml::de::from_str;
 use quick_xml::DeError;
-use serde::{Deserialize, Serialize};
-use tokio::sync::Mutex;
+use serde::Deserialize;
 use uuid::Uuid;
 
 
-use crate::services::scanner::types::{AnyScanResult, Error};
-use crate::services::scanner::VulnerabilityScanner;
-use crate::db::Pool;
-use crate::utils::errors::AppError;
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum ScanStatus {
-    Pending,
-    Running,
-    Completed,
-    Failed(String),
-}
-
-impl From<ScanStatus> for String {
-    fn from(status: ScanStatus) -> String {
-        match status {
-            ScanStatus::Pending => "pending".to_string(),
-            ScanStatus::Running => "running".to_string(),
-            ScanStatus::Completed => "completed".to_string(),
-            ScanStatus::Failed(_) => "failed".to_string(),
-        }
-    }
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ScanState {
-    pub status: ScanStatus,
-    pub result: Option<NmapScanResult>,
-}
+use crate::dtos::handlers::{HostForm, ServiceForm};
+use crate::services::scanner::types::{
+    AnyScanResult, Error, HostsScanResult, ScanOptions, ScannerCapability, ScannerOption,
+    ScannerOptionKind,
+};
+use crate::services::scanner::events::ScanProgress;
+use crate::services::scanner::process::ProcessRegistry;
+use crate::services::scanner::{is_binary_installed, parse_targets, VulnerabilityScanner};
 
 #[derive(Debug, Deserialize)]
 struct Address {
@@ -62,6 +37,113 @@ struct Host {
     status: Status,
     #[serde(rename = "address", default)]
     addresses: Vec<Address>,
+    #[serde(rename = "hostnames", default)]
+    hostnames: Option<Hostnames>,
+    #[serde(rename = "ports", default)]
+    ports: Option<Ports>,
+    #[serde(rename = "os", default)]
+    os: Option<Os>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Hostnames {
+    #[serde(rename = "hostname", default)]
+    hostnames: Vec<Hostname>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Hostname {
+    #[serde(rename = "@name")]
+    name: String,
+    #[serde(rename = "@type", default)]
+    name_type: Option<String>,
+}
+
+impl Hostnames {
+    /// Prefers the name the target was specified with over reverse DNS results.
+    fn preferred(self) -> Option<String> {
+        let mut hostnames = self.hostnames;
+        hostnames.sort_by_key(|hostname| match hostname.name_type.as_deref() {
+            Some("user") => 0,
+            Some("PTR") => 1,
+            _ => 2,
+        });
+        hostnames.into_iter().next().map(|hostname| hostname.name)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Os {
+    #[serde(rename = "osmatch", default)]
+    matches: Vec<OsMatch>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OsMatch {
+    #[serde(rename = "@name")]
+    name: String,
+    #[serde(rename = "@accuracy")]
+    accuracy: i32,
+}
+
+impl Os {
+    fn best_match(self) -> Option<OsMatch> {
+        self.matches.into_iter().max_by_key(|os_match| os_match.accuracy)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Ports {
+    #[serde(rename = "port", default)]
+    ports: Vec<Port>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Port {
+    #[serde(rename = "@protocol")]
+    protocol: String,
+    #[serde(rename = "@portid")]
+    port_id: i32,
+    #[serde(rename = "state")]
+    state: Status,
+    #[serde(rename = "service", default)]
+    service: Option<Service>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Service {
+    #[serde(rename = "@name", default)]
+    name: Option<String>,
+    #[serde(rename = "@product", default)]
+    product: Option<String>,
+    #[serde(rename = "@version", default)]
+    version: Option<String>,
+    #[serde(rename = "@extrainfo", default)]
+    extra_info: Option<String>,
+    #[serde(rename = "cpe", default)]
+    cpe: Vec<String>,
+}
+
+impl From<Port> for ServiceForm {
+    fn from(port: Port) -> Self {
+        let service = port.service.unwrap_or(Service {
+            name: None,
+            product: None,
+            version: None,
+            extra_info: None,
+            cpe: Vec::new(),
+        });
+        ServiceForm {
+            port: port.port_id,
+            protocol: port.protocol,
+            state: port.state.state,
+            service_name: service.name,
+            product: service.product,
+            version: service.version,
+            extra_info: service.extra_info,
+            cpe: if service.cpe.is_empty() { None } else { Some(service.cpe.join(", ")) },
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -83,113 +165,357 @@ struct NmapRun {
 #[derive(Clone)]
 pub struct NmapService {
     scans_dir: PathBuf,
-    active_scans: Arc<Mutex<HashMap<String, ScanState>>>,
-    pool: Pool,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct NmapScanRequest {
-    pub target: String,
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct NmapScanResult {
-    pub output_file: String,
-    pub hosts: Vec<String>,
+    processes: ProcessRegistry,
 }
 
 impl NmapService {
-    pub fn new(scans_dir: impl AsRef<Path>, pool: Pool) -> Self {
+    pub fn new(scans_dir: impl AsRef<Path>, processes: ProcessRegistry) -> Self {
         Self {
             scans_dir: scans_dir.as_ref().to_path_buf(),
-            active_scans: Arc::new(Mutex::new(HashMap::new())),
-            pool,
+            processes,
         }
     }
 
-    pub fn parse_up_hosts(xml_content: &str) -> Result<Vec<String>, DeError> {
-        let nmap_run: NmapRun = from_str(xml_content)?;
+    /// nmap runs any `--script` entry that looks like a file, relative to its
+    /// working directory, so only script names and categories are accepted.
+    fn check_scripts(options: &ScanOptions) -> Result<(), Error> {
+        match options.list("scripts").into_iter().find(|script| script.contains(['/', '.'])) {
+            Some(script) => Err(Error::InvalidOption(format!(
+                "scripts: {} is not a script name or category",
+                script
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Translates validated scan options into nmap arguments.
+    fn build_args(options: &ScanOptions, output_file: &str, targets: &[String]) -> Vec<String> {
+        let mut args = vec![
+            "-oX".to_string(),
+            output_file.to_string(),
+            "--stats-every".to_string(),
+            "5s".to_string(),
+        ];
+        if options.flag("udp") {
+            args.push("-sU".to_string());
+        }
+        if options.flag("service_detection") {
+            args.push("-sV".to_string());
+        }
+        if options.flag("os_detection") {
+            args.push("-O".to_string());
+        }
+        if let Some(ports) = options.string("ports") {
+            args.extend(["-p".to_string(), ports.to_string()]);
+        }
+        if let Some(top_ports) = options.integer("top_ports") {
+            args.extend(["--top-ports".to_string(), top_ports.to_string()]);
+        }
+        if let Some(timing) = options.integer("timing") {
+            args.push(format!("-T{}", timing));
+        }
+        let scripts = options.list("scripts");
+        if !scripts.is_empty() {
+            args.extend(["--script".to_string(), scripts.join(",")]);
+        }
+        args.extend(targets.iter().cloned());
+        args
+    }
+
+    /// nmap only closes the document when it finishes, so the output of an
+    /// interrupted scan is cut back to the last complete host and closed.
+    fn complete_truncated_xml(xml_content: &str) -> Cow<'_, str> {
+        if xml_content.contains("</nmaprun>") {
+            return Cow::Borrowed(xml_content);
+        }
+        let end = xml_content
+            .rfind("</host>")
+            .map(|index| index + "</host>".len())
+            .or_else(|| {
+                let start = xml_content.find("<nmaprun")?;
+                xml_content[start..].find('>').map(|index| start + index + 1)
+            });
+        match end {
+            Some(end) => Cow::Owned(format!("{}</nmaprun>", &xml_content[..end])),
+            None => Cow::Borrowed(xml_content),
+        }
+    }
+
+    /// Reads `--stats-every` lines such as
+    /// `SYN Stealth Scan Timing: About 45.20% done; ETC: 10:42 (0:00:06 remaining)`
+    /// and `Stats: 0:00:05 elapsed; 2 hosts completed (1 up), 1 undergoing ...`.
+    fn parse_progress(line: &str) -> Option<ScanProgress> {
+        if let Some((_, rest)) = line.split_once("About ") {
+            let (percent, _) = rest.split_once("% done")?;
+            return Some(ScanProgress {
+                percent: Some(percent.trim().parse().ok()?),
+                ..Default::default()
+            });
+        }
+        if line.starts_with("Stats: ") {
+            let (_, rest) = line.split_once(" (")?;
+            let (hosts_up, _) = rest.split_once(" up)")?;
+            return Some(ScanProgress {
+                hosts: Some(hosts_up.trim().parse().ok()?),
+                ..Default::default()
+            });
+        }
+        None
+    }
+
+    pub fn parse_up_hosts(xml_content: &str) -> Result<Vec<HostForm>, DeError> {
+        let nmap_run: NmapRun = from_str(&Self::complete_truncated_xml(xml_content))?;
 
         let mut up_hosts = Vec::new();
 
         for host in nmap_run.hosts {
             if host.status.state == "up" {
+                let services: Vec<ServiceForm> = host
+                    .ports
+                    .map(|ports| ports.ports.into_iter().map(ServiceForm::from).collect())
+                    .unwrap_or_default();
+                let hostname = host.hostnames.and_then(Hostnames::preferred);
+                let os_match = host.os.and_then(Os::best_match);
+
                 for addr in host.addresses {
                     if addr.addr_type == "ipv4" || addr.addr_type == "ipv6" {
-                        up_hosts.push(addr.addr);
+                        up_hosts.push(HostForm {
+                            hostname: hostname.clone(),
+                            ip_address: addr.addr,
+                            os: os_match.as_ref().map(|os_match| os_match.name.clone()),
+                            os_accuracy: os_match.as_ref().map(|os_match| os_match.accuracy),
+                            services: services.clone(),
+                            sources: Vec::new(),
+                            hostnames: Vec::new(),
+                        });
                     }
                 }
             }
         }
         Ok(up_hosts)
     }
-}
 
-#[async_trait]
-impl VulnerabilityScanner for NmapService {
-    type ScanRequest = NmapScanRequest;
-    type ScanResult = NmapScanResult;
-
-    async fn get_scan_result(&self, task_id: &str) -> Result<Self::ScanResult, Error> {
-        let scans = self.active_scans.lock().await;
+    /// Parses nmap output in either XML (`-oX`) or grepable (`-oG`) format.
+    pub fn parse_output(content: &str) -> Result<Vec<HostForm>, Error> {
+        let trimmed = content.trim_start();
+        if trimmed.starts_with("<?xml") || trimmed.starts_with("<nmaprun") {
+            return Ok(Self::parse_up_hosts(content)?);
+        }
+        if content.lines().any(|line| line.starts_with("Host: ")) {
+            return Self::parse_grepable(content);
+        }
+        Err(Error::ParseError("Unsupported nmap output format".to_string()))
+    }
 
-        if let Some(state) = scans.get(task_id) {
-            match &state.status {
-                ScanStatus::Completed => {
-                    if let Some(result) = &state.result {
-                        return Ok(result.clone());
-                    }
+    pub fn parse_grepable(content: &str) -> Result<Vec<HostForm>, Error> {
+        let mut up_hosts: Vec<HostForm> = Vec::new();
+
+        for line in content.lines() {
+            let Some(rest) = line.strip_prefix("Host: ") else {
+                continue;
+            };
+            let mut fields = rest.split('\t');
+            let host_field = fields.next().unwrap_or_default();
+            let (ip_address, hostname) = match host_field.split_once(' ') {
+                Some((ip, name)) => {
+                    let name = name.trim_matches(|c| c == '(' || c == ')');
+                    (ip.to_string(), (!name.is_empty()).then(|| name.to_string()))
                 },
-                ScanStatus::Failed(error) => {
-                    return Err(Error::ExecutionError(error.clone()));
-                },
-                _ => {
-                    return Ok(NmapScanResult { output_file: "".to_string(), hosts: Vec::new() });
+                None => (host_field.to_string(), None),
+            };
+
+            let mut is_up = false;
+            let mut services = Vec::new();
+            let mut os = None;
+            for field in fields {
+                if let Some(status) = field.strip_prefix("Status: ") {
+                    is_up = status == "Up";
+                } else if let Some(ports) = field.strip_prefix("Ports: ") {
+                    is_up = true;
+                    for port in ports.split(", ") {
+                        services.push(Self::parse_grepable_port(port)?);
+                    }
+                } else if let Some(os_name) = field.strip_prefix("OS: ") {
+                    os = Some(os_name.to_string());
+                }
+            }
+
+            if !is_up {
+                continue;
+            }
+            match up_hosts.iter_mut().find(|host| host.ip_address == ip_address) {
+                Some(host) => {
+                    host.services.extend(services);
+                    host.os = host.os.take().or(os);
+                    host.hostname = host.hostname.take().or(hostname);
                 },
+                None => up_hosts.push(HostForm {
+                    hostname,
+                    ip_address,
+                    os,
+                    os_accuracy: None,
+                    services,
+                    sources: Vec::new(),
+                    hostnames: Vec::new(),
+                }),
             }
         }
 
-        Ok(NmapScanResult { output_file: "".to_string(), hosts: Vec::new() })
+        Ok(up_hosts)
     }
 
-    async fn start_scan(&mut self, scan_id: Uuid, target: &str) -> Result<AnyScanResult, AppError> {
+    /// Parses a single `port/state/protocol/owner/service/rpc/version/` entry.
+    fn parse_grepable_port(entry: &str) -> Result<ServiceForm, Error> {
+        let parts: Vec<&str> = entry.trim().split('/').collect();
+        if parts.len() < 7 {
+            return Err(Error::ParseError(format!("Invalid port entry: {}", entry)));
+        }
+        let port = parts[0]
+            .parse::<i32>()
+            .map_err(|_| Error::ParseError(format!("Invalid port number: {}", parts[0])))?;
+        let non_empty = |value: &str| (!value.is_empty()).then(|| value.to_string());
+
+        // Grepable output squashes product, version and extra info into one field,
+        // e.g. "OpenSSH 8.9p1 Ubuntu 3ubuntu0.6 (Ubuntu Linux; protocol 2.0)".
+        let mut version_info = parts[6].trim();
+        let mut extra_info = None;
+        if version_info.ends_with(')') {
+            if let Some(start) = version_info.find(" (") {
+                extra_info = non_empty(&version_info[start + 2..version_info.len() - 1]);
+                version_info = &version_info[..start];
+            }
+        }
+        let tokens: Vec<&str> = version_info.split_whitespace().collect();
+        let (product, version) =
+            match tokens.iter().position(|token| token.starts_with(|c: char| c.is_ascii_digit())) {
+                Some(index) if index > 0 => {
+                    (Some(tokens[..index].join(" ")), Some(tokens[index..].join(" ")))
+                },
+                _ => (non_empty(version_info), None),
+            };
+
+        Ok(ServiceForm {
+            port,
+            protocol: parts[2].to_string(),
+            state: parts[1].to_string(),
+            service_name: non_empty(parts[4]),
+            product,
+            version,
+            extra_info,
+            cpe: None,
+        })
+    }
+}
+
+#[async_trait]
+impl VulnerabilityScanner for NmapService {
+    fn name(&self) -> &'static str {
+        "nmap"
+    }
+
+    fn capabilities(&self) -> Vec<ScannerCapability> {
+        vec![
+            ScannerCapability::HostDiscovery,
+            ScannerCapability::PortScan,
+            ScannerCapability::ServiceDetection,
+            ScannerCapability::OsDetection,
+        ]
+    }
+
+    fn options(&self) -> Vec<ScannerOption> {
+        vec![
+            ScannerOption {
+                name: "ports",
+                description: "Ports to scan",
+                kind: ScannerOptionKind::Ports,
+            },
+            ScannerOption {
+                name: "top_ports",
+                description: "Scan the N most common ports",
+                kind: ScannerOptionKind::Integer { min: 1, max: 65535 },
+            },
+            ScannerOption {
+                name: "timing",
+                description: "Timing template (-T0 to -T5)",
+                kind: ScannerOptionKind::Integer { min: 0, max: 5 },
+            },
+            ScannerOption {
+                name: "udp",
+                description: "Scan UDP ports instead of TCP",
+                kind: ScannerOptionKind::Boolean,
+            },
+            ScannerOption {
+                name: "service_detection",
+                description: "Probe open ports for service and version info",
+                kind: ScannerOptionKind::Boolean,
+            },
+            ScannerOption {
+                name: "os_detection",
+                description: "Enable OS detection",
+                kind: ScannerOptionKind::Boolean,
+            },
+            ScannerOption {
+                name: "scripts",
+                description: "NSE scripts or script categories to run",
+                kind: ScannerOptionKind::List,
+            },
+        ]
+    }
+
+    fn is_installed(&self) -> bool {
+        is_binary_installed("nmap")
+    }
+
+    async fn get_scan_result(&self, scan_id: Uuid) -> Result<AnyScanResult, Error> {
+        self.load_result(&self.scans_dir.join(scan_id.to_string()).join("scan.xml"))
+    }
+
+    fn load_result(&self, output_file: &Path) -> Result<AnyScanResult, Error> {
+        let content = std::fs::read_to_string(output_file).map_err(|e| {
+            Error::IoError(format!("Failed to read result file: {}", e))
+        })?;
+
+        // Imports keep grepable output as well.
+        Ok(AnyScanResult::Hosts(HostsScanResult {
+            output_file: output_file.display().to_string(),
+            hosts: NmapService::parse_output(&content)?,
+        }))
+    }
+
+    async fn start_scan(
+        &self,
+        scan_id: Uuid,
+        target: &str,
+        options: &ScanOptions,
+    ) -> Result<AnyScanResult, Error> {
+        let targets = parse_targets(target)?;
+        Self::check_scripts(options)?;
         let scan_path = format!("{}/{}", self.scans_dir.display(), scan_id);
         tokio::fs::create_dir_all(&scan_path).await.map_err(|e| {
             error!("Failed to create scan directory: {}", e);
-            AppError::InternalServerError
+            Error::IoError(format!("Failed to create scan directory: {}", e))
         })?;
 
         let output_file = format!("{}/scan.xml", scan_path);
 
-        let status = tokio::process::Command::new("nmap")
-            .arg("-oX")
-            .arg(&output_file)
-            .arg(target)
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .status()
-            .await
-            .map_err(|e| {
-                error!("Failed to execute nmap: {}", e);
-                AppError::InternalServerError
-            })?;
-
-        if !status.success() {
-            error!("Nmap scan failed with status: {}", status);
-            return Err(AppError::InternalServerError);
-        }
+        let mut command = tokio::process::Command::new("nmap");
+        command.args(Self::build_args(options, &output_file, &targets));
+        self.processes
+            .run(scan_id, command, &output_file, Self::parse_progress)
+            .await?
+            .check("nmap")?;
 
         let xml_output = tokio::fs::read_to_string(&output_file).await.map_err(|e| {
             error!("Failed to read result file: {}", e);
-            AppError::InternalServerError
+            Error::IoError(format!("Failed to read result file: {}", e))
         })?;
 
         let new_hosts = NmapService::parse_up_hosts(&xml_output).map_err(|e| {
             error!("Failed to parse result file: {}", e);
-            AppError::InternalServerError
+            Error::from(e)
         })?;
 
-        Ok(AnyScanResult::Nmap(NmapScanResult {
+        Ok(AnyScanResult::Hosts(HostsScanResult {
             output_file,
             hosts: new_hosts,
         }))
@@ -198,14 +524,145 @@ impl VulnerabilityScanner for NmapService {
 
 #[cfg(test)]
 mod tests {
+    use std::fs;
+
     use super::*;
 
+    const SCAN_FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/nmap/scan.xml");
+
     #[test]
     fn test_parse_up_hosts() {
-        let xml = fs::read_to_string("/app/scans/0e430a96-0fb9-4626-ae5b-318472b0f033/scan.xml").unwrap();
+        let xml = fs::read_to_string(SCAN_FIXTURE).unwrap();
 
         let hosts = NmapService::parse_up_hosts(&xml).unwrap();
         assert_eq!(hosts.len(), 1);
-        assert!(hosts.contains(&"192.168.1.254".to_string()));
+        assert!(hosts.iter().any(|host| host.ip_address == "192.168.1.254"));
+    }
+
+    #[test]
+    fn test_parse_interrupted_scan() {
+        let xml = fs::read_to_string(SCAN_FIXTURE).unwrap();
+        let cut = xml.find("<host starttime=\"1748260932\" endtime=\"1748261004\"><status state=\"down\"").unwrap();
+
+        let hosts = NmapService::parse_up_hosts(&xml[..cut + 40]).unwrap();
+        assert_eq!(hosts.len(), 1);
+        assert_eq!(hosts[0].ip_address, "192.168.1.254");
+
+        let header = &xml[..xml.find("<host ").unwrap()];
+        assert!(NmapService::parse_up_hosts(header).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_grepable() {
+        let content = fs::read_to_string(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/nmap/scan.gnmap"
+        ))
+        .unwrap();
+
+        let hosts = NmapService::parse_output(&content).unwrap();
+        assert_eq!(hosts.len(), 1);
+        assert_eq!(hosts[0].ip_address, "192.168.1.254");
+        assert_eq!(hosts[0].hostname.as_deref(), Some("router.lan"));
+        assert_eq!(hosts[0].os.as_deref(), Some("Linux 5.0 - 5.5"));
+
+        let ssh = hosts[0].services.iter().find(|service| service.port == 22).unwrap();
+        assert_eq!(ssh.service_name.as_deref(), Some("ssh"));
+        assert_eq!(ssh.product.as_deref(), Some("OpenSSH"));
+        assert_eq!(ssh.version.as_deref(), Some("8.9p1 Ubuntu 3ubuntu0.6"));
+        assert_eq!(ssh.extra_info.as_deref(), Some("Ubuntu Linux; protocol 2.0"));
+
+        let dns = hosts[0].services.iter().find(|service| service.port == 53).unwrap();
+        assert_eq!(dns.protocol, "udp");
+        assert_eq!(dns.state, "open|filtered");
+        assert_eq!(dns.product, None);
+    }
+
+    #[test]
+    fn test_parse_hostname_and_os() {
+        let xml = fs::read_to_string(SCAN_FIXTURE).unwrap();
+
+        let hosts = NmapService::parse_up_hosts(&xml).unwrap();
+        assert_eq!(hosts[0].hostname.as_deref(), Some("gw.corp.example"));
+        assert_eq!(hosts[0].os.as_deref(), Some("Linux 5.0 - 5.5"));
+        assert_eq!(hosts[0].os_accuracy, Some(97));
+    }
+
+    #[test]
+    fn test_parse_services() {
+        let xml = fs::read_to_string(SCAN_FIXTURE).unwrap();
+
+        let hosts = NmapService::parse_up_hosts(&xml).unwrap();
+        let services = &hosts[0].services;
+        assert_eq!(services.len(), 3);
+
+        let ssh = services.iter().find(|service| service.port == 22).unwrap();
+        assert_eq!(ssh.protocol, "tcp");
+        assert_eq!(ssh.state, "open");
+        assert_eq!(ssh.service_name.as_deref(), Some("ssh"));
+        assert_eq!(ssh.product.as_deref(), Some("OpenSSH"));
+        assert_eq!(ssh.version.as_deref(), Some("8.9p1 Ubuntu 3ubuntu0.6"));
+        assert_eq!(ssh.extra_info.as_deref(), Some("Ubuntu Linux; protocol 2.0"));
+        assert_eq!(
+            ssh.cpe.as_deref(),
+            Some("cpe:/a:openbsd:openssh:8.9p1, cpe:/o:linux:linux_kernel")
+        );
+
+        let dns = services.iter().find(|service| service.port == 53).unwrap();
+        assert_eq!(dns.protocol, "udp");
+        assert_eq!(dns.state, "open|filtered");
+        assert_eq!(dns.product, None);
+    }
+
+    #[test]
+    fn test_build_args() {
+        let options = ScanOptions::validate(
+            &NmapService::new("/tmp", ProcessRegistry::default()).options(),
+            serde_json::json!({
+                "ports": "1-65535",
+                "service_detection": true,
+                "timing": 4,
+                "scripts": ["http-title", "ssl-cert"],
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        )
+        .unwrap();
+        let targets = parse_targets("10.0.0.1 example.com").unwrap();
+
+        let args = NmapService::build_args(&options, "/tmp/scan.xml", &targets);
+        assert_eq!(args, vec![
+            "-oX", "/tmp/scan.xml", "--stats-every", "5s", "-sV", "-p", "1-65535", "-T4", "--script",
+            "http-title,ssl-cert", "10.0.0.1", "example.com",
+        ]);
+        assert!(parse_targets("10.0.0.1 -iL /etc/passwd").is_err());
+    }
+
+    #[test]
+    fn test_check_scripts() {
+        let schema = NmapService::new("/tmp", ProcessRegistry::default()).options();
+        let validate = |scripts: serde_json::Value| {
+            ScanOptions::validate(&schema, serde_json::json!({ "scripts": scripts }).as_object().unwrap().clone())
+        };
+
+        let options = validate(serde_json::json!(["default", "http-title", "ssl-cert"])).unwrap();
+        assert!(NmapService::check_scripts(&options).is_ok());
+        assert!(validate(serde_json::json!(["/app/wordlists/evil.nse"])).is_err());
+        let options = validate(serde_json::json!(["wordlists/evil.nse"])).unwrap();
+        assert!(NmapService::check_scripts(&options).is_err());
+        let options = validate(serde_json::json!(["evil.nse"])).unwrap();
+        assert!(NmapService::check_scripts(&options).is_err());
+    }
+
+    #[test]
+    fn test_parse_progress() {
+        let timing = "SYN Stealth Scan Timing: About 45.20% done; ETC: 10:42 (0:00:06 remaining)";
+        assert_eq!(NmapService::parse_progress(timing).unwrap().percent, Some(45.2));
+
+        let stats = "Stats: 0:00:05 elapsed; 2 hosts completed (1 up), 1 undergoing SYN Stealth Scan";
+        assert_eq!(NmapService::parse_progress(stats).unwrap().hosts, Some(1));
+
+        assert!(NmapService::parse_progress("Nmap scan report for 192.168.1.254").is_none());
     }
 }
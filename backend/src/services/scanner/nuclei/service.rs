@@ -1,47 +1,52 @@
 extern crate chrono;
 
-use std::collections::HashMap;
+use std::fmt;
 use std::fs;
+use std::io::{BufRead, BufReader};
+use std::net::IpAddr;
 use std::path::{Path, PathBuf};
-use std::process::Stdio;
 use async_trait::async_trait;
-use log::{debug, error, info};
-use serde::{Deserialize, Serialize};
+use log::{debug, error};
+use serde::de::{self, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
 use serde_json;
-use tracing::field::debug;
 use uuid::Uuid;
-use crate::utils::errors::AppError;
-use crate::dtos::handlers::{HostForm, IssueForm};
-use crate::models::host::Host;
-use crate::services::scanner::types::{AnyScanResult, Error};
-use crate::services::scanner::VulnerabilityScanner;
+use crate::dtos::handlers::{ClassificationForm, EvidenceForm, HostForm, IssueForm};
+use crate::services::scanner::types::{
+    resolve_under, AnyScanResult, Error, IssuesScanResult, ScanOptions, ScannerCapability,
+    ScannerOption, ScannerOptionKind,
+};
+use crate::services::scanner::events::ScanProgress;
+use crate::services::scanner::process::ProcessRegistry;
+use crate::services::scanner::{is_binary_installed, parse_targets, VulnerabilityScanner};
+use crate::services::scope::host_of;
 
 #[derive(Clone)]
 pub struct NucleiService {
     scans_dir: PathBuf,
-}
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct NucleiScanRequest {
-    pub target: String,
-    pub templates: Option<Vec<String>>,
-    pub severity: Option<Vec<String>>,
-    pub output_format: Option<String>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct NucleiScanResult {
-    pub output_file: String,
-    pub findings: Vec<IssueForm>,
+    templates_dir: PathBuf,
+    processes: ProcessRegistry,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct NucleiFinding {
+    #[serde(rename = "template-id")]
+    pub template_id: String,
+    #[serde(rename = "matcher-name", default)]
+    pub matcher_name: Option<String>,
     pub host: String,
     pub severity: String,
     pub info: NucleiFindingInfo,
     #[serde(rename = "matched-at")]
     pub matched_at: String,
+    #[serde(rename = "extracted-results", default)]
+    pub extracted_results: Vec<String>,
+    #[serde(rename = "curl-command", default)]
+    pub curl_command: Option<String>,
+    #[serde(default)]
+    pub request: Option<String>,
+    #[serde(default)]
+    pub response: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -49,16 +54,120 @@ pub struct NucleiFindingInfo {
     pub name: String,
     pub description: Option<String>,
     pub remediation: Option<String>,
-    pub cvss: Option<f64>
+    pub cvss: Option<f64>,
+    #[serde(default)]
+    pub cve_ids: Vec<String>,
+    #[serde(default)]
+    pub cwe_ids: Vec<String>,
+    #[serde(default)]
+    pub references: Vec<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub cvss_vector: Option<String>,
+}
+
+/// Reads a template field written either as a list or as a comma-separated
+/// string, as nuclei accepts both for tags, references and ids.
+fn string_list(value: &serde_json::Value) -> Vec<String> {
+    match value {
+        serde_json::Value::Array(items) => items
+            .iter()
+            .filter_map(|item| item.as_str())
+            .map(|item| item.trim().to_string())
+            .filter(|item| !item.is_empty())
+            .collect(),
+        serde_json::Value::String(items) => items
+            .split(',')
+            .map(|item| item.trim().to_string())
+            .filter(|item| !item.is_empty())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Converts the elements of a `-je` JSON array one by one instead of
+/// collecting the whole array into a `serde_json::Value` first.
+struct FindingsVisitor;
+
+impl<'de> Visitor<'de> for FindingsVisitor {
+    type Value = Vec<NucleiFinding>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an array of nuclei findings")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut findings = Vec::new();
+        while let Some(raw_finding) = seq.next_element::<serde_json::Value>()? {
+            let finding = NucleiService::parse_finding(&raw_finding).map_err(de::Error::custom)?;
+            findings.push(finding);
+        }
+        Ok(findings)
+    }
 }
 
 impl NucleiService {
-    pub fn new(scans_dir: impl AsRef<Path>) -> Self {
+    pub fn new(
+        scans_dir: impl AsRef<Path>,
+        templates_dir: impl AsRef<Path>,
+        processes: ProcessRegistry,
+    ) -> Self {
         Self {
             scans_dir: scans_dir.as_ref().to_path_buf(),
+            templates_dir: templates_dir.as_ref().to_path_buf(),
+            processes,
         }
     }
 
+    /// Translates validated scan options into nuclei arguments. Template
+    /// entries must exist inside the configured templates directory.
+    fn build_args(
+        &self,
+        options: &ScanOptions,
+        output_file: &str,
+        targets: &[String],
+    ) -> Result<Vec<String>, Error> {
+        let mut args = vec!["-u".to_string(), targets.join(",")];
+        let templates = options.list("templates");
+        if templates.is_empty() {
+            args.extend(["-t".to_string(), self.templates_dir.display().to_string()]);
+        }
+        for template in templates {
+            let path = resolve_under(&self.templates_dir, template)
+                .ok_or_else(|| Error::InvalidOption(format!("templates: {} not found", template)))?;
+            args.extend(["-t".to_string(), path.display().to_string()]);
+        }
+        let tags = options.list("tags");
+        if !tags.is_empty() {
+            args.extend(["-tags".to_string(), tags.join(",")]);
+        }
+        let severity = options.list("severity");
+        if !severity.is_empty() {
+            args.extend(["-severity".to_string(), severity.join(",")]);
+        }
+        if let Some(rate_limit) = options.integer("rate_limit") {
+            args.extend(["-rl".to_string(), rate_limit.to_string()]);
+        }
+        args.extend(["-stats".to_string(), "-stats-json".to_string()]);
+        args.extend(["-je".to_string(), output_file.to_string()]);
+        Ok(args)
+    }
+
+    /// Reads `-stats-json` lines. nuclei reports most counters as strings,
+    /// e.g. `{"hosts":"2","matched":"3","percent":"45",...}`.
+    fn parse_progress(line: &str) -> Option<ScanProgress> {
+        let stats: serde_json::Value = serde_json::from_str(line).ok()?;
+        let number = |name: &str| {
+            let value = stats.get(name)?;
+            value.as_f64().or_else(|| value.as_str()?.parse().ok())
+        };
+        Some(ScanProgress {
+            percent: Some(number("percent")?),
+            hosts: number("hosts").map(|hosts| hosts as u64),
+            findings: number("matched").map(|matched| matched as u64),
+        })
+    }
+
     fn severity_to_cvss(severity: &str) -> f64 {
         match severity.to_lowercase().as_str() {
             "critical" => 9.0,
@@ -70,68 +179,161 @@ impl NucleiService {
         }
     }
 
-    pub fn parse_nuclei_output(content: &str) -> Result<Vec<NucleiFinding>, Error> {
-        let raw_findings: Vec<serde_json::Value> = serde_json::from_str(content)
-            .map_err(|e| Error::ParseError(e.to_string()))?;
-
-        let mut findings = Vec::new();
-
-        for raw_finding in raw_findings {
-            let info = &raw_finding["info"];
-            let template_name = info["name"]
-                .as_str()
-                .ok_or(Error::ParseError("Missing template name".to_string()))?
-                .to_string();
-
-            let host = raw_finding["ip"]
-                .as_str()
-                .unwrap_or_else(|| "127.0.0.2")
-                .to_string();
-
-            let severity = info["severity"]
-                .as_str()
-                .ok_or(Error::ParseError("Missing severity".to_string()))?
-                .to_string();
-
-            let mut description = info["description"]
-                .as_str()
-                .map(|s| s.to_string());
-
-            if template_name.eq("HTTP Missing Security Headers") {
-                let matcher = raw_finding["matcher-name"]
-                    .as_str()
-                    .ok_or(Error::ParseError("Missing matcher".to_string()))?
-                    .to_string();
-                description = Some(format!("The \"{}\" header is not set", matcher));
+    /// Parses nuclei output written with `-je` (a JSON array) or `-jsonl` (one
+    /// finding per line). The input is consumed incrementally, so large result
+    /// files are never loaded into memory as a whole.
+    pub fn parse_nuclei_output<R: BufRead>(mut reader: R) -> Result<Vec<NucleiFinding>, Error> {
+        let first_byte = loop {
+            let buf = reader.fill_buf().map_err(|e| Error::IoError(e.to_string()))?;
+            if buf.is_empty() {
+                return Ok(Vec::new());
             }
-
-            let remediation = info["remediation"]
-                .as_str()
-                .map(|s| s.to_string());
-
-            let cvss = raw_finding["cvss-score"]
-                .as_str()
-                .map(|s| s.parse::<f64>().unwrap_or(0.0));
-
-            let matched_at = raw_finding["matched-at"]
-                .as_str()
-                .ok_or(Error::ParseError("Missing matched-at".to_string()))?
-                .to_string();
-
-            findings.push(NucleiFinding {
-                host,
-                severity,
-                matched_at,
-                info: NucleiFindingInfo {
-                    name: template_name,
-                    description,
-                    remediation,
-                    cvss,
+            match buf.iter().position(|b| !b.is_ascii_whitespace()) {
+                Some(pos) => {
+                    let first_byte = buf[pos];
+                    reader.consume(pos);
+                    break first_byte;
                 },
-            });
+                None => {
+                    let len = buf.len();
+                    reader.consume(len);
+                },
+            }
+        };
+
+        if first_byte == b'[' {
+            let mut deserializer = serde_json::Deserializer::from_reader(reader);
+            let findings = deserializer
+                .deserialize_seq(FindingsVisitor)
+                .map_err(|e| Error::ParseError(e.to_string()))?;
+            deserializer.end().map_err(|e| Error::ParseError(e.to_string()))?;
+            Ok(findings)
+        } else {
+            let mut findings = Vec::new();
+            for line in reader.lines() {
+                let line = line.map_err(|e| Error::IoError(e.to_string()))?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let raw_finding: serde_json::Value = serde_json::from_str(&line)
+                    .map_err(|e| Error::ParseError(e.to_string()))?;
+                findings.push(Self::parse_finding(&raw_finding)?);
+            }
+            Ok(findings)
         }
+    }
 
-        Ok(findings)
+    fn parse_finding(raw_finding: &serde_json::Value) -> Result<NucleiFinding, Error> {
+        let info = &raw_finding["info"];
+        let template_name = info["name"]
+            .as_str()
+            .ok_or(Error::ParseError("Missing template name".to_string()))?
+            .to_string();
+
+        let template_id = raw_finding["template-id"]
+            .as_str()
+            .ok_or(Error::ParseError("Missing template id".to_string()))?
+            .to_string();
+
+        let matcher_name = raw_finding["matcher-name"].as_str().map(|s| s.to_string());
+
+        let severity = info["severity"]
+            .as_str()
+            .ok_or(Error::ParseError("Missing severity".to_string()))?
+            .to_string();
+
+        let description = info["description"]
+            .as_str()
+            .map(|s| s.to_string());
+
+        let remediation = info["remediation"]
+            .as_str()
+            .map(|s| s.to_string());
+
+        let classification = &info["classification"];
+        let cvss = match &classification["cvss-score"] {
+            serde_json::Value::String(score) => score.parse::<f64>().ok(),
+            score => score.as_f64(),
+        };
+        // nuclei lowercases the ids, reports use the canonical form.
+        let ids = |name: &str| -> Vec<String> {
+            string_list(&classification[name])
+                .into_iter()
+                .map(|id| id.to_uppercase())
+                .collect()
+        };
+
+        let matched_at = raw_finding["matched-at"]
+            .as_str()
+            .ok_or(Error::ParseError("Missing matched-at".to_string()))?
+            .to_string();
+
+        // Imported output often lacks the resolved ip, the target nuclei was
+        // given then names the host.
+        let host = raw_finding["ip"]
+            .as_str()
+            .or(raw_finding["host"].as_str())
+            .map(host_of)
+            .unwrap_or_else(|| host_of(&matched_at))
+            .to_string();
+
+        let text = |name: &str| raw_finding[name].as_str().map(|s| s.to_string());
+        let extracted_results = raw_finding["extracted-results"]
+            .as_array()
+            .map(|results| results.iter().filter_map(|r| r.as_str().map(|s| s.to_string())).collect())
+            .unwrap_or_default();
+
+        Ok(NucleiFinding {
+            template_id,
+            matcher_name,
+            host,
+            severity,
+            matched_at,
+            extracted_results,
+            curl_command: text("curl-command"),
+            request: text("request"),
+            response: text("response"),
+            info: NucleiFindingInfo {
+                name: template_name,
+                description,
+                remediation,
+                cvss,
+                cve_ids: ids("cve-id"),
+                cwe_ids: ids("cwe-id"),
+                references: string_list(&info["reference"]),
+                tags: string_list(&info["tags"]),
+                cvss_vector: classification["cvss-metrics"].as_str().map(|s| s.to_string()),
+            },
+        })
+    }
+
+    /// Stable identity of a finding: the template and, for templates with
+    /// several matchers, the matcher that fired.
+    fn fingerprint(finding: &NucleiFinding) -> String {
+        match &finding.matcher_name {
+            Some(matcher) => format!("{}:{}", finding.template_id, matcher),
+            None => finding.template_id.clone(),
+        }
+    }
+
+    /// Raw request, response, curl command and extracted values of a
+    /// finding, so reviewers can check it without rerunning the template.
+    fn evidence(finding: &NucleiFinding) -> Vec<EvidenceForm> {
+        let mut evidence = Vec::new();
+        let mut add = |label: &str, content: Option<String>| {
+            if let Some(content) = content.filter(|content| !content.trim().is_empty()) {
+                evidence.push(EvidenceForm {
+                    description: format!("{} {}", label, finding.matched_at),
+                    content,
+                    host: finding.host.clone(),
+                });
+            }
+        };
+        add("Request to", finding.request.clone());
+        add("Response from", finding.response.clone());
+        add("curl command for", finding.curl_command.clone());
+        add("Values extracted at", Some(finding.extracted_results.join("\n")));
+        evidence
     }
 
     pub fn parse_to_issues(
@@ -139,11 +341,21 @@ impl NucleiService {
     ) -> Vec<IssueForm> {
         let mut issues = Vec::new();
         for finding in findings {
+            let fingerprint = Self::fingerprint(&finding);
+            let evidence = Self::evidence(&finding);
+            // Hosts are known by address, findings on a name stay unlinked.
             let mut host: Vec<HostForm> = Vec::new();
-            host.push(HostForm {
-                hostname: None,
-                ip_address: finding.host,
-            });
+            if finding.host.parse::<IpAddr>().is_ok() {
+                host.push(HostForm {
+                    hostname: None,
+                    ip_address: finding.host,
+                    os: None,
+                    os_accuracy: None,
+                    services: Vec::new(),
+                    sources: Vec::new(),
+                    hostnames: Vec::new(),
+                });
+            }
 
             let mut cvss;
             if let Some(c) = finding.info.cvss {
@@ -158,6 +370,17 @@ impl NucleiService {
                 mitigation: finding.info.remediation.clone(),
                 cvss: Some(cvss),
                 hosts: host,
+                template_id: Some(finding.template_id),
+                matched_at: Some(finding.matched_at),
+                fingerprint: Some(fingerprint),
+                evidence,
+                classification: ClassificationForm {
+                    cve_ids: finding.info.cve_ids,
+                    cwe_ids: finding.info.cwe_ids,
+                    reference_urls: finding.info.references,
+                    tags: finding.info.tags,
+                    cvss_vector: finding.info.cvss_vector,
+                },
             })
         }
 
@@ -167,78 +390,272 @@ impl NucleiService {
 
 #[async_trait]
 impl VulnerabilityScanner for NucleiService {
-    type ScanRequest = NucleiScanRequest;
-    type ScanResult = NucleiScanResult;
+    fn name(&self) -> &'static str {
+        "nuclei"
+    }
+
+    fn capabilities(&self) -> Vec<ScannerCapability> {
+        vec![ScannerCapability::VulnerabilityScan]
+    }
 
-    async fn get_scan_result(&self, task_id: &str) -> Result<Self::ScanResult, Error> {
-        let output_file = self.scans_dir.join(task_id).join("results.json");
-        let output_file_str = output_file.to_str()
-            .ok_or(Error::IoError("Can't unwrap output file".to_string()))?;
+    fn options(&self) -> Vec<ScannerOption> {
+        vec![
+            ScannerOption {
+                name: "templates",
+                description: "Template files or directories to run",
+                kind: ScannerOptionKind::List,
+            },
+            ScannerOption {
+                name: "tags",
+                description: "Run only templates with these tags",
+                kind: ScannerOptionKind::List,
+            },
+            ScannerOption {
+                name: "severity",
+                description: "Run only templates with these severities",
+                kind: ScannerOptionKind::Choice {
+                    values: vec!["info", "low", "medium", "high", "critical"],
+                },
+            },
+            ScannerOption {
+                name: "rate_limit",
+                description: "Maximum number of requests per second",
+                kind: ScannerOptionKind::Integer { min: 1, max: 10000 },
+            },
+        ]
+    }
+
+    fn is_installed(&self) -> bool {
+        is_binary_installed("nuclei")
+    }
 
+    async fn get_scan_result(&self, scan_id: Uuid) -> Result<AnyScanResult, Error> {
+        let output_file = self.scans_dir.join(scan_id.to_string()).join("scan.json");
         if !output_file.exists() {
-            return Ok(NucleiScanResult {
-                output_file: output_file_str.to_string(),
+            return Ok(AnyScanResult::Issues(IssuesScanResult {
+                output_file: output_file.display().to_string(),
                 findings: vec![],
-            });
+            }));
         }
+        self.load_result(&output_file)
+    }
 
-        let content = fs::read_to_string(&output_file)
+    fn load_result(&self, output_file: &Path) -> Result<AnyScanResult, Error> {
+        let file = fs::File::open(output_file)
             .map_err(|_| Error::IoError("Can't read output file".to_string()))?;
 
-        let findings = Self::parse_nuclei_output(&content)?;
+        let findings = Self::parse_nuclei_output(BufReader::new(file))?;
         let issues = Self::parse_to_issues(findings);
 
-        Ok(NucleiScanResult {
-            output_file: output_file_str.to_string(),
+        Ok(AnyScanResult::Issues(IssuesScanResult {
+            output_file: output_file.display().to_string(),
             findings: issues,
-        })
+        }))
     }
 
-    async fn start_scan(&mut self, scan_id: Uuid, target: &str) -> Result<AnyScanResult, AppError> {
+    async fn start_scan(
+        &self,
+        scan_id: Uuid,
+        target: &str,
+        options: &ScanOptions,
+    ) -> Result<AnyScanResult, Error> {
+        let targets = parse_targets(target)?;
         let scan_path = format!("{}/{}", self.scans_dir.display(), scan_id);
         fs::create_dir_all(&scan_path).map_err(|e| {
             error!("Failed to create scan directory: {}", e);
-            AppError::InternalServerError
+            Error::IoError(format!("Failed to create scan directory: {}", e))
         })?;
 
         let output_file = format!("{}/scan.json", scan_path);
-        debug!("Run command nuclei -u {} -t /home/helgesander/nuclei-templates -je {}", &target.replace(" ", ","), &output_file);
-        let status = tokio::process::Command::new("nuclei")
-            .arg("-u")
-            .arg(&target.replace(" ", ","))
-            .arg("-t")
-            .arg("/home/ubuntu/nuclei-templates")
-            .arg("-je")
-            .arg(&output_file)
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .status()
-            .await
-            .map_err(|e| {
-                error!("Failed to start nuclei scan: {}", e);
-                AppError::InternalServerError
-            })?;
-
-        if !status.success() {
-            error!("Nuclei scanning failed with status: {}", status);
-            return Err(AppError::InternalServerError);
-        }
-
-        let json_output = tokio::fs::read_to_string(&output_file).await.map_err(|e| {
+        let args = self.build_args(options, &output_file, &targets)?;
+        debug!("Run command nuclei {}", args.join(" "));
+        let mut command = tokio::process::Command::new("nuclei");
+        command.args(args);
+        self.processes
+            .run(scan_id, command, &output_file, Self::parse_progress)
+            .await?
+            .check("nuclei")?;
+
+        let json_output = fs::File::open(&output_file).map_err(|e| {
             error!("Failed to read output file: {}", e);
-            AppError::InternalServerError
+            Error::IoError(format!("Failed to read output file: {}", e))
         })?;
 
-        let findings = NucleiService::parse_nuclei_output(&json_output)
+        let findings = NucleiService::parse_nuclei_output(BufReader::new(json_output))
             .map_err(|e| {
                 error!("Can't parse nuclei output file: {}", e);
-                AppError::InternalServerError
+                e
             })?;
         let findings = NucleiService::parse_to_issues(findings);
 
-        Ok(AnyScanResult::Nuclei(NucleiScanResult {
+        Ok(AnyScanResult::Issues(IssuesScanResult {
             output_file,
             findings,
         }))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+
+    use super::*;
+
+    fn open_fixture(name: &str) -> BufReader<File> {
+        let path = format!("{}/tests/fixtures/nuclei/{}", env!("CARGO_MANIFEST_DIR"), name);
+        BufReader::new(File::open(path).unwrap())
+    }
+
+    #[test]
+    fn test_parse_json_array() {
+        let findings = NucleiService::parse_nuclei_output(open_fixture("scan.json")).unwrap();
+        assert_eq!(findings.len(), 4);
+        assert_eq!(findings[2].host, "10.0.0.6");
+        assert_eq!(findings[2].severity, "high");
+        assert_eq!(
+            findings[2].matched_at,
+            "http://10.0.0.6:8080/icons/.%2e/%2e%2e/%2e%2e/%2e%2e/etc/passwd"
+        );
+    }
+
+    #[test]
+    fn test_parse_jsonl_matches_json_array() {
+        let from_array = NucleiService::parse_nuclei_output(open_fixture("scan.json")).unwrap();
+        let from_lines = NucleiService::parse_nuclei_output(open_fixture("scan.jsonl")).unwrap();
+        assert_eq!(from_array.len(), from_lines.len());
+        for (a, b) in from_array.iter().zip(from_lines.iter()) {
+            assert_eq!(a.info.name, b.info.name);
+            assert_eq!(a.matched_at, b.matched_at);
+        }
+    }
+
+    #[test]
+    fn test_fingerprints() {
+        let findings = NucleiService::parse_nuclei_output(open_fixture("scan.json")).unwrap();
+        let issues = NucleiService::parse_to_issues(findings);
+        let fingerprints: Vec<&str> = issues
+            .iter()
+            .filter_map(|issue| issue.fingerprint.as_deref())
+            .collect();
+        assert_eq!(fingerprints, vec![
+            "http-missing-security-headers:x-frame-options",
+            "http-missing-security-headers:content-security-policy",
+            "CVE-2021-41773",
+            "tech-detect:nginx",
+        ]);
+    }
+
+    #[test]
+    fn test_evidence() {
+        let findings = NucleiService::parse_nuclei_output(open_fixture("scan.json")).unwrap();
+        let issues = NucleiService::parse_to_issues(findings);
+
+        let descriptions: Vec<&str> = issues[2]
+            .evidence
+            .iter()
+            .map(|evidence| evidence.description.as_str())
+            .collect();
+        assert_eq!(descriptions.len(), 4);
+        assert!(descriptions[0].starts_with("Request to http://10.0.0.6:8080/icons/"));
+        assert!(descriptions[3].starts_with("Values extracted at"));
+        assert!(issues[2].evidence[0].content.starts_with("GET /icons/"));
+        assert_eq!(issues[2].evidence[0].host, "10.0.0.6");
+        assert_eq!(issues[3].evidence.len(), 1);
+        assert!(issues[3].evidence[0].description.starts_with("curl command for"));
+    }
+
+    #[test]
+    fn test_classification() {
+        let findings = NucleiService::parse_nuclei_output(open_fixture("scan.json")).unwrap();
+        let info = &findings[2].info;
+        assert_eq!(info.cvss, Some(7.5));
+        assert_eq!(info.cve_ids, vec!["CVE-2021-41773"]);
+        assert_eq!(info.cwe_ids, vec!["CWE-22"]);
+        assert_eq!(info.references.len(), 2);
+        assert!(info.tags.contains(&"kev".to_string()));
+        assert_eq!(info.cvss_vector.as_deref(), Some("CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:N/A:N"));
+
+        assert_eq!(findings[0].info.cvss, None);
+        assert_eq!(findings[0].info.tags, vec!["misconfig", "headers", "generic"]);
+        assert_eq!(string_list(&serde_json::json!("cve, rce")), vec!["cve", "rce"]);
+    }
+
+    #[test]
+    fn test_host_without_ip() {
+        let finding = |fields: serde_json::Value| {
+            let mut raw = serde_json::json!({
+                "template-id": "tech-detect",
+                "info": {"name": "Wappalyzer Technology Detection", "severity": "info"},
+                "matched-at": "https://app.example:8443/login",
+            });
+            raw.as_object_mut().unwrap().extend(fields.as_object().unwrap().clone());
+            NucleiService::parse_finding(&raw).unwrap()
+        };
+
+        assert_eq!(finding(serde_json::json!({})).host, "app.example");
+        assert_eq!(finding(serde_json::json!({"host": "10.0.0.9:8443"})).host, "10.0.0.9");
+        let resolved = finding(serde_json::json!({"ip": "10.0.0.6", "host": "app.example"}));
+        assert_eq!(resolved.host, "10.0.0.6");
+
+        let issues = NucleiService::parse_to_issues(vec![
+            finding(serde_json::json!({})),
+            finding(serde_json::json!({"host": "https://10.0.0.9:8443"})),
+        ]);
+        assert!(issues[0].hosts.is_empty());
+        assert!(issues[0].evidence.iter().all(|evidence| evidence.host == "app.example"));
+        assert_eq!(issues[1].hosts[0].ip_address, "10.0.0.9");
+    }
+
+    #[test]
+    fn test_parse_empty_output() {
+        assert!(NucleiService::parse_nuclei_output(&b"  \n"[..]).unwrap().is_empty());
+        assert!(NucleiService::parse_nuclei_output(&b"[]"[..]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_build_args() {
+        let templates_dir = std::env::temp_dir().join(format!("nuclei-templates-{}", Uuid::new_v4()));
+        fs::create_dir_all(templates_dir.join("http/cves")).unwrap();
+        fs::create_dir_all(templates_dir.join("dns")).unwrap();
+        let service = NucleiService::new("/tmp/scans", &templates_dir, ProcessRegistry::default());
+        let validate = |options: serde_json::Value| {
+            ScanOptions::validate(&service.options(), options.as_object().unwrap().clone())
+        };
+        let options = validate(serde_json::json!({
+            "templates": ["http/cves", "dns"],
+            "severity": ["critical", "high"],
+            "rate_limit": 50,
+        }))
+        .unwrap();
+        let targets = parse_targets("https://a.example, https://b.example").unwrap();
+
+        let args = service.build_args(&options, "/tmp/scan.json", &targets).unwrap();
+        let template = |name: &str| templates_dir.canonicalize().unwrap().join(name).display().to_string();
+        assert_eq!(args, vec![
+            "-u".to_string(), "https://a.example,https://b.example".to_string(),
+            "-t".to_string(), template("http/cves"),
+            "-t".to_string(), template("dns"),
+            "-severity".to_string(), "critical,high".to_string(),
+            "-rl".to_string(), "50".to_string(),
+            "-stats".to_string(), "-stats-json".to_string(),
+            "-je".to_string(), "/tmp/scan.json".to_string(),
+        ]);
+
+        // Absolute paths would replace the templates directory in the join.
+        assert!(validate(serde_json::json!({ "templates": ["/etc/passwd"] })).is_err());
+        let options = validate(serde_json::json!({ "templates": ["http/missing"] })).unwrap();
+        assert!(service.build_args(&options, "/tmp/scan.json", &targets).is_err());
+        fs::remove_dir_all(&templates_dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_progress() {
+        let stats = r#"{"duration":"0:00:10","errors":"0","hosts":"2","matched":"3","percent":"45","requests":"900","rps":"90","templates":"150","total":"2000"}"#;
+        assert_eq!(NucleiService::parse_progress(stats), Some(ScanProgress {
+            percent: Some(45.0),
+            hosts: Some(2),
+            findings: Some(3),
+        }));
+        assert!(NucleiService::parse_progress("[INF] Using Nuclei Engine 3.2.4").is_none());
+    }
+}
@@ -0,0 +1,267 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::process::{ExitStatus, Stdio};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use log::{debug, error};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::Command;
+use uuid::Uuid;
+
+use crate::services::scanner::events::{ScanEvent, ScanEvents, ScanProgress};
+use crate::services::scanner::types::Error;
+
+/// How long a cancelled scanner gets to flush partial results before it is killed.
+const KILL_TIMEOUT: Duration = Duration::from_secs(5);
+/// Number of trailing stderr lines kept to explain a failed run.
+const STDERR_TAIL: usize = 20;
+/// Kept next to the output file of every run.
+pub const COMMAND_FILE: &str = "command.txt";
+pub const STDERR_LOG: &str = "stderr.log";
+
+#[derive(Default)]
+struct Processes {
+    running: HashMap<Uuid, u32>,
+    cancelled: HashSet<Uuid>,
+}
+
+pub struct ProcessOutput {
+    pub status: ExitStatus,
+    pub stderr: Vec<String>,
+}
+
+impl ProcessOutput {
+    /// Turns a non-zero exit into an error that includes the end of stderr.
+    pub fn check(self, program: &str) -> Result<(), Error> {
+        if self.status.success() {
+            return Ok(());
+        }
+        error!("{} failed with status: {}", program, self.status);
+        let mut message = format!("{} exited with {}", program, self.status);
+        if !self.stderr.is_empty() {
+            message.push_str(": ");
+            message.push_str(&self.stderr.join("\n"));
+        }
+        Err(Error::ExecutionError(message))
+    }
+}
+
+/// Child processes of running scans keyed by scan id.
+#[derive(Clone, Default)]
+pub struct ProcessRegistry {
+    processes: Arc<Mutex<Processes>>,
+    events: ScanEvents,
+}
+
+impl ProcessRegistry {
+    pub fn new(events: ScanEvents) -> Self {
+        Self {
+            processes: Arc::default(),
+            events,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Processes> {
+        self.processes.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Runs the command in its own process group and waits for it to exit.
+    /// Lines recognised by `parse_progress` are published as progress events,
+    /// other stderr lines as log events. The command line and the whole of
+    /// stderr are saved in the directory of `output_file`.
+    /// Returns `Error::Cancelled` with `output_file` if the scan was cancelled.
+    pub async fn run(
+        &self,
+        scan_id: Uuid,
+        mut command: Command,
+        output_file: &str,
+        parse_progress: fn(&str) -> Option<ScanProgress>,
+    ) -> Result<ProcessOutput, Error> {
+        command
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .process_group(0)
+            .kill_on_drop(true);
+        let scan_dir = Path::new(output_file).parent().unwrap_or(Path::new("."));
+        if let Err(e) = fs::write(scan_dir.join(COMMAND_FILE), command_line(&command) + "\n") {
+            error!("Failed to save the command line of scan {}: {}", scan_id, e);
+        }
+        let mut log = match File::create(scan_dir.join(STDERR_LOG)) {
+            Ok(file) => Some(BufWriter::new(file)),
+            Err(e) => {
+                error!("Failed to create the stderr log of scan {}: {}", scan_id, e);
+                None
+            },
+        };
+        let mut child = {
+            let mut processes = self.lock();
+            if processes.cancelled.remove(&scan_id) {
+                return Err(Error::Cancelled(output_file.to_string()));
+            }
+            let child = command.spawn().map_err(|e| {
+                let program = command.as_std().get_program().to_string_lossy().to_string();
+                error!("Failed to execute {}: {}", program, e);
+                Error::ExecutionError(format!("Failed to execute {}: {}", program, e))
+            })?;
+            if let Some(pid) = child.id() {
+                processes.running.insert(scan_id, pid);
+            }
+            child
+        };
+
+        let stdout = child.stdout.take();
+        let stderr = child.stderr.take();
+        let read_stdout = async {
+            if let Some(stdout) = stdout {
+                read_lines(stdout, |line| {
+                    if let Some(progress) = parse_progress(&line) {
+                        self.events.publish(scan_id, ScanEvent::Progress(progress));
+                    }
+                })
+                .await;
+            }
+        };
+        let read_stderr = async {
+            let mut tail = VecDeque::new();
+            if let Some(stderr) = stderr {
+                read_lines(stderr, |line| {
+                    if let Some(log) = log.as_mut() {
+                        let _ = writeln!(log, "{}", line);
+                    }
+                    match parse_progress(&line) {
+                        Some(progress) => self.events.publish(scan_id, ScanEvent::Progress(progress)),
+                        None => {
+                            self.events.publish(scan_id, ScanEvent::Log { line: line.clone() });
+                            if tail.len() == STDERR_TAIL {
+                                tail.pop_front();
+                            }
+                            tail.push_back(line);
+                        },
+                    }
+                })
+                .await;
+            }
+            if let Some(log) = log.as_mut() {
+                let _ = log.flush();
+            }
+            tail
+        };
+        let (status, _, stderr) = tokio::join!(child.wait(), read_stdout, read_stderr);
+
+        let cancelled = {
+            let mut processes = self.lock();
+            processes.running.remove(&scan_id);
+            processes.cancelled.remove(&scan_id)
+        };
+        if cancelled {
+            return Err(Error::Cancelled(output_file.to_string()));
+        }
+        let status = status.map_err(|e| {
+            Error::ExecutionError(format!("Failed to wait for scanner process: {}", e))
+        })?;
+        Ok(ProcessOutput {
+            status,
+            stderr: stderr.into(),
+        })
+    }
+
+    /// Tells a scanner that runs in process whether its scan was cancelled.
+    pub fn is_cancelled(&self, scan_id: Uuid) -> bool {
+        self.lock().cancelled.contains(&scan_id)
+    }
+
+    /// Forgets the cancellation of a scan that ran in process, returning
+    /// whether it was cancelled.
+    pub fn take_cancelled(&self, scan_id: Uuid) -> bool {
+        self.lock().cancelled.remove(&scan_id)
+    }
+
+    pub fn publish_progress(&self, scan_id: Uuid, progress: ScanProgress) {
+        self.events.publish(scan_id, ScanEvent::Progress(progress));
+    }
+
+    /// Interrupts the process group of the scan, or prevents it from starting
+    /// if it has not been spawned yet.
+    pub fn cancel(&self, scan_id: Uuid) {
+        let pid = {
+            let mut processes = self.lock();
+            processes.cancelled.insert(scan_id);
+            processes.running.get(&scan_id).copied()
+        };
+        let Some(pid) = pid else {
+            return;
+        };
+
+        debug!("Interrupting process group {} of scan {}", pid, scan_id);
+        signal_group(pid, libc::SIGINT);
+        let registry = self.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(KILL_TIMEOUT).await;
+            if registry.lock().running.get(&scan_id) == Some(&pid) {
+                debug!("Killing process group {} of scan {}", pid, scan_id);
+                signal_group(pid, libc::SIGKILL);
+            }
+        });
+    }
+}
+
+/// The command as it would be typed in a shell.
+fn command_line(command: &Command) -> String {
+    let command = command.as_std();
+    std::iter::once(command.get_program())
+        .chain(command.get_args())
+        .map(|arg| {
+            let arg = arg.to_string_lossy();
+            let plain = |c: char| c.is_ascii_alphanumeric() || "-_./:,=@%+".contains(c);
+            if !arg.is_empty() && arg.chars().all(plain) {
+                arg.to_string()
+            } else {
+                format!("'{}'", arg.replace('\'', "'\\''"))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+async fn read_lines(stream: impl AsyncRead + Unpin, mut on_line: impl FnMut(String)) {
+    let mut reader = BufReader::new(stream);
+    let mut buffer = Vec::new();
+    loop {
+        buffer.clear();
+        match reader.read_until(b'\n', &mut buffer).await {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {
+                let line = String::from_utf8_lossy(&buffer).trim_end().to_string();
+                if !line.is_empty() {
+                    on_line(line);
+                }
+            },
+        }
+    }
+}
+
+fn signal_group(pgid: u32, signal: libc::c_int) {
+    // SAFETY: killpg has no memory safety requirements; the group id comes from
+    // a child spawned with `process_group(0)`, so it only reaches that scan.
+    if unsafe { libc::killpg(pgid as libc::pid_t, signal) } != 0 {
+        error!("Failed to signal process group {}: {}", pgid, std::io::Error::last_os_error());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_line() {
+        let mut command = Command::new("nmap");
+        command.args(["-oX", "/app/scans/1/scan.xml", "--script", "http-title and safe", "", "it's"]);
+        assert_eq!(
+            command_line(&command),
+            "nmap -oX /app/scans/1/scan.xml --script 'http-title and safe' '' 'it'\\''s'"
+        );
+    }
+}
@@ -0,0 +1,242 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use log::{error, info};
+use tokio::sync::{Notify, Semaphore};
+use uuid::Uuid;
+
+use crate::db::Pool;
+use crate::models::project::Project;
+use crate::models::scan::Scan;
+use crate::models::scan_artifact::ScanArtifact;
+use crate::services::scanner::events::ScanEvent;
+use crate::services::scanner::types::{Error, ScanOptions};
+use crate::services::scanner::ScannerService;
+use crate::utils::config::AppConfig;
+use crate::utils::errors::AppError;
+
+/// How often the queue is checked when nothing wakes the dispatcher.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Runs scans queued in the `scans` table with a bounded number of workers per
+/// scanner type.
+pub struct ScanQueue {
+    pool: Pool,
+    scanner_service: Arc<ScannerService>,
+    slots: HashMap<&'static str, Arc<Semaphore>>,
+    notify: Notify,
+    scans_dir: PathBuf,
+}
+
+impl ScanQueue {
+    pub fn new(pool: Pool, scanner_service: Arc<ScannerService>, config: &AppConfig) -> Self {
+        let slots = scanner_service
+            .names()
+            .into_iter()
+            .map(|name| (name, Arc::new(Semaphore::new(config.scan_concurrency(name)))))
+            .collect();
+        Self {
+            pool,
+            scanner_service,
+            slots,
+            notify: Notify::new(),
+            scans_dir: PathBuf::from(&config.scans_path),
+        }
+    }
+
+    /// Wakes the dispatcher, e.g. after a scan was queued.
+    pub fn notify(&self) {
+        self.notify.notify_one();
+    }
+
+    /// Requeues scans interrupted by a restart and starts dispatching jobs.
+    pub fn start(self: Arc<Self>) {
+        tokio::spawn(async move {
+            match self.requeue_interrupted().await {
+                Ok(0) => {},
+                Ok(count) => info!("Requeued {} interrupted scans", count),
+                Err(e) => error!("Failed to requeue interrupted scans: {}", e),
+            }
+            loop {
+                self.stop_outside_window().await;
+                self.dispatch().await;
+                tokio::select! {
+                    _ = self.notify.notified() => {},
+                    _ = tokio::time::sleep(POLL_INTERVAL) => {},
+                }
+            }
+        });
+    }
+
+    async fn requeue_interrupted(&self) -> Result<usize, AppError> {
+        let pool = self.pool.clone();
+        let scanners = self.scanner_service.names();
+        tokio::task::spawn_blocking(move || {
+            let mut conn = pool.get().map_err(|e| {
+                error!("Failed to get database connection: {}", e);
+                AppError::InternalServerError
+            })?;
+            Ok(Scan::requeue_interrupted(&mut conn, &scanners)?)
+        })
+        .await
+        .map_err(|_| AppError::InternalServerError)?
+    }
+
+    async fn claim(&self, scanner: &'static str) -> Result<Option<Scan>, AppError> {
+        let pool = self.pool.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut conn = pool.get().map_err(|e| {
+                error!("Failed to get database connection: {}", e);
+                AppError::InternalServerError
+            })?;
+            Ok(Scan::claim_next(&mut conn, scanner)?)
+        })
+        .await
+        .map_err(|_| AppError::InternalServerError)?
+    }
+
+    /// Starts queued scans until every scanner type is out of jobs or workers.
+    async fn dispatch(self: &Arc<Self>) {
+        for (&scanner, slots) in &self.slots {
+            while let Ok(permit) = slots.clone().try_acquire_owned() {
+                let scan = match self.claim(scanner).await {
+                    Ok(Some(scan)) => scan,
+                    Ok(None) => break,
+                    Err(e) => {
+                        error!("Failed to claim {} scan: {}", scanner, e);
+                        break;
+                    },
+                };
+                self.scanner_service.publish(scan.id, ScanEvent::Status {
+                    status: scan.status.clone(),
+                });
+                let queue = self.clone();
+                tokio::spawn(async move {
+                    queue.run(scan).await;
+                    drop(permit);
+                    queue.notify();
+                });
+            }
+        }
+    }
+
+    /// Fails scans whose project engagement window is over or not started yet.
+    async fn check_engagement_window(&self, project_id: Uuid) -> Result<(), Error> {
+        let pool = self.pool.clone();
+        let project = tokio::task::spawn_blocking(move || {
+            let mut conn = pool.get().ok()?;
+            Project::find_by_id(&mut conn, project_id).ok().flatten()
+        })
+        .await
+        .ok()
+        .flatten();
+        match project {
+            Some(project) if project.in_engagement_window(Utc::now().naive_utc()) => Ok(()),
+            Some(_) => Err(Error::ExecutionError(
+                "Scan is outside the engagement window of the project".to_string(),
+            )),
+            None => Err(Error::ExecutionError("Failed to load the project of the scan".to_string())),
+        }
+    }
+
+    /// Cancels running scans once the engagement window of their project
+    /// closes, the dispatcher only checks it when a scan starts.
+    async fn stop_outside_window(&self) {
+        let pool = self.pool.clone();
+        let stopped = tokio::task::spawn_blocking(move || -> Result<Vec<Scan>, AppError> {
+            let mut conn = pool.get().map_err(|e| {
+                error!("Failed to get database connection: {}", e);
+                AppError::InternalServerError
+            })?;
+            let mut stopped = Vec::new();
+            for scan_id in Scan::find_running_outside_window(&mut conn, Utc::now().date_naive())? {
+                stopped.extend(Scan::mark_outside_window(&mut conn, scan_id)?);
+            }
+            Ok(stopped)
+        })
+        .await;
+
+        match stopped {
+            Ok(Ok(scans)) => {
+                for scan in scans {
+                    info!("Cancelling scan {} outside the engagement window", scan.id);
+                    self.scanner_service.cancel(scan.id);
+                    self.scanner_service.finish(scan.id, scan.status);
+                }
+            },
+            _ => error!("Failed to stop scans outside the engagement window"),
+        }
+    }
+
+    async fn run(&self, scan: Scan) {
+        let result = match self.scanner_service.get(&scan.scanner_type) {
+            Some(scanner) if !scanner.is_installed() => Err(Error::ExecutionError(format!(
+                "{} is not installed on the server",
+                scanner.name()
+            ))),
+            Some(scanner) => {
+                let options = scan.options.as_object().cloned().unwrap_or_default();
+                match self.check_engagement_window(scan.project_id).await {
+                    Ok(()) => match ScanOptions::validate(&scanner.options(), options) {
+                        Ok(options) => scanner.start_scan(scan.id, &scan.target, &options).await,
+                        Err(e) => Err(e),
+                    },
+                    Err(e) => Err(e),
+                }
+            },
+            None => Err(Error::ExecutionError(format!("Unknown scanner type: {}", scan.scanner_type))),
+        };
+
+        let scan_id = scan.id;
+        let pool = self.pool.clone();
+        let scan_dir = self.scans_dir.join(scan.id.to_string());
+        let update = tokio::task::spawn_blocking(move || -> Result<Option<Scan>, AppError> {
+            let mut conn = pool.get().map_err(|e| {
+                error!("Failed to get database connection: {}", e);
+                AppError::InternalServerError
+            })?;
+            let update = match result {
+                Ok(scan_result) => match scan_result.save_data(scan.project_id, &mut conn) {
+                    Ok(saved) => {
+                        info!(
+                            "Scan {} created {} and updated {} hosts",
+                            scan.id, saved.created, saved.updated
+                        );
+                        Scan::mark_completed(
+                            &mut conn,
+                            scan.id,
+                            scan_result.output_file().to_string(),
+                        )
+                    },
+                    Err(e) => {
+                        error!("Scan {} failed: {}", scan.id, e);
+                        Scan::mark_failed(&mut conn, scan.id, e.to_string())
+                    },
+                },
+                Err(Error::Cancelled(output_file)) => {
+                    Scan::mark_cancelled(&mut conn, scan.id, Some(output_file))
+                },
+                Err(e) => {
+                    error!("Scan failed: {}", e);
+                    Scan::mark_failed(&mut conn, scan.id, e.to_string())
+                },
+            };
+            // Failed scans keep their stderr too, which is what explains them.
+            if let Err(e) = ScanArtifact::record(&mut conn, scan.id, &scan_dir) {
+                error!("Failed to record artifacts of scan {}: {}", scan.id, e);
+            }
+            Ok(update?)
+        })
+        .await;
+
+        match update {
+            Ok(Ok(Some(scan))) => self.scanner_service.finish(scan_id, scan.status),
+            Ok(Ok(None)) => info!("Scan {} was cancelled before it finished", scan_id),
+            _ => error!("Failed to update status of scan {}", scan_id),
+        }
+        self.scanner_service.forget_cancellation(scan_id);
+    }
+}
@@ -0,0 +1,253 @@
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{NaiveDateTime, TimeDelta, Utc};
+use cron::Schedule;
+use diesel::{Connection, PgConnection};
+use log::{error, info};
+
+use crate::db::Pool;
+use crate::models::project::Project;
+use crate::models::scan::{NewScan, Scan};
+use crate::models::scan_schedule::ScanSchedule;
+use crate::services::scanner::parse_targets;
+use crate::services::scanner::queue::ScanQueue;
+use crate::services::scanner::types::{Error, ScanStatus};
+use crate::services::scope::enforce_scope;
+use crate::utils::errors::AppError;
+
+/// How often due schedules are looked up.
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Parses a standard five-field cron expression, or the six or seven-field
+/// form with seconds (and years) understood by the `cron` crate. Days of the
+/// week are numbered 0 (Sunday) to 6 in the standard form and 1 (Sunday) to 7
+/// in the others, names such as `MON` work in both.
+pub fn parse_cron(expression: &str) -> Result<Schedule, Error> {
+    let fields: Vec<&str> = expression.split_whitespace().collect();
+    let expression = match fields.as_slice() {
+        [minute, hour, day, month, weekday] => format!(
+            "0 {} {} {} {} {}",
+            minute,
+            hour,
+            day,
+            month,
+            standard_weekdays(weekday)?
+        ),
+        _ => expression.trim().to_string(),
+    };
+    Schedule::from_str(&expression)
+        .map_err(|e| Error::InvalidOption(format!("invalid cron expression: {}", e)))
+}
+
+/// Renumbers a standard day-of-week field, where 0 and 7 are Sunday, to the
+/// 1 (Sunday) to 7 of the `cron` crate. Numeric items are expanded to the
+/// days they cover, named ones mean the same in both and are kept.
+fn standard_weekdays(field: &str) -> Result<String, Error> {
+    let invalid = || Error::InvalidOption(format!("invalid day of week: {}", field));
+    let day = |value: &str| value.parse::<u32>().ok().filter(|day| *day <= 7).ok_or_else(invalid);
+    let mut items = Vec::new();
+    for item in field.split(',') {
+        if !item.chars().all(|c| c.is_ascii_digit() || matches!(c, '*' | '-' | '/')) {
+            items.push(item.to_string());
+            continue;
+        }
+        let (base, step) = match item.split_once('/') {
+            Some((base, step)) => {
+                let step = step.parse::<usize>().ok().filter(|step| *step > 0);
+                (base, step.ok_or_else(invalid)?)
+            },
+            None => (item, 1),
+        };
+        let (first, last) = match base.split_once('-') {
+            _ if base == "*" => (0, 6),
+            Some((first, last)) => (day(first)?, day(last)?),
+            None if step > 1 => (day(base)?, 6),
+            None => (day(base)?, day(base)?),
+        };
+        if first > last {
+            return Err(invalid());
+        }
+        items.extend((first..=last).step_by(step).map(|day| (day % 7 + 1).to_string()));
+    }
+    Ok(items.join(","))
+}
+
+/// First run strictly after `after` that falls inside the engagement window of
+/// the project.
+pub fn next_run(schedule: &Schedule, after: NaiveDateTime, project: &Project) -> Option<NaiveDateTime> {
+    let window_start = project.start_date.and_hms_opt(0, 0, 0)? - TimeDelta::seconds(1);
+    schedule
+        .after(&after.max(window_start).and_utc())
+        .next()
+        .map(|next| next.naive_utc())
+        .filter(|next| project.in_engagement_window(*next))
+}
+
+/// Queues scans of schedules that are due.
+pub struct ScanScheduler {
+    pool: Pool,
+    scan_queue: Arc<ScanQueue>,
+}
+
+impl ScanScheduler {
+    pub fn new(pool: Pool, scan_queue: Arc<ScanQueue>) -> Self {
+        Self { pool, scan_queue }
+    }
+
+    pub fn start(self: Arc<Self>) {
+        tokio::spawn(async move {
+            loop {
+                match self.enqueue_due().await {
+                    Ok(0) => {},
+                    Ok(count) => {
+                        info!("Queued {} scheduled scans", count);
+                        self.scan_queue.notify();
+                    },
+                    Err(e) => error!("Failed to queue scheduled scans: {}", e),
+                }
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+        });
+    }
+
+    async fn enqueue_due(&self) -> Result<usize, AppError> {
+        let pool = self.pool.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut conn = pool.get().map_err(|e| {
+                error!("Failed to get database connection: {}", e);
+                AppError::InternalServerError
+            })?;
+            let now = Utc::now().naive_utc();
+            conn.transaction(|conn| {
+                let mut queued = 0;
+                for schedule in ScanSchedule::lock_due(conn, now)? {
+                    let Some(project) = Project::find_by_id(conn, schedule.project_id)? else {
+                        continue;
+                    };
+                    if Self::enqueue(conn, &schedule, &project, now)? {
+                        queued += 1;
+                    }
+                    let next = schedule
+                        .cron
+                        .as_deref()
+                        .and_then(|expression| parse_cron(expression).ok())
+                        .and_then(|cron| next_run(&cron, now, &project));
+                    ScanSchedule::mark_run(conn, schedule.id, now, next)?;
+                }
+                Ok(queued)
+            })
+        })
+        .await
+        .map_err(|_| AppError::InternalServerError)?
+    }
+
+    /// Creates the scan of a due schedule unless it would run outside the
+    /// engagement window or the project scope.
+    fn enqueue(
+        conn: &mut PgConnection,
+        schedule: &ScanSchedule,
+        project: &Project,
+        now: NaiveDateTime,
+    ) -> Result<bool, AppError> {
+        if !project.in_engagement_window(now) {
+            info!("Skipping schedule {} outside the engagement window", schedule.id);
+            return Ok(false);
+        }
+        let targets = match parse_targets(&schedule.target) {
+            Ok(targets) => targets,
+            Err(e) => {
+                error!("Skipping schedule {}: {}", schedule.id, e);
+                return Ok(false);
+            },
+        };
+        match enforce_scope(conn, project.id, &schedule.scanner_type, &targets, None, None) {
+            Ok(()) => {},
+            Err(AppError::OutOfScope(details)) => {
+                info!("Skipping schedule {}: {}", schedule.id, details);
+                return Ok(false);
+            },
+            Err(e) => return Err(e),
+        }
+        Scan::create_scan(conn, NewScan {
+            project_id: project.id,
+            scanner_type: schedule.scanner_type.clone(),
+            status: ScanStatus::Queued.into(),
+            target: schedule.target.clone(),
+            result_path: None,
+            profile_id: schedule.profile_id,
+            options: schedule.options.clone(),
+            priority: schedule.priority,
+            schedule_id: Some(schedule.id),
+        })?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn project(start: NaiveDate, end: NaiveDate) -> Project {
+        Project {
+            id: Uuid::nil(),
+            name: "test".to_string(),
+            description: None,
+            start_date: start,
+            end_date: end,
+            folder: String::new(),
+            team_id: Uuid::nil(),
+        }
+    }
+
+    fn at(date: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_parse_cron() {
+        assert!(parse_cron("0 3 * * *").is_ok());
+        assert!(parse_cron("0 0 3 * * MON").is_ok());
+        assert!(parse_cron("every night").is_err());
+    }
+
+    #[test]
+    fn test_standard_weekdays() {
+        let weekdays = |expression: &str| -> Vec<String> {
+            parse_cron(expression)
+                .unwrap()
+                .after(&at("2026-10-18 12:00").and_utc())
+                .take(7)
+                .map(|next| next.format("%a").to_string())
+                .collect()
+        };
+
+        assert_eq!(weekdays("0 2 * * 1-5"), vec!["Mon", "Tue", "Wed", "Thu", "Fri", "Mon", "Tue"]);
+        assert_eq!(weekdays("0 2 * * 0"), vec!["Sun"; 7]);
+        assert_eq!(weekdays("0 2 * * 7"), vec!["Sun"; 7]);
+        assert_eq!(weekdays("0 2 * * 5-7"), vec!["Fri", "Sat", "Sun", "Fri", "Sat", "Sun", "Fri"]);
+        assert_eq!(weekdays("0 2 * * MON-FRI"), weekdays("0 2 * * 1-5"));
+        assert_eq!(weekdays("0 2 * * */2"), vec!["Tue", "Thu", "Sat", "Sun", "Tue", "Thu", "Sat"]);
+        // The six-field form keeps the numbering of the cron crate.
+        assert_eq!(weekdays("0 0 2 * * 2"), vec!["Mon"; 7]);
+        assert!(parse_cron("0 2 * * 8").is_err());
+        assert!(parse_cron("0 2 * * 5-1").is_err());
+    }
+
+    #[test]
+    fn test_next_run_stays_in_window() {
+        let project = project(
+            NaiveDate::from_ymd_opt(2026, 3, 1).unwrap(),
+            NaiveDate::from_ymd_opt(2026, 3, 31).unwrap(),
+        );
+        let nightly = parse_cron("0 3 * * *").unwrap();
+
+        assert_eq!(next_run(&nightly, at("2026-01-15 12:00"), &project), Some(at("2026-03-01 03:00")));
+        assert_eq!(next_run(&nightly, at("2026-03-10 03:00"), &project), Some(at("2026-03-11 03:00")));
+        assert_eq!(next_run(&nightly, at("2026-03-31 03:00"), &project), None);
+    }
+}
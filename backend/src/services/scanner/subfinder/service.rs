@@ -0,0 +1,483 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use async_trait::async_trait;
+use futures::stream::{self, StreamExt};
+use log::{debug, error};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::dtos::handlers::HostForm;
+use crate::services::scanner::types::{
+    AnyScanResult, Error, HostsScanResult, ScanOptions, ScannerCapability, ScannerOption,
+    ScannerOptionKind,
+};
+use crate::services::scanner::events::ScanProgress;
+use crate::services::scanner::process::ProcessRegistry;
+use crate::services::scanner::{is_binary_installed, parse_targets, VulnerabilityScanner};
+use crate::services::scope::IpRange;
+
+/// Names longer than the hostname column are dropped.
+const MAX_HOSTNAME_LENGTH: usize = 100;
+const LOOKUP_TIMEOUT: Duration = Duration::from_secs(5);
+const LOOKUP_CONCURRENCY: usize = 32;
+
+/// One line of subfinder `-oJ` output. amass `-json` lines use other field
+/// names for the same data and are read as well.
+#[derive(Debug, Deserialize)]
+struct Record {
+    #[serde(alias = "name")]
+    host: String,
+    #[serde(default)]
+    ip: Option<String>,
+    #[serde(default)]
+    addresses: Vec<AmassAddress>,
+    #[serde(default)]
+    source: Option<String>,
+    #[serde(default)]
+    sources: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AmassAddress {
+    ip: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Subdomain {
+    pub name: String,
+    pub addresses: Vec<String>,
+    pub sources: Vec<String>,
+}
+
+/// DNS lookups done after subfinder finished, stored next to its output so
+/// a stored scan yields the same hosts.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Resolution {
+    /// Addresses of names subfinder reported without one.
+    pub addresses: BTreeMap<String, Vec<String>>,
+    /// Addresses a random name under each parent domain resolves to.
+    pub wildcards: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Clone)]
+pub struct SubfinderService {
+    scans_dir: PathBuf,
+    processes: ProcessRegistry,
+}
+
+fn parent_domain(name: &str) -> Option<&str> {
+    name.split_once('.').map(|(_, parent)| parent).filter(|parent| parent.contains('.'))
+}
+
+fn push_unique(values: &mut Vec<String>, value: String) {
+    if !values.contains(&value) {
+        values.push(value);
+    }
+}
+
+async fn lookup(name: &str) -> Vec<String> {
+    let mut addresses = Vec::new();
+    if let Ok(Ok(resolved)) =
+        tokio::time::timeout(LOOKUP_TIMEOUT, tokio::net::lookup_host((name, 0))).await
+    {
+        for address in resolved {
+            push_unique(&mut addresses, address.ip().to_string());
+        }
+    }
+    addresses
+}
+
+impl SubfinderService {
+    pub fn new(scans_dir: impl AsRef<Path>, processes: ProcessRegistry) -> Self {
+        Self {
+            scans_dir: scans_dir.as_ref().to_path_buf(),
+            processes,
+        }
+    }
+
+    fn check_targets(targets: &[String]) -> Result<(), Error> {
+        match targets.iter().find(|target| {
+            IpRange::parse(target).is_some() || target.contains('/') || !target.contains('.')
+        }) {
+            Some(target) => Err(Error::InvalidTarget(format!(
+                "{}: subfinder accepts only domain names",
+                target
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Translates validated scan options into subfinder arguments.
+    fn build_args(options: &ScanOptions, output_file: &str, targets: &[String]) -> Vec<String> {
+        let mut args = vec![
+            "-d".to_string(),
+            targets.join(","),
+            "-oJ".to_string(),
+            "-cs".to_string(),
+            "-active".to_string(),
+            "-oI".to_string(),
+            "-silent".to_string(),
+            "-o".to_string(),
+            output_file.to_string(),
+        ];
+        let sources = options.list("sources");
+        if !sources.is_empty() {
+            args.extend(["-s".to_string(), sources.join(",")]);
+        }
+        if options.flag("all_sources") {
+            args.push("-all".to_string());
+        }
+        if let Some(rate_limit) = options.integer("rate_limit") {
+            args.extend(["-rl".to_string(), rate_limit.to_string()]);
+        }
+        args
+    }
+
+    /// subfinder doesn't report progress.
+    fn parse_progress(_line: &str) -> Option<ScanProgress> {
+        None
+    }
+
+    /// Parses subfinder `-oJ` or amass `-json` output. Lines of the same name
+    /// are merged, wildcard certificate names such as `*.example.com` are
+    /// skipped.
+    pub fn parse_output(content: &str) -> Result<Vec<Subdomain>, Error> {
+        let mut subdomains: Vec<Subdomain> = Vec::new();
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let record: Record =
+                serde_json::from_str(line).map_err(|e| Error::ParseError(e.to_string()))?;
+            let name = record.host.trim().trim_end_matches('.').to_lowercase();
+            if name.starts_with("*.") || name.is_empty() || name.len() > MAX_HOSTNAME_LENGTH {
+                continue;
+            }
+
+            let index = match subdomains.iter().position(|subdomain| subdomain.name == name) {
+                Some(index) => index,
+                None => {
+                    subdomains.push(Subdomain { name, addresses: Vec::new(), sources: Vec::new() });
+                    subdomains.len() - 1
+                },
+            };
+            let subdomain = &mut subdomains[index];
+            let addresses = record.ip.into_iter().chain(record.addresses.into_iter().map(|a| a.ip));
+            for address in addresses {
+                push_unique(&mut subdomain.addresses, address);
+            }
+            for source in record.source.into_iter().chain(record.sources) {
+                push_unique(&mut subdomain.sources, source.to_lowercase());
+            }
+        }
+        Ok(subdomains)
+    }
+
+    /// Looks up names without an address and probes every parent domain for
+    /// wildcard DNS.
+    async fn resolve(subdomains: &[Subdomain]) -> Resolution {
+        let unresolved: Vec<String> = subdomains
+            .iter()
+            .filter(|subdomain| subdomain.addresses.is_empty())
+            .map(|subdomain| subdomain.name.clone())
+            .collect();
+        let parents: BTreeSet<String> = subdomains
+            .iter()
+            .filter_map(|subdomain| parent_domain(&subdomain.name))
+            .map(str::to_string)
+            .collect();
+
+        let addresses = stream::iter(unresolved)
+            .map(|name| async move {
+                let addresses = lookup(&name).await;
+                (name, addresses)
+            })
+            .buffer_unordered(LOOKUP_CONCURRENCY)
+            .filter(|(_, addresses)| futures::future::ready(!addresses.is_empty()))
+            .collect()
+            .await;
+        let wildcards = stream::iter(parents)
+            .map(|parent| async move {
+                let probe = format!("{}.{}", Uuid::new_v4().simple(), parent);
+                let addresses = lookup(&probe).await;
+                (parent, addresses)
+            })
+            .buffer_unordered(LOOKUP_CONCURRENCY)
+            .filter(|(_, addresses)| futures::future::ready(!addresses.is_empty()))
+            .collect()
+            .await;
+        Resolution { addresses, wildcards }
+    }
+
+    /// Turns subdomains into one host per address, which lists every name
+    /// resolving to it. Names that did not resolve or only resolve to the
+    /// wildcard addresses of their parent domain are dropped.
+    pub fn to_hosts(subdomains: Vec<Subdomain>, resolution: &Resolution) -> Vec<HostForm> {
+        let mut hosts: Vec<HostForm> = Vec::new();
+        for subdomain in subdomains {
+            let mut addresses = subdomain.addresses;
+            for address in resolution.addresses.get(&subdomain.name).into_iter().flatten() {
+                push_unique(&mut addresses, address.clone());
+            }
+            let wildcard = parent_domain(&subdomain.name)
+                .and_then(|parent| resolution.wildcards.get(parent))
+                .is_some_and(|wildcard| addresses.iter().all(|address| wildcard.contains(address)));
+            if addresses.is_empty() || wildcard {
+                debug!("Skipping subdomain {}", subdomain.name);
+                continue;
+            }
+
+            let sources: Vec<String> = subdomain
+                .sources
+                .iter()
+                .map(|source| format!("subfinder:{}", source))
+                .collect();
+            for address in addresses {
+                match hosts.iter_mut().find(|host| host.ip_address == address) {
+                    Some(host) => {
+                        push_unique(&mut host.hostnames, subdomain.name.clone());
+                        for source in &sources {
+                            push_unique(&mut host.sources, source.clone());
+                        }
+                    },
+                    None => hosts.push(HostForm {
+                        hostname: Some(subdomain.name.clone()),
+                        ip_address: address,
+                        os: None,
+                        os_accuracy: None,
+                        services: Vec::new(),
+                        sources: sources.clone(),
+                        hostnames: vec![subdomain.name.clone()],
+                    }),
+                }
+            }
+        }
+        hosts
+    }
+
+    fn resolution_file(output_file: &Path) -> PathBuf {
+        output_file.with_file_name("resolved.json")
+    }
+}
+
+#[async_trait]
+impl VulnerabilityScanner for SubfinderService {
+    fn name(&self) -> &'static str {
+        "subfinder"
+    }
+
+    fn capabilities(&self) -> Vec<ScannerCapability> {
+        vec![ScannerCapability::SubdomainEnumeration]
+    }
+
+    fn options(&self) -> Vec<ScannerOption> {
+        vec![
+            ScannerOption {
+                name: "sources",
+                description: "Passive sources to query",
+                kind: ScannerOptionKind::List,
+            },
+            ScannerOption {
+                name: "all_sources",
+                description: "Query all sources, including slow ones",
+                kind: ScannerOptionKind::Boolean,
+            },
+            ScannerOption {
+                name: "rate_limit",
+                description: "Maximum number of requests per second",
+                kind: ScannerOptionKind::Integer { min: 1, max: 10000 },
+            },
+        ]
+    }
+
+    fn is_installed(&self) -> bool {
+        is_binary_installed("subfinder")
+    }
+
+    async fn get_scan_result(&self, scan_id: Uuid) -> Result<AnyScanResult, Error> {
+        self.load_result(&self.scans_dir.join(scan_id.to_string()).join("scan.jsonl"))
+    }
+
+    fn load_result(&self, output_file: &Path) -> Result<AnyScanResult, Error> {
+        let content = std::fs::read_to_string(output_file).map_err(|e| {
+            Error::IoError(format!("Failed to read result file: {}", e))
+        })?;
+        let resolution = match std::fs::read_to_string(Self::resolution_file(output_file)) {
+            Ok(resolution) => serde_json::from_str(&resolution)
+                .map_err(|e| Error::ParseError(e.to_string()))?,
+            Err(_) => Resolution::default(),
+        };
+
+        Ok(AnyScanResult::Hosts(HostsScanResult {
+            output_file: output_file.display().to_string(),
+            hosts: Self::to_hosts(Self::parse_output(&content)?, &resolution),
+        }))
+    }
+
+    async fn start_scan(
+        &self,
+        scan_id: Uuid,
+        target: &str,
+        options: &ScanOptions,
+    ) -> Result<AnyScanResult, Error> {
+        let targets = parse_targets(target)?;
+        Self::check_targets(&targets)?;
+        let scan_path = self.scans_dir.join(scan_id.to_string());
+        tokio::fs::create_dir_all(&scan_path).await.map_err(|e| {
+            error!("Failed to create scan directory: {}", e);
+            Error::IoError(format!("Failed to create scan directory: {}", e))
+        })?;
+
+        let output_file = scan_path.join("scan.jsonl");
+        let output = output_file.display().to_string();
+
+        let mut command = tokio::process::Command::new("subfinder");
+        command.args(Self::build_args(options, &output, &targets));
+        self.processes
+            .run(scan_id, command, &output, Self::parse_progress)
+            .await?
+            .check("subfinder")?;
+
+        let content = match tokio::fs::read_to_string(&output_file).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => {
+                error!("Failed to read result file: {}", e);
+                return Err(Error::IoError(format!("Failed to read result file: {}", e)));
+            },
+        };
+        let subdomains = Self::parse_output(&content).map_err(|e| {
+            error!("Failed to parse result file: {}", e);
+            e
+        })?;
+
+        let resolution = Self::resolve(&subdomains).await;
+        let resolution_json = serde_json::to_string_pretty(&resolution)
+            .map_err(|e| Error::IoError(e.to_string()))?;
+        tokio::fs::write(Self::resolution_file(&output_file), resolution_json)
+            .await
+            .map_err(|e| Error::IoError(format!("Failed to write resolved addresses: {}", e)))?;
+
+        Ok(AnyScanResult::Hosts(HostsScanResult {
+            output_file: output,
+            hosts: Self::to_hosts(subdomains, &resolution),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn fixture() -> String {
+        fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/subfinder/scan.jsonl"))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_parse_output() {
+        let subdomains = SubfinderService::parse_output(&fixture()).unwrap();
+        let names: Vec<&str> = subdomains.iter().map(|subdomain| subdomain.name.as_str()).collect();
+        assert_eq!(names, vec![
+            "www.example.com",
+            "api.example.com",
+            "x7f3k.dev.example.com",
+            "shop.dev.example.com",
+            "old.example.com",
+        ]);
+        assert_eq!(subdomains[0].sources, vec!["crtsh", "alienvault"]);
+        assert_eq!(subdomains[1].addresses, vec!["93.184.216.40", "93.184.216.41"]);
+        assert_eq!(subdomains[1].sources, vec!["hackertarget", "crtsh"]);
+        assert!(subdomains[4].addresses.is_empty());
+    }
+
+    #[test]
+    fn test_parse_amass_output() {
+        let line = r#"{"name":"mail.example.com","domain":"example.com","addresses":[{"ip":"93.184.216.50","cidr":"93.184.216.0/24","asn":15133,"desc":"EDGECAST"}],"tag":"cert","sources":["Crtsh"]}"#;
+        let subdomains = SubfinderService::parse_output(line).unwrap();
+        assert_eq!(subdomains, vec![Subdomain {
+            name: "mail.example.com".to_string(),
+            addresses: vec!["93.184.216.50".to_string()],
+            sources: vec!["crtsh".to_string()],
+        }]);
+    }
+
+    #[test]
+    fn test_to_hosts_filters_wildcards() {
+        let subdomains = SubfinderService::parse_output(&fixture()).unwrap();
+        let resolution = Resolution {
+            addresses: BTreeMap::from([("old.example.com".to_string(), vec!["93.184.216.34".to_string()])]),
+            wildcards: BTreeMap::from([("dev.example.com".to_string(), vec!["203.0.113.10".to_string()])]),
+        };
+
+        let hosts = SubfinderService::to_hosts(subdomains, &resolution);
+        let addresses: Vec<(&str, Option<&str>)> = hosts
+            .iter()
+            .map(|host| (host.ip_address.as_str(), host.hostname.as_deref()))
+            .collect();
+        assert_eq!(addresses, vec![
+            ("93.184.216.34", Some("www.example.com")),
+            ("93.184.216.40", Some("api.example.com")),
+            ("93.184.216.41", Some("api.example.com")),
+        ]);
+        assert_eq!(hosts[0].sources, vec![
+            "subfinder:crtsh",
+            "subfinder:alienvault",
+            "subfinder:waybackarchive",
+        ]);
+        assert_eq!(hosts[0].hostnames, vec!["www.example.com", "old.example.com"]);
+
+        let hosts = SubfinderService::to_hosts(
+            SubfinderService::parse_output(&fixture()).unwrap(),
+            &Resolution::default(),
+        );
+        assert!(hosts.iter().any(|host| host.hostname.as_deref() == Some("x7f3k.dev.example.com")));
+    }
+
+    #[test]
+    fn test_to_hosts_keeps_names_of_shared_address() {
+        let subdomain = |name: &str, source: &str| Subdomain {
+            name: name.to_string(),
+            addresses: vec!["104.16.0.1".to_string()],
+            sources: vec![source.to_string()],
+        };
+        let hosts = SubfinderService::to_hosts(
+            vec![
+                subdomain("shop.example.com", "crtsh"),
+                subdomain("blog.example.com", "alienvault"),
+                subdomain("shop.example.com", "alienvault"),
+            ],
+            &Resolution::default(),
+        );
+
+        assert_eq!(hosts.len(), 1);
+        assert_eq!(hosts[0].hostname.as_deref(), Some("shop.example.com"));
+        assert_eq!(hosts[0].hostnames, vec!["shop.example.com", "blog.example.com"]);
+        assert_eq!(hosts[0].sources, vec!["subfinder:crtsh", "subfinder:alienvault"]);
+    }
+
+    #[test]
+    fn test_build_args() {
+        let options = ScanOptions::validate(
+            &SubfinderService::new("/tmp", ProcessRegistry::default()).options(),
+            serde_json::json!({ "sources": ["crtsh", "hackertarget"] })
+                .as_object()
+                .unwrap()
+                .clone(),
+        )
+        .unwrap();
+        let targets = parse_targets("example.com,example.org").unwrap();
+
+        let args = SubfinderService::build_args(&options, "/tmp/scan.jsonl", &targets);
+        assert_eq!(args, vec![
+            "-d", "example.com,example.org", "-oJ", "-cs", "-active", "-oI", "-silent", "-o",
+            "/tmp/scan.jsonl", "-s", "crtsh,hackertarget",
+        ]);
+        assert!(SubfinderService::check_targets(&targets).is_ok());
+        assert!(SubfinderService::check_targets(&parse_targets("10.0.0.0/24").unwrap()).is_err());
+    }
+}
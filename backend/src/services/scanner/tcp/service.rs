@@ -0,0 +1,446 @@
+use std::collections::BTreeMap;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use async_trait::async_trait;
+use futures::{stream, StreamExt};
+use log::{debug, error};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{lookup_host, TcpStream};
+use tokio::time::timeout;
+use uuid::Uuid;
+
+use crate::dtos::handlers::{HostForm, ServiceForm};
+use crate::services::scanner::types::{
+    tcp_ports, AnyScanResult, Error, HostsScanResult, ScanOptions, ScannerCapability,
+    ScannerOption, ScannerOptionKind,
+};
+use crate::services::scanner::events::ScanProgress;
+use crate::services::scanner::process::ProcessRegistry;
+use crate::services::scanner::{parse_targets, VulnerabilityScanner};
+use crate::services::scope::IpRange;
+
+/// The 100 most common TCP ports according to nmap.
+const COMMON_PORTS: [u16; 100] = [
+    7, 9, 13, 21, 22, 23, 25, 26, 37, 53, 79, 80, 81, 88, 106, 110, 111, 113, 119, 135, 139, 143,
+    144, 179, 199, 389, 427, 443, 444, 445, 465, 513, 514, 515, 543, 544, 548, 554, 587, 631, 646,
+    873, 990, 993, 995, 1025, 1026, 1027, 1028, 1029, 1110, 1433, 1720, 1723, 1755, 1900, 2000,
+    2001, 2049, 2121, 2717, 3000, 3128, 3306, 3389, 3986, 4899, 5000, 5009, 5051, 5060, 5101, 5190,
+    5357, 5432, 5631, 5666, 5800, 5900, 6000, 6001, 6646, 7070, 8000, 8008, 8009, 8080, 8081, 8443,
+    8888, 9100, 9999, 10000, 32768, 49152, 49153, 49154, 49155, 49156, 49157,
+];
+const DEFAULT_CONCURRENCY: i64 = 200;
+const DEFAULT_TIMEOUT_MS: i64 = 1000;
+/// Keeps a mistyped CIDR block from turning into millions of connections.
+const MAX_ADDRESSES: u128 = 65536;
+/// Bounded by the columns of host services.
+const MAX_BANNER_LENGTH: usize = 200;
+const MAX_VERSION_LENGTH: usize = 100;
+/// Sent to services that wait for the client to speak first.
+const HTTP_PROBE: &[u8] = b"HEAD / HTTP/1.0\r\n\r\n";
+
+/// What an open port told about itself.
+#[derive(Debug, Default, PartialEq)]
+struct Banner {
+    service_name: Option<String>,
+    product: Option<String>,
+    version: Option<String>,
+    line: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct TcpConnectService {
+    scans_dir: PathBuf,
+    processes: ProcessRegistry,
+}
+
+impl TcpConnectService {
+    pub fn new(scans_dir: impl AsRef<Path>, processes: ProcessRegistry) -> Self {
+        Self {
+            scans_dir: scans_dir.as_ref().to_path_buf(),
+            processes,
+        }
+    }
+
+    /// Expands addresses, ranges and CIDR blocks and resolves host names,
+    /// which are kept as the host name of their addresses.
+    async fn resolve_targets(targets: &[String]) -> Result<Vec<(IpAddr, Option<String>)>, Error> {
+        let mut addresses: Vec<(IpAddr, Option<String>)> = Vec::new();
+        for target in targets {
+            let resolved: Vec<(IpAddr, Option<String>)> = match IpRange::parse(target) {
+                Some(range) if range.size() > MAX_ADDRESSES - addresses.len() as u128 => {
+                    return Err(Error::InvalidTarget(format!(
+                        "{}: at most {} addresses can be scanned at once",
+                        target, MAX_ADDRESSES
+                    )));
+                },
+                Some(range) => range.addresses().map(|ip| (ip, None)).collect(),
+                None => lookup_host((target.as_str(), 0))
+                    .await
+                    .map_err(|e| Error::InvalidTarget(format!("{}: {}", target, e)))?
+                    .map(|address| (address.ip(), Some(target.clone())))
+                    .collect(),
+            };
+            for (ip, hostname) in resolved {
+                if !addresses.iter().any(|(known, _)| *known == ip) {
+                    addresses.push((ip, hostname));
+                }
+            }
+        }
+        if addresses.len() as u128 > MAX_ADDRESSES {
+            return Err(Error::InvalidTarget(format!(
+                "at most {} addresses can be scanned at once",
+                MAX_ADDRESSES
+            )));
+        }
+        Ok(addresses)
+    }
+
+    /// Every port of every address, generated lazily since the product of a
+    /// large range and port list doesn't fit in memory.
+    fn socket_addresses(ips: Vec<IpAddr>, ports: Vec<u16>) -> impl Iterator<Item = SocketAddr> + Send {
+        ips.into_iter().flat_map(move |ip| {
+            ports.clone().into_iter().map(move |port| SocketAddr::new(ip, port))
+        })
+    }
+
+    /// Connects to the port, returning `None` if it is not open.
+    async fn probe(address: SocketAddr, wait: Duration, banners: bool) -> Option<Banner> {
+        let mut stream = timeout(wait, TcpStream::connect(address)).await.ok()?.ok()?;
+        if !banners {
+            return Some(Banner::default());
+        }
+        Some(Self::grab_banner(&mut stream, wait).await.unwrap_or_default())
+    }
+
+    /// Waits for the service to greet first, then tries an HTTP request.
+    async fn grab_banner(stream: &mut TcpStream, wait: Duration) -> Option<Banner> {
+        let mut buffer = [0u8; 1024];
+        let read = match timeout(wait, stream.read(&mut buffer)).await {
+            Ok(Ok(read)) if read > 0 => read,
+            Ok(Ok(_)) | Ok(Err(_)) => return None,
+            Err(_) => {
+                stream.write_all(HTTP_PROBE).await.ok()?;
+                timeout(wait, stream.read(&mut buffer)).await.ok()?.ok()?
+            },
+        };
+        Some(Self::parse_banner(&buffer[..read]))
+    }
+
+    fn parse_banner(data: &[u8]) -> Banner {
+        let text = String::from_utf8_lossy(data);
+        let line: String = text
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .chars()
+            .filter(|c| !c.is_control())
+            .take(MAX_BANNER_LENGTH)
+            .collect();
+        let mut banner = Banner {
+            line: Some(line.clone()).filter(|line| !line.is_empty()),
+            ..Banner::default()
+        };
+        // SSH-2.0-OpenSSH_9.6p1 Ubuntu-3
+        let ssh_software = line
+            .strip_prefix("SSH-")
+            .and_then(|rest| rest.split_once('-'))
+            .map(|(_, software)| software);
+        if let Some(software) = ssh_software {
+            let software = software.split_whitespace().next().unwrap_or_default();
+            let (product, version) = software.split_once('_').unwrap_or((software, ""));
+            banner.service_name = Some("ssh".to_string());
+            banner.product = Some(product.to_string()).filter(|product| !product.is_empty());
+            banner.version = Some(version.chars().take(MAX_VERSION_LENGTH).collect())
+                .filter(|version: &String| !version.is_empty());
+        } else if line.starts_with("HTTP/") {
+            banner.service_name = Some("http".to_string());
+            banner.product = text.lines().find_map(|header| {
+                let (name, value) = header.split_once(':')?;
+                name.eq_ignore_ascii_case("server")
+                    .then(|| value.trim().chars().take(MAX_BANNER_LENGTH).collect())
+            });
+        }
+        banner
+    }
+
+    fn to_hosts(
+        addresses: &[(IpAddr, Option<String>)],
+        mut open: BTreeMap<IpAddr, Vec<ServiceForm>>,
+    ) -> Vec<HostForm> {
+        addresses
+            .iter()
+            .filter_map(|(ip, hostname)| {
+                let mut services = open.remove(ip)?;
+                services.sort_by_key(|service| service.port);
+                Some(HostForm {
+                    hostname: hostname.clone(),
+                    ip_address: ip.to_string(),
+                    os: None,
+                    os_accuracy: None,
+                    services,
+                    sources: Vec::new(),
+                    hostnames: Vec::new(),
+                })
+            })
+            .collect()
+    }
+
+    fn parse_output(content: &str) -> Result<Vec<HostForm>, Error> {
+        serde_json::from_str(content).map_err(|e| Error::ParseError(e.to_string()))
+    }
+
+    fn write_output(output_file: &str, hosts: &[HostForm]) -> Result<(), Error> {
+        let content = serde_json::to_string_pretty(hosts).map_err(|e| Error::ParseError(e.to_string()))?;
+        std::fs::write(output_file, content).map_err(|e| {
+            error!("Failed to write result file: {}", e);
+            Error::IoError(format!("Failed to write result file: {}", e))
+        })
+    }
+}
+
+#[async_trait]
+impl VulnerabilityScanner for TcpConnectService {
+    fn name(&self) -> &'static str {
+        "tcp-connect"
+    }
+
+    fn capabilities(&self) -> Vec<ScannerCapability> {
+        vec![ScannerCapability::PortScan]
+    }
+
+    fn options(&self) -> Vec<ScannerOption> {
+        vec![
+            ScannerOption {
+                name: "ports",
+                description: "TCP ports to scan, defaults to the 100 most common ones",
+                kind: ScannerOptionKind::Ports,
+            },
+            ScannerOption {
+                name: "concurrency",
+                description: "Number of connection attempts in flight",
+                kind: ScannerOptionKind::Integer { min: 1, max: 5000 },
+            },
+            ScannerOption {
+                name: "timeout_ms",
+                description: "Milliseconds to wait for a connection or a banner",
+                kind: ScannerOptionKind::Integer { min: 50, max: 30000 },
+            },
+            ScannerOption {
+                name: "banners",
+                description: "Read the greeting of open ports, or their answer to an HTTP request",
+                kind: ScannerOptionKind::Boolean,
+            },
+        ]
+    }
+
+    /// Runs in process, so it works where no scanner binary is installed.
+    fn is_installed(&self) -> bool {
+        true
+    }
+
+    async fn get_scan_result(&self, scan_id: Uuid) -> Result<AnyScanResult, Error> {
+        self.load_result(&self.scans_dir.join(scan_id.to_string()).join("scan.json"))
+    }
+
+    fn load_result(&self, output_file: &Path) -> Result<AnyScanResult, Error> {
+        let content = std::fs::read_to_string(output_file).map_err(|e| {
+            Error::IoError(format!("Failed to read result file: {}", e))
+        })?;
+
+        Ok(AnyScanResult::Hosts(HostsScanResult {
+            output_file: output_file.display().to_string(),
+            hosts: Self::parse_output(&content)?,
+        }))
+    }
+
+    async fn start_scan(
+        &self,
+        scan_id: Uuid,
+        target: &str,
+        options: &ScanOptions,
+    ) -> Result<AnyScanResult, Error> {
+        let targets = parse_targets(target)?;
+        let ports = match options.string("ports") {
+            Some(spec) => tcp_ports(spec),
+            None => COMMON_PORTS.to_vec(),
+        };
+        if ports.is_empty() {
+            return Err(Error::InvalidOption("ports: no TCP ports given".to_string()));
+        }
+        let addresses = Self::resolve_targets(&targets).await?;
+        let concurrency = options.integer("concurrency").unwrap_or(DEFAULT_CONCURRENCY) as usize;
+        let wait = Duration::from_millis(options.integer("timeout_ms").unwrap_or(DEFAULT_TIMEOUT_MS) as u64);
+        let banners = options.flag("banners");
+
+        let scan_path = self.scans_dir.join(scan_id.to_string());
+        tokio::fs::create_dir_all(&scan_path).await.map_err(|e| {
+            error!("Failed to create scan directory: {}", e);
+            Error::IoError(format!("Failed to create scan directory: {}", e))
+        })?;
+        let output_file = scan_path.join("scan.json").display().to_string();
+
+        debug!(
+            "Scan {} ports on {} addresses with {} connections",
+            ports.len(),
+            addresses.len(),
+            concurrency
+        );
+        let total = (addresses.len() * ports.len()) as u64;
+        let probes = Self::socket_addresses(addresses.iter().map(|(ip, _)| *ip).collect(), ports);
+        let mut results = stream::iter(probes)
+            .map(move |address| async move { (address, Self::probe(address, wait, banners).await) })
+            .buffer_unordered(concurrency);
+
+        let mut open: BTreeMap<IpAddr, Vec<ServiceForm>> = BTreeMap::new();
+        let (mut done, mut findings, mut reported) = (0u64, 0u64, 0u64);
+        while let Some((address, banner)) = results.next().await {
+            // Dropping the stream abandons the connections in flight.
+            if self.processes.is_cancelled(scan_id) {
+                break;
+            }
+            done += 1;
+            if let Some(banner) = banner {
+                findings += 1;
+                open.entry(address.ip()).or_default().push(ServiceForm {
+                    port: address.port() as i32,
+                    protocol: "tcp".to_string(),
+                    state: "open".to_string(),
+                    service_name: banner.service_name,
+                    product: banner.product,
+                    version: banner.version,
+                    extra_info: banner.line,
+                    cpe: None,
+                });
+            }
+            let percent = done * 100 / total;
+            if percent > reported {
+                reported = percent;
+                self.processes.publish_progress(scan_id, ScanProgress {
+                    percent: Some(percent as f64),
+                    hosts: Some(open.len() as u64),
+                    findings: Some(findings),
+                });
+            }
+        }
+
+        let hosts = Self::to_hosts(&addresses, open);
+        Self::write_output(&output_file, &hosts)?;
+        if self.processes.take_cancelled(scan_id) {
+            return Err(Error::Cancelled(output_file));
+        }
+        Ok(AnyScanResult::Hosts(HostsScanResult { output_file, hosts }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    fn scan_options(ports: &str) -> ScanOptions {
+        ScanOptions::validate(
+            &TcpConnectService::new("/tmp", ProcessRegistry::default()).options(),
+            serde_json::json!({ "ports": ports, "timeout_ms": 300, "banners": true })
+                .as_object()
+                .unwrap()
+                .clone(),
+        )
+        .unwrap()
+    }
+
+    /// Accepts connections on 127.0.0.1, greeting every client with `greeting`
+    /// or answering its first request with `answer`.
+    async fn listen(greeting: &'static [u8], answer: &'static [u8]) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let _ = stream.write_all(greeting).await;
+                    let mut buffer = [0u8; 256];
+                    if stream.read(&mut buffer).await.is_ok() {
+                        let _ = stream.write_all(answer).await;
+                    }
+                });
+            }
+        });
+        port
+    }
+
+    #[test]
+    fn test_parse_banner() {
+        let ssh = TcpConnectService::parse_banner(b"SSH-2.0-OpenSSH_9.6p1 Ubuntu-3ubuntu13\r\n");
+        assert_eq!(ssh, Banner {
+            service_name: Some("ssh".to_string()),
+            product: Some("OpenSSH".to_string()),
+            version: Some("9.6p1".to_string()),
+            line: Some("SSH-2.0-OpenSSH_9.6p1 Ubuntu-3ubuntu13".to_string()),
+        });
+
+        let http = TcpConnectService::parse_banner(b"HTTP/1.1 404 Not Found\r\nserver: nginx/1.25.3\r\n\r\n");
+        assert_eq!(http.service_name.as_deref(), Some("http"));
+        assert_eq!(http.product.as_deref(), Some("nginx/1.25.3"));
+        assert_eq!(http.line.as_deref(), Some("HTTP/1.1 404 Not Found"));
+
+        let smtp = TcpConnectService::parse_banner(b"220 mail.example.com ESMTP\x07\r\n");
+        assert_eq!(smtp.service_name, None);
+        assert_eq!(smtp.line.as_deref(), Some("220 mail.example.com ESMTP"));
+    }
+
+    #[tokio::test]
+    async fn test_scan_local_listeners() {
+        let ssh = listen(b"SSH-2.0-OpenSSH_9.6\r\n", b"").await;
+        let http = listen(b"", b"HTTP/1.0 200 OK\r\nServer: test/1.0\r\n\r\n").await;
+        let closed = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap().port();
+
+        let scans_dir = std::env::temp_dir().join(format!("tcp-connect-{}", Uuid::new_v4()));
+        let scanner = TcpConnectService::new(&scans_dir, ProcessRegistry::default());
+        let ports = format!("{},{},{}", ssh, http, closed);
+        let result = scanner
+            .start_scan(Uuid::new_v4(), "127.0.0.1", &scan_options(&ports))
+            .await
+            .unwrap();
+        let AnyScanResult::Hosts(result) = result else {
+            panic!("expected hosts");
+        };
+
+        assert_eq!(result.hosts.len(), 1);
+        let host = &result.hosts[0];
+        assert_eq!(host.ip_address, "127.0.0.1");
+        let mut services: Vec<_> = host
+            .services
+            .iter()
+            .map(|service| (service.port as u16, service.service_name.as_deref(), service.product.as_deref()))
+            .collect();
+        services.sort();
+        let mut expected = vec![(ssh, Some("ssh"), Some("OpenSSH")), (http, Some("http"), Some("test/1.0"))];
+        expected.sort();
+        assert_eq!(services, expected);
+
+        let AnyScanResult::Hosts(loaded) = scanner.load_result(Path::new(&result.output_file)).unwrap() else {
+            panic!("expected hosts");
+        };
+        assert_eq!(loaded.hosts.len(), 1);
+        std::fs::remove_dir_all(scans_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_scan_keeps_partial_results() {
+        let processes = ProcessRegistry::default();
+        let scans_dir = std::env::temp_dir().join(format!("tcp-connect-{}", Uuid::new_v4()));
+        let scanner = TcpConnectService::new(&scans_dir, processes.clone());
+        let scan_id = Uuid::new_v4();
+        processes.cancel(scan_id);
+
+        let result = scanner.start_scan(scan_id, "127.0.0.1", &scan_options("1-1024")).await;
+        let Err(Error::Cancelled(output_file)) = result else {
+            panic!("expected the scan to be cancelled");
+        };
+        assert!(Path::new(&output_file).is_file());
+        assert!(!processes.is_cancelled(scan_id));
+        std::fs::remove_dir_all(scans_dir).unwrap();
+    }
+}
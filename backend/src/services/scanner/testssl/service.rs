@@ -0,0 +1,633 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use async_trait::async_trait;
+use log::{debug, error};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::dtos::handlers::{ClassificationForm, EvidenceForm, HostForm, IssueForm};
+use crate::services::scanner::types::{
+    AnyScanResult, Error, IssuesScanResult, ScanOptions, ScannerCapability, ScannerOption,
+    ScannerOptionKind,
+};
+use crate::services::scanner::events::ScanProgress;
+use crate::services::scanner::process::ProcessRegistry;
+use crate::services::scanner::{is_binary_installed, parse_targets, VulnerabilityScanner};
+
+/// Exit codes from here up are testssl.sh's own errors, lower ones only
+/// count problems found on the targets.
+const FIRST_ERROR_STATUS: i32 = 242;
+
+/// One entry of a `--jsonfile` report.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TestsslFinding {
+    pub id: String,
+    /// `hostname/ip` of the tested endpoint.
+    pub ip: String,
+    pub port: String,
+    pub severity: String,
+    #[serde(default)]
+    pub cve: Option<String>,
+    #[serde(default)]
+    pub cwe: Option<String>,
+    #[serde(default)]
+    pub finding: String,
+}
+
+/// Issue a group of testssl.sh checks is reported as.
+struct TlsIssue {
+    key: &'static str,
+    name: &'static str,
+    description: &'static str,
+    mitigation: &'static str,
+    references: &'static [&'static str],
+}
+
+const PROTOCOLS: TlsIssue = TlsIssue {
+    key: "protocols",
+    name: "Outdated SSL/TLS protocol versions supported",
+    description: "The service accepts SSL 2.0, SSL 3.0, TLS 1.0 or TLS 1.1. These protocol versions \
+        have known cryptographic weaknesses and are deprecated.",
+    mitigation: "Disable SSL 2.0, SSL 3.0, TLS 1.0 and TLS 1.1, keep only TLS 1.2 and TLS 1.3.",
+    references: &["https://datatracker.ietf.org/doc/html/rfc8996"],
+};
+
+const CIPHERS: TlsIssue = TlsIssue {
+    key: "ciphers",
+    name: "Weak TLS cipher suites supported",
+    description: "The service negotiates NULL, anonymous, export, DES/3DES, RC4 or other obsolete \
+        cipher suites, which allow an attacker to read or modify the traffic.",
+    mitigation: "Allow only AEAD cipher suites with forward secrecy, e.g. ECDHE with AES-GCM or \
+        ChaCha20-Poly1305.",
+    references: &["https://ssl-config.mozilla.org/"],
+};
+
+const CERTIFICATE: TlsIssue = TlsIssue {
+    key: "certificate",
+    name: "TLS certificate problems",
+    description: "The certificate of the service is not trusted, expired, issued for another name \
+        or uses a weak key or signature. Clients can't tell the service from an impostor.",
+    mitigation: "Install a certificate with a complete chain from a trusted authority that covers \
+        the service name, uses at least a 2048 bit RSA or 256 bit EC key and is renewed in time.",
+    references: &[],
+};
+
+const VULNERABILITIES: &[TlsIssue] = &[
+    TlsIssue {
+        key: "heartbleed",
+        name: "OpenSSL Heartbleed",
+        description: "The heartbeat extension leaks up to 64 KB of server memory per request, \
+            including private keys and session data.",
+        mitigation: "Update OpenSSL, then replace the private key and revoke the certificate.",
+        references: &["https://heartbleed.com/"],
+    },
+    TlsIssue {
+        key: "CCS",
+        name: "OpenSSL CCS injection",
+        description: "A ChangeCipherSpec message sent early makes both sides use weak keying \
+            material, so a man in the middle can decrypt the traffic.",
+        mitigation: "Update OpenSSL.",
+        references: &[],
+    },
+    TlsIssue {
+        key: "ticketbleed",
+        name: "Ticketbleed",
+        description: "Session tickets leak up to 31 bytes of server memory per request.",
+        mitigation: "Update the firmware of the TLS terminator or disable session tickets.",
+        references: &[],
+    },
+    TlsIssue {
+        key: "ROBOT",
+        name: "ROBOT attack",
+        description: "RSA key exchange works as a Bleichenbacher oracle, which lets an attacker \
+            decrypt recorded traffic or sign messages with the server key.",
+        mitigation: "Disable cipher suites with RSA key exchange.",
+        references: &["https://robotattack.org/"],
+    },
+    TlsIssue {
+        key: "secure_renego",
+        name: "Insecure TLS renegotiation",
+        description: "The service doesn't support secure renegotiation, so a man in the middle \
+            can inject data into the session of a client.",
+        mitigation: "Enable the renegotiation_info extension (RFC 5746).",
+        references: &[],
+    },
+    TlsIssue {
+        key: "secure_client_renego",
+        name: "Client-initiated TLS renegotiation",
+        description: "Clients may renegotiate the session, which makes the service easier to \
+            overload with costly handshakes.",
+        mitigation: "Disable client-initiated renegotiation.",
+        references: &[],
+    },
+    TlsIssue {
+        key: "CRIME_TLS",
+        name: "CRIME attack",
+        description: "TLS compression lets an attacker recover secrets such as session cookies \
+            from the size of encrypted requests.",
+        mitigation: "Disable TLS compression.",
+        references: &[],
+    },
+    TlsIssue {
+        key: "BREACH",
+        name: "BREACH attack",
+        description: "HTTP compression of responses that reflect user input lets an attacker \
+            recover secrets from the size of encrypted responses.",
+        mitigation: "Disable HTTP compression for pages with secrets or mask the secrets per \
+            request.",
+        references: &["https://www.breachattack.com/"],
+    },
+    TlsIssue {
+        key: "POODLE_SSL",
+        name: "POODLE attack",
+        description: "CBC padding in SSL 3.0 is not authenticated, so an attacker who can force \
+            a downgrade decrypts the traffic byte by byte.",
+        mitigation: "Disable SSL 3.0.",
+        references: &[],
+    },
+    TlsIssue {
+        key: "fallback_SCSV",
+        name: "TLS_FALLBACK_SCSV not supported",
+        description: "The service doesn't detect forced protocol downgrades.",
+        mitigation: "Enable TLS_FALLBACK_SCSV or disable the outdated protocol versions.",
+        references: &[],
+    },
+    TlsIssue {
+        key: "SWEET32",
+        name: "SWEET32 attack",
+        description: "64 bit block ciphers such as 3DES let an attacker recover data from long \
+            sessions through birthday collisions.",
+        mitigation: "Disable 3DES, IDEA and other 64 bit block ciphers.",
+        references: &["https://sweet32.info/"],
+    },
+    TlsIssue {
+        key: "FREAK",
+        name: "FREAK attack",
+        description: "The service accepts export RSA key exchange, which can be factored to \
+            decrypt the traffic.",
+        mitigation: "Disable export cipher suites.",
+        references: &[],
+    },
+    TlsIssue {
+        key: "DROWN",
+        name: "DROWN attack",
+        description: "The key of the service is usable with SSL 2.0, which lets an attacker \
+            decrypt TLS traffic protected by the same key.",
+        mitigation: "Disable SSL 2.0 on every service that shares the key or certificate.",
+        references: &["https://drownattack.com/"],
+    },
+    TlsIssue {
+        key: "LOGJAM",
+        name: "Logjam attack",
+        description: "Export or weak Diffie-Hellman groups let an attacker downgrade and break \
+            the key exchange.",
+        mitigation: "Disable export cipher suites and use Diffie-Hellman groups of at least \
+            2048 bits or ECDHE.",
+        references: &["https://weakdh.org/"],
+    },
+    TlsIssue {
+        key: "BEAST",
+        name: "BEAST attack",
+        description: "CBC ciphers with SSL 3.0 or TLS 1.0 use predictable IVs, which lets an \
+            attacker recover parts of the traffic.",
+        mitigation: "Disable SSL 3.0 and TLS 1.0 or CBC cipher suites for them.",
+        references: &[],
+    },
+    TlsIssue {
+        key: "LUCKY13",
+        name: "Lucky Thirteen attack",
+        description: "Timing differences of CBC padding checks let an attacker recover plaintext.",
+        mitigation: "Prefer AEAD cipher suites over CBC ones.",
+        references: &[],
+    },
+    TlsIssue {
+        key: "winshock",
+        name: "Winshock",
+        description: "The Schannel library of the service allows remote code execution.",
+        mitigation: "Install the MS14-066 update.",
+        references: &[],
+    },
+    TlsIssue {
+        key: "RC4",
+        name: "RC4 cipher suites supported",
+        description: "RC4 keystream biases let an attacker recover data from encrypted sessions.",
+        mitigation: "Disable RC4 cipher suites.",
+        references: &["https://datatracker.ietf.org/doc/html/rfc7465"],
+    },
+];
+
+#[derive(Clone)]
+pub struct TestsslService {
+    scans_dir: PathBuf,
+    processes: ProcessRegistry,
+}
+
+/// Issue a check belongs to, `None` for informational ones. Certificate
+/// checks carry a `<cert#N>` suffix for every certificate of the service.
+fn tls_issue(id: &str) -> Option<&'static TlsIssue> {
+    let id = id.split_whitespace().next().unwrap_or(id);
+    match id {
+        "SSLv2" | "SSLv3" | "TLS1" | "TLS1_1" => Some(&PROTOCOLS),
+        _ if id.starts_with("cipherlist_") => Some(&CIPHERS),
+        _ if id.starts_with("cert_") || id.starts_with("intermediate_cert") => Some(&CERTIFICATE),
+        "DROWN_hint" => VULNERABILITIES.iter().find(|issue| issue.key == "DROWN"),
+        _ if id.starts_with("LOGJAM") => VULNERABILITIES.iter().find(|issue| issue.key == "LOGJAM"),
+        _ if id.starts_with("BEAST") => VULNERABILITIES.iter().find(|issue| issue.key == "BEAST"),
+        _ => VULNERABILITIES.iter().find(|issue| issue.key == id),
+    }
+}
+
+/// Only these severities mean a problem, the others are OK, INFO, WARN
+/// (scan problems), DEBUG and FATAL.
+fn severity_to_cvss(severity: &str) -> Option<f64> {
+    match severity {
+        "CRITICAL" => Some(9.0),
+        "HIGH" => Some(7.5),
+        "MEDIUM" => Some(5.3),
+        "LOW" => Some(3.1),
+        _ => None,
+    }
+}
+
+/// testssl.sh rates an expired or self-signed certificate as CRITICAL, the
+/// same as Heartbleed. Configuration and certificate problems are scored as
+/// MEDIUM at most, known vulnerabilities keep their severity.
+fn finding_cvss(issue: &TlsIssue, severity: &str) -> Option<f64> {
+    let cvss = severity_to_cvss(severity)?;
+    match issue.key {
+        "protocols" | "ciphers" | "certificate" => Some(cvss.min(5.3)),
+        _ => Some(cvss),
+    }
+}
+
+fn push_ids(ids: &mut Vec<String>, value: &Option<String>) {
+    for id in value.iter().flat_map(|value| value.split_whitespace()) {
+        let id = id.to_uppercase();
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+}
+
+impl TestsslService {
+    pub fn new(scans_dir: impl AsRef<Path>, processes: ProcessRegistry) -> Self {
+        Self {
+            scans_dir: scans_dir.as_ref().to_path_buf(),
+            processes,
+        }
+    }
+
+    fn check_targets(targets: &[String]) -> Result<(), Error> {
+        let has_port = |target: &str| match target.rsplit_once(':') {
+            Some((host, port)) => {
+                !host.is_empty()
+                    && (!host.contains(':') || host.ends_with(']'))
+                    && port.parse::<u16>().is_ok()
+            },
+            None => false,
+        };
+        match targets.iter().find(|target| target.contains("://") || !has_port(target)) {
+            Some(target) => Err(Error::InvalidTarget(format!("{}: expected host:port", target))),
+            None => Ok(()),
+        }
+    }
+
+    /// Translates validated scan options into testssl.sh arguments. Targets
+    /// are read from a file, one per line, so several are tested in one run.
+    fn build_args(options: &ScanOptions, output_file: &str, targets_file: &str) -> Vec<String> {
+        let mut args = vec![
+            "--quiet".to_string(),
+            "--color".to_string(),
+            "0".to_string(),
+            "--warnings".to_string(),
+            "off".to_string(),
+            "--overwrite".to_string(),
+            "--jsonfile".to_string(),
+            output_file.to_string(),
+        ];
+        for check in options.list("checks") {
+            let flag = match check {
+                "protocols" => "--protocols",
+                "ciphers" => "--std",
+                "certificate" => "--server-defaults",
+                _ => "--vulnerable",
+            };
+            args.push(flag.to_string());
+        }
+        if options.flag("parallel") {
+            args.push("--parallel".to_string());
+        }
+        if let Some(timeout) = options.integer("connect_timeout") {
+            args.extend(["--connect-timeout".to_string(), timeout.to_string()]);
+        }
+        args.extend(["--file".to_string(), targets_file.to_string()]);
+        args
+    }
+
+    /// testssl.sh doesn't report progress.
+    fn parse_progress(_line: &str) -> Option<ScanProgress> {
+        None
+    }
+
+    /// Parses a flat `--jsonfile` report. An empty file means nothing was
+    /// tested, e.g. because no target answered.
+    pub fn parse_output(content: &str) -> Result<Vec<TestsslFinding>, Error> {
+        if content.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+        serde_json::from_str(content).map_err(|e| Error::ParseError(e.to_string()))
+    }
+
+    /// Groups the problems of every endpoint into one issue per protocol,
+    /// cipher, certificate or vulnerability check. The checks that fired
+    /// are kept as evidence and the worst of their severities sets the CVSS,
+    /// capped for configuration problems.
+    pub fn parse_to_issues(findings: Vec<TestsslFinding>) -> Vec<IssueForm> {
+        let mut groups: BTreeMap<(String, &'static str), (&'static TlsIssue, Vec<TestsslFinding>)> =
+            BTreeMap::new();
+        for finding in findings {
+            let Some(issue) = tls_issue(&finding.id) else {
+                continue;
+            };
+            if severity_to_cvss(&finding.severity).is_none() {
+                continue;
+            }
+            let endpoint = format!("{}:{}", finding.ip, finding.port);
+            groups.entry((endpoint, issue.key)).or_insert((issue, Vec::new())).1.push(finding);
+        }
+
+        let mut issues = Vec::new();
+        for (issue, findings) in groups.into_values() {
+            let first = &findings[0];
+            let (hostname, ip_address) = match first.ip.split_once('/') {
+                Some((hostname, ip)) if hostname != ip => (Some(hostname.to_string()), ip.to_string()),
+                Some((_, ip)) => (None, ip.to_string()),
+                None => (None, first.ip.clone()),
+            };
+            let matched_at = format!(
+                "{}:{}",
+                hostname.as_deref().unwrap_or(&ip_address),
+                first.port
+            );
+            debug!("testssl.sh found {} at {}", issue.name, matched_at);
+
+            let cvss = findings
+                .iter()
+                .filter_map(|finding| finding_cvss(issue, &finding.severity))
+                .fold(0.0, f64::max);
+            let mut classification = ClassificationForm {
+                reference_urls: issue.references.iter().map(|url| url.to_string()).collect(),
+                tags: vec!["tls".to_string()],
+                ..Default::default()
+            };
+            for finding in &findings {
+                push_ids(&mut classification.cve_ids, &finding.cve);
+                push_ids(&mut classification.cwe_ids, &finding.cwe);
+            }
+            let content = findings
+                .iter()
+                .map(|finding| format!("{} ({}): {}", finding.id, finding.severity, finding.finding))
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            issues.push(IssueForm {
+                name: issue.name.to_string(),
+                description: Some(issue.description.to_string()),
+                mitigation: Some(issue.mitigation.to_string()),
+                cvss: Some(cvss),
+                hosts: vec![HostForm {
+                    hostname,
+                    ip_address: ip_address.clone(),
+                    os: None,
+                    os_accuracy: None,
+                    services: Vec::new(),
+                    sources: Vec::new(),
+                    hostnames: Vec::new(),
+                }],
+                template_id: Some(format!("testssl:{}", issue.key)),
+                evidence: vec![EvidenceForm {
+                    description: format!("testssl.sh results for {}", matched_at),
+                    content,
+                    host: ip_address,
+                }],
+                matched_at: Some(matched_at),
+                fingerprint: Some(format!("testssl:{}", issue.key)),
+                classification,
+            });
+        }
+        issues
+    }
+}
+
+#[async_trait]
+impl VulnerabilityScanner for TestsslService {
+    fn name(&self) -> &'static str {
+        "testssl"
+    }
+
+    fn capabilities(&self) -> Vec<ScannerCapability> {
+        vec![ScannerCapability::VulnerabilityScan]
+    }
+
+    fn options(&self) -> Vec<ScannerOption> {
+        vec![
+            ScannerOption {
+                name: "checks",
+                description: "Run only these groups of checks",
+                kind: ScannerOptionKind::Choice {
+                    values: vec!["protocols", "ciphers", "certificate", "vulnerabilities"],
+                },
+            },
+            ScannerOption {
+                name: "parallel",
+                description: "Test the targets in parallel",
+                kind: ScannerOptionKind::Boolean,
+            },
+            ScannerOption {
+                name: "connect_timeout",
+                description: "Seconds to wait for a TCP connection",
+                kind: ScannerOptionKind::Integer { min: 1, max: 600 },
+            },
+        ]
+    }
+
+    fn is_installed(&self) -> bool {
+        is_binary_installed("testssl.sh")
+    }
+
+    async fn get_scan_result(&self, scan_id: Uuid) -> Result<AnyScanResult, Error> {
+        self.load_result(&self.scans_dir.join(scan_id.to_string()).join("scan.json"))
+    }
+
+    fn load_result(&self, output_file: &Path) -> Result<AnyScanResult, Error> {
+        let content = fs::read_to_string(output_file)
+            .map_err(|e| Error::IoError(format!("Failed to read result file: {}", e)))?;
+
+        Ok(AnyScanResult::Issues(IssuesScanResult {
+            output_file: output_file.display().to_string(),
+            findings: Self::parse_to_issues(Self::parse_output(&content)?),
+        }))
+    }
+
+    async fn start_scan(
+        &self,
+        scan_id: Uuid,
+        target: &str,
+        options: &ScanOptions,
+    ) -> Result<AnyScanResult, Error> {
+        let targets = parse_targets(target)?;
+        Self::check_targets(&targets)?;
+        let scan_path = self.scans_dir.join(scan_id.to_string());
+        fs::create_dir_all(&scan_path).map_err(|e| {
+            error!("Failed to create scan directory: {}", e);
+            Error::IoError(format!("Failed to create scan directory: {}", e))
+        })?;
+
+        let targets_file = scan_path.join("targets.txt");
+        fs::write(&targets_file, targets.join("\n") + "\n")
+            .map_err(|e| Error::IoError(format!("Failed to write targets file: {}", e)))?;
+        let output_file = scan_path.join("scan.json").display().to_string();
+        let args = Self::build_args(options, &output_file, &targets_file.display().to_string());
+        debug!("Run command testssl.sh {}", args.join(" "));
+
+        let mut command = tokio::process::Command::new("testssl.sh");
+        command.args(args);
+        let output = self
+            .processes
+            .run(scan_id, command, &output_file, Self::parse_progress)
+            .await?;
+        if output.status.code().is_some_and(|code| code < FIRST_ERROR_STATUS) {
+            debug!("testssl.sh exited with {}", output.status);
+        } else {
+            output.check("testssl.sh")?;
+        }
+
+        let content = match fs::read_to_string(&output_file) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => {
+                error!("Failed to read result file: {}", e);
+                return Err(Error::IoError(format!("Failed to read result file: {}", e)));
+            },
+        };
+        let findings = Self::parse_output(&content).map_err(|e| {
+            error!("Can't parse testssl.sh output file: {}", e);
+            e
+        })?;
+
+        Ok(AnyScanResult::Issues(IssuesScanResult {
+            output_file,
+            findings: Self::parse_to_issues(findings),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> Vec<TestsslFinding> {
+        let content = fs::read_to_string(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/testssl/scan.json"
+        ))
+        .unwrap();
+        TestsslService::parse_output(&content).unwrap()
+    }
+
+    fn find<'a>(issues: &'a [IssueForm], fingerprint: &str, matched_at: &str) -> &'a IssueForm {
+        issues
+            .iter()
+            .find(|issue| {
+                issue.fingerprint.as_deref() == Some(fingerprint)
+                    && issue.matched_at.as_deref() == Some(matched_at)
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn test_parse_output() {
+        let findings = fixture();
+        assert_eq!(findings.len(), 39);
+        assert_eq!(findings[3].id, "SSLv3");
+        assert_eq!(findings[3].ip, "example.com/93.184.216.34");
+        assert_eq!(findings[3].cve.as_deref(), Some("CVE-2014-3566"));
+        assert!(TestsslService::parse_output("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_to_issues() {
+        let issues = TestsslService::parse_to_issues(fixture());
+        let mut found: Vec<(&str, &str)> = issues
+            .iter()
+            .map(|issue| (issue.fingerprint.as_deref().unwrap(), issue.matched_at.as_deref().unwrap()))
+            .collect();
+        found.sort();
+        assert_eq!(found, vec![
+            ("testssl:BEAST", "example.com:443"),
+            ("testssl:LOGJAM", "10.0.0.5:8443"),
+            ("testssl:POODLE_SSL", "example.com:443"),
+            ("testssl:SWEET32", "example.com:443"),
+            ("testssl:certificate", "10.0.0.5:8443"),
+            ("testssl:ciphers", "10.0.0.5:8443"),
+            ("testssl:ciphers", "example.com:443"),
+            ("testssl:heartbleed", "10.0.0.5:8443"),
+            ("testssl:protocols", "10.0.0.5:8443"),
+            ("testssl:protocols", "example.com:443"),
+            ("testssl:secure_client_renego", "10.0.0.5:8443"),
+        ]);
+
+        let protocols = find(&issues, "testssl:protocols", "example.com:443");
+        assert_eq!(protocols.cvss, Some(5.3));
+        assert_eq!(protocols.hosts[0].hostname.as_deref(), Some("example.com"));
+        assert_eq!(protocols.hosts[0].ip_address, "93.184.216.34");
+        assert_eq!(protocols.classification.cve_ids, vec!["CVE-2014-3566"]);
+        assert_eq!(
+            protocols.evidence[0].content,
+            "SSLv3 (HIGH): offered\nTLS1 (LOW): offered (deprecated)\nTLS1_1 (LOW): offered (deprecated)"
+        );
+
+        let certificate = find(&issues, "testssl:certificate", "10.0.0.5:8443");
+        assert_eq!(certificate.cvss, Some(5.3));
+        assert_eq!(certificate.hosts[0].hostname, None);
+        assert_eq!(certificate.evidence[0].content.lines().count(), 3);
+
+        assert_eq!(find(&issues, "testssl:heartbleed", "10.0.0.5:8443").cvss, Some(9.0));
+
+        let beast = find(&issues, "testssl:BEAST", "example.com:443");
+        assert_eq!(beast.cvss, Some(5.3));
+        assert_eq!(beast.classification.cwe_ids, vec!["CWE-20"]);
+
+        let sweet32 = find(&issues, "testssl:SWEET32", "example.com:443");
+        assert_eq!(sweet32.classification.cve_ids, vec!["CVE-2016-2183", "CVE-2016-6329"]);
+    }
+
+    #[test]
+    fn test_check_targets() {
+        let valid = parse_targets("example.com:443 10.0.0.5:8443 [2001:db8::1]:443").unwrap();
+        assert!(TestsslService::check_targets(&valid).is_ok());
+        for target in ["example.com", "https://example.com:443", "2001:db8::1", "host:https"] {
+            assert!(TestsslService::check_targets(&[target.to_string()]).is_err(), "{}", target);
+        }
+    }
+
+    #[test]
+    fn test_build_args() {
+        let options = ScanOptions::validate(
+            &TestsslService::new("/tmp", ProcessRegistry::default()).options(),
+            serde_json::json!({ "checks": ["protocols", "vulnerabilities"], "connect_timeout": 10 })
+                .as_object()
+                .unwrap()
+                .clone(),
+        )
+        .unwrap();
+
+        let args = TestsslService::build_args(&options, "/tmp/scan.json", "/tmp/targets.txt");
+        assert_eq!(args, vec![
+            "--quiet", "--color", "0", "--warnings", "off", "--overwrite", "--jsonfile",
+            "/tmp/scan.json", "--protocols", "--vulnerable", "--connect-timeout", "10", "--file",
+            "/tmp/targets.txt",
+        ]);
+    }
+}
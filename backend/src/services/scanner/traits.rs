@@ -1,13 +1,27 @@
+use std::path::Path;
+
 use async_trait::async_trait;
 use uuid::Uuid;
-use crate::utils::errors::AppError;
 
-use crate::services::scanner::types::{AnyScanResult, Error};
+use crate::services::scanner::types::{
+    AnyScanResult, Error, ScanOptions, ScannerCapability, ScannerOption,
+};
 
 #[async_trait]
 pub trait VulnerabilityScanner: Send + Sync {
-    type ScanRequest;
-    type ScanResult;
-    async fn get_scan_result(&self, task_id: &str) -> Result<Self::ScanResult, Error>;
-    async fn start_scan(&mut self, scan_id: Uuid, target: &str) -> Result<AnyScanResult, AppError>;
+    /// Name the scanner is registered under, e.g. `nmap`.
+    fn name(&self) -> &'static str;
+    fn capabilities(&self) -> Vec<ScannerCapability>;
+    fn options(&self) -> Vec<ScannerOption>;
+    /// Whether the tool the scanner depends on is available on this machine.
+    fn is_installed(&self) -> bool;
+    async fn get_scan_result(&self, scan_id: Uuid) -> Result<AnyScanResult, Error>;
+    /// Parses a result file written by an earlier run of the scanner.
+    fn load_result(&self, output_file: &Path) -> Result<AnyScanResult, Error>;
+    async fn start_scan(
+        &self,
+        scan_id: Uuid,
+        target: &str,
+        options: &ScanOptions,
+    ) -> Result<AnyScanResult, Error>;
 }
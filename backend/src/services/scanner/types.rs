@@ -1,14 +1,18 @@
+use std::collections::BTreeSet;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+
 use diesel::PgConnection;
 use log::error;
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use thiserror::Error;
 use uuid::Uuid;
-use crate::dtos::handlers::HostForm;
-use crate::models::host::Host;
+use crate::dtos::handlers::{HostForm, IssueForm, WebEndpointForm, WebServiceForm};
+use crate::models::host::{Host, SavedHosts};
 use crate::models::issue::Issue;
-use crate::services::scanner::nmap::service::NmapScanResult;
-use crate::services::scanner::nuclei::service::NucleiScanResult;
-use crate::utils::errors::AppError;
+use crate::models::web_endpoint::WebEndpoint;
+use crate::models::web_service::WebService;
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub enum ScanStatus {
@@ -16,6 +20,19 @@ pub enum ScanStatus {
     Running,
     Completed,
     Failed,
+    Cancelled,
+}
+
+impl From<ScanStatus> for String {
+    fn from(status: ScanStatus) -> String {
+        match status {
+            ScanStatus::Queued => "queued".to_string(),
+            ScanStatus::Running => "running".to_string(),
+            ScanStatus::Completed => "completed".to_string(),
+            ScanStatus::Failed => "failed".to_string(),
+            ScanStatus::Cancelled => "cancelled".to_string(),
+        }
+    }
 }
 
 #[derive(Debug, Error)]
@@ -26,6 +43,14 @@ pub enum Error {
     IoError(String),
     #[error("Output parsing failed: {0}")]
     ParseError(String),
+    #[error("Invalid scan option: {0}")]
+    InvalidOption(String),
+    #[error("Invalid scan target: {0}")]
+    InvalidTarget(String),
+    #[error("Scan was cancelled")]
+    Cancelled(String),
+    #[error("Failed to save scan data: {0}")]
+    SaveFailed(String),
 }
 
 impl From<quick_xml::Error> for Error {
@@ -48,39 +73,447 @@ pub struct Vulnerability {
 }
 
 
+/// What a scanner is able to find out about its targets.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ScannerCapability {
+    HostDiscovery,
+    PortScan,
+    ServiceDetection,
+    OsDetection,
+    VulnerabilityScan,
+    SubdomainEnumeration,
+    ContentDiscovery,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ScannerOptionKind {
+    Boolean,
+    Integer { min: i64, max: i64 },
+    /// Port specification such as `22,80,8000-8100`.
+    Ports,
+    /// HTTP status codes such as `200-299,301,403`, or `all`.
+    StatusCodes,
+    /// One or more of the listed values.
+    Choice { values: Vec<&'static str> },
+    /// List of free-form values, e.g. script or template names.
+    List,
+    /// Single free-form value, e.g. a wordlist name.
+    Text,
+}
+
+/// Describes a single option a scanner accepts.
+#[derive(Debug, Clone, Serialize)]
+pub struct ScannerOption {
+    pub name: &'static str,
+    pub description: &'static str,
+    #[serde(flatten)]
+    pub kind: ScannerOptionKind,
+}
+
+impl ScannerOptionKind {
+    fn validate(&self, value: &Value) -> Result<(), String> {
+        match self {
+            ScannerOptionKind::Boolean if value.is_boolean() => Ok(()),
+            ScannerOptionKind::Boolean => Err("expected a boolean".to_string()),
+            ScannerOptionKind::Integer { min, max } => match value.as_i64() {
+                Some(number) if (*min..=*max).contains(&number) => Ok(()),
+                _ => Err(format!("expected an integer between {} and {}", min, max)),
+            },
+            ScannerOptionKind::Ports => match value.as_str() {
+                Some(ports) if is_valid_port_spec(ports) => Ok(()),
+                _ => Err("expected a port list such as 22,80,8000-8100".to_string()),
+            },
+            ScannerOptionKind::StatusCodes => match value.as_str() {
+                Some(codes) if is_valid_status_codes(codes) => Ok(()),
+                _ => Err("expected status codes such as 200-299,403 or all".to_string()),
+            },
+            ScannerOptionKind::Choice { values } => {
+                let items = string_items(value)?;
+                match items.iter().find(|item| !values.contains(item)) {
+                    Some(item) => Err(format!("unsupported value {}", item)),
+                    None => Ok(()),
+                }
+            },
+            ScannerOptionKind::List => {
+                let items = string_items(value)?;
+                match items.iter().find(|item| !is_safe_argument(item)) {
+                    Some(item) => Err(format!("invalid value {}", item)),
+                    None => Ok(()),
+                }
+            },
+            ScannerOptionKind::Text => match value.as_str() {
+                Some(text) if is_safe_argument(text) => Ok(()),
+                _ => Err("expected a name without spaces or leading dashes".to_string()),
+            },
+        }
+    }
+}
+
+fn string_items(value: &Value) -> Result<Vec<&str>, String> {
+    let items: Option<Vec<&str>> = value
+        .as_array()
+        .map(|items| items.iter().map(Value::as_str).collect())
+        .unwrap_or(None);
+    match items {
+        Some(items) if !items.is_empty() => Ok(items),
+        _ => Err("expected a non-empty list of strings".to_string()),
+    }
+}
+
+/// Accepts nmap style port lists, e.g. `22,80,8000-8100` or `T:80,U:53`.
+fn is_valid_port_spec(spec: &str) -> bool {
+    let parse_port = |port: &str| {
+        if port.is_empty() || !port.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        port.parse::<u16>().ok()
+    };
+    spec.split(',').all(|item| {
+        let item = item
+            .strip_prefix("T:")
+            .or_else(|| item.strip_prefix("U:"))
+            .unwrap_or(item);
+        match item.split_once('-') {
+            Some((start, end)) => match (parse_port(start), parse_port(end)) {
+                (Some(start), Some(end)) => start <= end,
+                _ => false,
+            },
+            None => parse_port(item).is_some(),
+        }
+    })
+}
+
+/// Accepts HTTP status code lists, e.g. `200-299,301,403`, or `all`.
+fn is_valid_status_codes(spec: &str) -> bool {
+    let parse_code = |code: &str| {
+        if code.len() != 3 || !code.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        code.parse::<u16>().ok().filter(|code| (100..=599).contains(code))
+    };
+    spec == "all"
+        || spec.split(',').all(|item| match item.split_once('-') {
+            Some((start, end)) => match (parse_code(start), parse_code(end)) {
+                (Some(start), Some(end)) => start <= end,
+                _ => false,
+            },
+            None => parse_code(item).is_some(),
+        })
+}
+
+/// Expands a validated port list into its TCP ports, for scanners that
+/// connect themselves.
+pub fn tcp_ports(spec: &str) -> Vec<u16> {
+    let mut ports = BTreeSet::new();
+    for item in spec.split(',').filter(|item| !item.starts_with("U:")) {
+        let item = item.strip_prefix("T:").unwrap_or(item);
+        let (start, end) = item.split_once('-').unwrap_or((item, item));
+        if let (Ok(start), Ok(end)) = (start.parse::<u16>(), end.parse::<u16>()) {
+            ports.extend(start..=end);
+        }
+    }
+    ports.into_iter().collect()
+}
+
+/// Values end up as separate process arguments, so anything that could be read
+/// as a flag or escape the configured directories is rejected. Absolute paths
+/// are rejected too, since `Path::join` would replace the base directory.
+pub fn is_safe_argument(value: &str) -> bool {
+    !value.is_empty()
+        && !value.starts_with('-')
+        && !value.starts_with('/')
+        && !value.split('/').any(|part| part == "..")
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "._-/:+".contains(c))
+}
+
+/// Resolves a file named in the options inside `base`. Symlinks are followed,
+/// so the file must exist and stay under the base directory once resolved.
+pub fn resolve_under(base: &Path, name: &str) -> Option<PathBuf> {
+    let base = base.canonicalize().ok()?;
+    let path = base.join(name).canonicalize().ok()?;
+    path.starts_with(&base).then_some(path)
+}
+
+/// Scanner options that passed validation against the scanner's schema.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ScanOptions(Map<String, Value>);
+
+impl ScanOptions {
+    pub fn validate(schema: &[ScannerOption], options: Map<String, Value>) -> Result<Self, Error> {
+        for (name, value) in &options {
+            let option = schema
+                .iter()
+                .find(|option| option.name == name)
+                .ok_or_else(|| Error::InvalidOption(format!("unknown option {}", name)))?;
+            option
+                .kind
+                .validate(value)
+                .map_err(|reason| Error::InvalidOption(format!("{}: {}", name, reason)))?;
+        }
+        Ok(Self(options))
+    }
+
+    pub fn flag(&self, name: &str) -> bool {
+        self.0.get(name).and_then(Value::as_bool).unwrap_or(false)
+    }
+
+    pub fn integer(&self, name: &str) -> Option<i64> {
+        self.0.get(name).and_then(Value::as_i64)
+    }
+
+    pub fn string(&self, name: &str) -> Option<&str> {
+        self.0.get(name).and_then(Value::as_str)
+    }
+
+    pub fn list(&self, name: &str) -> Vec<&str> {
+        self.0
+            .get(name)
+            .and_then(Value::as_array)
+            .map(|items| items.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn into_value(self) -> Value {
+        Value::Object(self.0)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HostsScanResult {
+    pub output_file: String,
+    pub hosts: Vec<HostForm>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct IssuesScanResult {
+    pub output_file: String,
+    pub findings: Vec<IssueForm>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EndpointsScanResult {
+    pub output_file: String,
+    pub endpoints: Vec<WebEndpointForm>,
+}
+
+/// Web servers found by a probe and the issues in their responses.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WebServicesScanResult {
+    pub output_file: String,
+    pub services: Vec<WebServiceForm>,
+    pub findings: Vec<IssueForm>,
+}
+
+/// Scanner output grouped by the kind of data it produces rather than by tool,
+/// so new scanners can reuse the existing persistence logic.
 #[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
 pub enum AnyScanResult {
-    Nmap(NmapScanResult),
-    Nuclei(NucleiScanResult),
+    Hosts(HostsScanResult),
+    Issues(IssuesScanResult),
+    Endpoints(EndpointsScanResult),
+    WebServices(WebServicesScanResult),
 }
 
 impl AnyScanResult {
+    pub fn output_file(&self) -> &str {
+        match &self {
+            AnyScanResult::Hosts(res) => &res.output_file,
+            AnyScanResult::Issues(res) => &res.output_file,
+            AnyScanResult::Endpoints(res) => &res.output_file,
+            AnyScanResult::WebServices(res) => &res.output_file,
+        }
+    }
 
-    pub fn save_data(&self, project_id: Uuid, conn: &mut PgConnection) -> Result<(), AppError> {
+    /// Persists the result in the project and returns how many hosts were
+    /// created and how many known ones were updated.
+    pub fn save_data(&self, project_id: Uuid, conn: &mut PgConnection) -> Result<SavedHosts, Error> {
         match &self {
-            AnyScanResult::Nmap(res) => {
-                let mut new_hosts_vec: Vec<HostForm> = Vec::new();
-                for ip_address in res.hosts.clone() {
-                    new_hosts_vec.push(HostForm {
+            AnyScanResult::Hosts(res) => {
+                Host::create_hosts(conn, res.hosts.clone(), project_id)
+                    .map_err(|e| {
+                        error!("Error creating hosts: {:?}", e);
+                        Error::SaveFailed(format!("creating hosts: {}", e))
+                    })
+            }
+            AnyScanResult::Issues(res) => {
+                let affected_hosts: Vec<HostForm> = res
+                    .findings
+                    .iter()
+                    .flat_map(|finding| finding.hosts.clone())
+                    .collect();
+                let saved = Host::create_missing_hosts(conn, affected_hosts, project_id)
+                    .map_err(|e| {
+                        error!("Error creating hosts: {:?}", e);
+                        Error::SaveFailed(format!("creating hosts: {}", e))
+                    })?;
+                Issue::create_issues(conn, res.findings.clone(), project_id)
+                    .map_err(|e| {
+                        error!("Error creating issues: {:?}", e);
+                        Error::SaveFailed(format!("creating issues: {}", e))
+                    })?;
+                Ok(saved)
+            }
+            AnyScanResult::Endpoints(res) => {
+                // Endpoints of servers addressed by IP get a host of their own.
+                let addressed_hosts: Vec<HostForm> = res
+                    .endpoints
+                    .iter()
+                    .filter(|endpoint| endpoint.host.parse::<IpAddr>().is_ok())
+                    .map(|endpoint| HostForm {
                         hostname: None,
-                        ip_address,
+                        ip_address: endpoint.host.clone(),
+                        os: None,
+                        os_accuracy: None,
+                        services: Vec::new(),
+                        sources: Vec::new(),
+                        hostnames: Vec::new(),
                     })
-                };
-                Host::create_hosts(conn, new_hosts_vec, project_id)
+                    .collect();
+                let saved = Host::create_missing_hosts(conn, addressed_hosts, project_id)
                     .map_err(|e| {
                         error!("Error creating hosts: {:?}", e);
-                        AppError::DatabaseError
+                        Error::SaveFailed(format!("creating hosts: {}", e))
                     })?;
-                Ok(())
+                WebEndpoint::create_endpoints(conn, &res.endpoints, project_id)
+                    .map_err(|e| {
+                        error!("Error creating web endpoints: {:?}", e);
+                        Error::SaveFailed(format!("creating web endpoints: {}", e))
+                    })?;
+                Ok(saved)
             }
-            AnyScanResult::Nuclei(res) => {
+            AnyScanResult::WebServices(res) => {
+                let probed_hosts: Vec<HostForm> = res
+                    .services
+                    .iter()
+                    .map(|service| HostForm {
+                        hostname: (service.host != service.ip_address).then(|| service.host.clone()),
+                        ip_address: service.ip_address.clone(),
+                        os: None,
+                        os_accuracy: None,
+                        services: Vec::new(),
+                        sources: Vec::new(),
+                        hostnames: Vec::new(),
+                    })
+                    .collect();
+                let saved = Host::create_missing_hosts(conn, probed_hosts, project_id)
+                    .map_err(|e| {
+                        error!("Error creating hosts: {:?}", e);
+                        Error::SaveFailed(format!("creating hosts: {}", e))
+                    })?;
+                WebService::create_services(conn, &res.services, project_id)
+                    .map_err(|e| {
+                        error!("Error creating web services: {:?}", e);
+                        Error::SaveFailed(format!("creating web services: {}", e))
+                    })?;
                 Issue::create_issues(conn, res.findings.clone(), project_id)
                     .map_err(|e| {
                         error!("Error creating issues: {:?}", e);
-                        AppError::DatabaseError
+                        Error::SaveFailed(format!("creating issues: {}", e))
                     })?;
-                Ok(())
+                Ok(saved)
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use serde_json::json;
+
+    use super::*;
+
+    fn schema() -> Vec<ScannerOption> {
+        vec![
+            ScannerOption { name: "ports", description: "", kind: ScannerOptionKind::Ports },
+            ScannerOption {
+                name: "timing",
+                description: "",
+                kind: ScannerOptionKind::Integer { min: 0, max: 5 },
+            },
+            ScannerOption {
+                name: "severity",
+                description: "",
+                kind: ScannerOptionKind::Choice { values: vec!["high", "critical"] },
+            },
+            ScannerOption { name: "codes", description: "", kind: ScannerOptionKind::StatusCodes },
+            ScannerOption { name: "scripts", description: "", kind: ScannerOptionKind::List },
+            ScannerOption { name: "wordlist", description: "", kind: ScannerOptionKind::Text },
+        ]
+    }
+
+    fn validate(options: Value) -> Result<ScanOptions, Error> {
+        ScanOptions::validate(&schema(), options.as_object().unwrap().clone())
+    }
+
+    #[test]
+    fn test_tcp_ports() {
+        assert_eq!(tcp_ports("22,80,8000-8002"), vec![22, 80, 8000, 8001, 8002]);
+        assert_eq!(tcp_ports("T:443,U:53,80,443"), vec![80, 443]);
+        assert!(tcp_ports("U:53,U:161").is_empty());
+    }
+
+    #[test]
+    fn test_resolve_under() {
+        let base = std::env::temp_dir().join(format!("resolve-under-{}", Uuid::new_v4()));
+        fs::create_dir_all(base.join("http")).unwrap();
+        fs::write(base.join("http/cves.yaml"), "").unwrap();
+        let outside = base.with_extension("outside");
+        fs::write(&outside, "").unwrap();
+        std::os::unix::fs::symlink(&outside, base.join("link.yaml")).unwrap();
+
+        let resolved = resolve_under(&base, "http/cves.yaml").unwrap();
+        assert_eq!(resolved, base.canonicalize().unwrap().join("http/cves.yaml"));
+        assert!(resolve_under(&base, "http/missing.yaml").is_none());
+        assert!(resolve_under(&base, outside.to_str().unwrap()).is_none());
+        assert!(resolve_under(&base, "link.yaml").is_none());
+
+        fs::remove_dir_all(&base).unwrap();
+        fs::remove_file(&outside).unwrap();
+    }
+
+    #[test]
+    fn test_validate_accepts_schema_options() {
+        let options = validate(json!({
+            "ports": "T:22,80,8000-8100,U:53",
+            "timing": 4,
+            "severity": ["critical"],
+            "codes": "200-299,301,403",
+            "scripts": ["http-title", "ssl-cert"],
+            "wordlist": "common.txt",
+        }))
+        .unwrap();
+
+        assert_eq!(options.string("ports"), Some("T:22,80,8000-8100,U:53"));
+        assert_eq!(options.integer("timing"), Some(4));
+        assert_eq!(options.list("scripts"), vec!["http-title", "ssl-cert"]);
+        assert_eq!(options.string("wordlist"), Some("common.txt"));
+    }
+
+    #[test]
+    fn test_validate_rejects_invalid_options() {
+        assert!(validate(json!({ "output": "/tmp/x" })).is_err());
+        assert!(validate(json!({ "timing": 6 })).is_err());
+        assert!(validate(json!({ "ports": "80-22" })).is_err());
+        assert!(validate(json!({ "ports": "22 -iL /etc/passwd" })).is_err());
+        assert!(validate(json!({ "severity": ["info"] })).is_err());
+        assert!(validate(json!({ "codes": "all" })).is_ok());
+        assert!(validate(json!({ "codes": "T:200,U:404" })).is_err());
+        assert!(validate(json!({ "codes": "200,8080" })).is_err());
+        assert!(validate(json!({ "codes": "099-200" })).is_err());
+        assert!(validate(json!({ "codes": "404-200" })).is_err());
+        assert!(validate(json!({ "scripts": ["--script-args=x"] })).is_err());
+        assert!(validate(json!({ "scripts": ["../../etc/passwd"] })).is_err());
+        assert!(validate(json!({ "scripts": ["/app/wordlists/x.nse"] })).is_err());
+        assert!(validate(json!({ "wordlist": "/etc/passwd" })).is_err());
+        assert!(validate(json!({ "scripts": "default" })).is_err());
+        assert!(validate(json!({ "wordlist": "../secrets.txt" })).is_err());
+        assert!(validate(json!({ "wordlist": ["common.txt"] })).is_err());
+    }
+}
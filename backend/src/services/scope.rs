@@ -0,0 +1,548 @@
+use std::net::IpAddr;
+
+use diesel::PgConnection;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::middleware::auth::{Role, UserSession};
+use crate::models::project::Project;
+use crate::models::scope_audit::{NewScopeAuditRecord, ScopeAuditRecord};
+use crate::models::scope_entry::ScopeEntry;
+use crate::utils::errors::AppError;
+
+/// Inclusive range of addresses of a single IP family.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpRange {
+    v6: bool,
+    start: u128,
+    end: u128,
+}
+
+fn ip_to_u128(ip: IpAddr) -> (bool, u128) {
+    match ip {
+        IpAddr::V4(ip) => (false, u32::from(ip) as u128),
+        IpAddr::V6(ip) => (true, u128::from(ip)),
+    }
+}
+
+impl IpRange {
+    pub fn single(ip: IpAddr) -> Self {
+        let (v6, value) = ip_to_u128(ip);
+        Self { v6, start: value, end: value }
+    }
+
+    pub fn cidr(ip: IpAddr, prefix: u32) -> Option<Self> {
+        let (v6, value) = ip_to_u128(ip);
+        let bits = if v6 { 128 } else { 32 };
+        if prefix > bits {
+            return None;
+        }
+        let host_mask = if prefix == 0 {
+            if v6 { u128::MAX } else { u32::MAX as u128 }
+        } else {
+            (1u128 << (bits - prefix)) - 1
+        };
+        Some(Self {
+            v6,
+            start: value & !host_mask,
+            end: value | host_mask,
+        })
+    }
+
+    pub fn between(start: IpAddr, end: IpAddr) -> Option<Self> {
+        let (start_v6, start) = ip_to_u128(start);
+        let (end_v6, end) = ip_to_u128(end);
+        (start_v6 == end_v6 && start <= end).then_some(Self { v6: start_v6, start, end })
+    }
+
+    /// Parses `10.0.0.1`, `10.0.0.0/24`, `10.0.0.1-10.0.0.50` or the nmap style
+    /// short form `10.0.0.1-50`.
+    pub fn parse(value: &str) -> Option<Self> {
+        if let Some((ip, prefix)) = value.split_once('/') {
+            return Self::cidr(ip.parse().ok()?, prefix.parse().ok()?);
+        }
+        if let Some((start, end)) = value.split_once('-') {
+            let start: IpAddr = start.parse().ok()?;
+            let end = match (start, end.parse::<u8>()) {
+                (IpAddr::V4(start), Ok(last_octet)) => {
+                    let [a, b, c, _] = start.octets();
+                    IpAddr::from([a, b, c, last_octet])
+                },
+                _ => end.parse().ok()?,
+            };
+            return Self::between(start, end);
+        }
+        value.parse().ok().map(Self::single)
+    }
+
+    /// Number of addresses in the range.
+    pub fn size(&self) -> u128 {
+        (self.end - self.start).saturating_add(1)
+    }
+
+    pub fn addresses(&self) -> impl Iterator<Item = IpAddr> {
+        let v6 = self.v6;
+        (self.start..=self.end).map(move |value| {
+            if v6 {
+                IpAddr::from(value.to_be_bytes())
+            } else {
+                IpAddr::from((value as u32).to_be_bytes())
+            }
+        })
+    }
+
+    fn overlaps(&self, other: &IpRange) -> bool {
+        self.v6 == other.v6 && self.start <= other.end && other.start <= self.end
+    }
+}
+
+/// Type of a project scope entry as stored in the `scope_entries` table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScopeKind {
+    Cidr,
+    IpRange,
+    Domain,
+    UrlPrefix,
+    /// Any of the other kinds that must never be scanned.
+    Exclusion,
+}
+
+impl ScopeKind {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "cidr" => Some(ScopeKind::Cidr),
+            "ip_range" => Some(ScopeKind::IpRange),
+            "domain" => Some(ScopeKind::Domain),
+            "url_prefix" => Some(ScopeKind::UrlPrefix),
+            "exclusion" => Some(ScopeKind::Exclusion),
+            _ => None,
+        }
+    }
+
+    /// Guesses the kind of a token of a free-text scope.
+    pub fn guess(value: &str) -> Self {
+        if value.contains("://") {
+            ScopeKind::UrlPrefix
+        } else if IpRange::parse(value).is_some() {
+            if value.contains('/') { ScopeKind::Cidr } else { ScopeKind::IpRange }
+        } else {
+            ScopeKind::Domain
+        }
+    }
+}
+
+impl From<ScopeKind> for String {
+    fn from(kind: ScopeKind) -> Self {
+        match kind {
+            ScopeKind::Cidr => "cidr",
+            ScopeKind::IpRange => "ip_range",
+            ScopeKind::Domain => "domain",
+            ScopeKind::UrlPrefix => "url_prefix",
+            ScopeKind::Exclusion => "exclusion",
+        }
+        .to_string()
+    }
+}
+
+/// Splits a free-text scope on whitespace, commas and semicolons.
+pub fn split_text(text: &str) -> Vec<(ScopeKind, &str)> {
+    text.split(|c: char| c.is_whitespace() || c == ',' || c == ';')
+        .filter(|token| !token.is_empty())
+        .map(|token| (ScopeKind::guess(token), token))
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScopeRule {
+    Addresses(IpRange),
+    /// Host name, `*.example.com` matches every subdomain of example.com.
+    Domain { name: String, wildcard: bool },
+    UrlPrefix(String),
+}
+
+impl ScopeRule {
+    /// Parses the value of an entry, `None` if it does not fit the kind.
+    pub fn parse(kind: ScopeKind, value: &str) -> Option<Self> {
+        let value = value.trim();
+        match kind {
+            ScopeKind::Cidr if value.contains('/') => IpRange::parse(value).map(ScopeRule::Addresses),
+            ScopeKind::IpRange if !value.contains('/') => {
+                IpRange::parse(value).map(ScopeRule::Addresses)
+            },
+            ScopeKind::Domain => {
+                let (name, wildcard) = match value.strip_prefix("*.") {
+                    Some(name) => (name, true),
+                    None => (value, false),
+                };
+                let name = name.trim_end_matches('.').to_lowercase();
+                is_host_name(&name).then_some(ScopeRule::Domain { name, wildcard })
+            },
+            ScopeKind::UrlPrefix => {
+                let (scheme, rest) = value.split_once("://")?;
+                let valid = matches!(scheme.to_lowercase().as_str(), "http" | "https")
+                    && !host_of(value).is_empty()
+                    && !rest.contains(char::is_whitespace);
+                valid.then(|| ScopeRule::UrlPrefix(normalize_url(value)))
+            },
+            ScopeKind::Exclusion => ScopeRule::parse(ScopeKind::guess(value), value),
+            _ => None,
+        }
+    }
+
+    fn matches_host(&self, host: &str) -> bool {
+        match self {
+            ScopeRule::Domain { name, wildcard: false } => host == name,
+            ScopeRule::Domain { name, wildcard: true } => host
+                .strip_suffix(name.as_str())
+                .is_some_and(|prefix| prefix.ends_with('.')),
+            _ => false,
+        }
+    }
+
+    fn matches_url(&self, url: &str) -> bool {
+        matches!(self, ScopeRule::UrlPrefix(prefix) if is_under(url, prefix))
+    }
+
+    /// Whether scanning the target may touch what this exclusion covers.
+    /// Scanners given a whole host or a URL above an excluded path crawl
+    /// into it, so those are excluded too.
+    fn excludes(&self, target: &ScopeTarget) -> bool {
+        match (self, target) {
+            (ScopeRule::Addresses(excluded), ScopeTarget::Addresses(range)) => excluded.overlaps(range),
+            (ScopeRule::Domain { .. }, ScopeTarget::Host(host)) => self.matches_host(host),
+            (ScopeRule::UrlPrefix(prefix), ScopeTarget::Url(url)) => {
+                is_under(url, prefix) || is_under(prefix, url)
+            },
+            (ScopeRule::UrlPrefix(prefix), _) => match (ScopeTarget::parse(host_of(prefix)), target) {
+                (ScopeTarget::Host(excluded), ScopeTarget::Host(host)) => &excluded == host,
+                (ScopeTarget::Addresses(excluded), ScopeTarget::Addresses(range)) => {
+                    excluded.overlaps(range)
+                },
+                _ => false,
+            },
+            (_, ScopeTarget::Url(url)) => self.excludes(&ScopeTarget::parse(host_of(url))),
+            _ => false,
+        }
+    }
+}
+
+/// Whether the URL is the prefix or lies below it, so `/app` covers
+/// `/app/login` and `/app?id=1` but not `/application`.
+fn is_under(url: &str, prefix: &str) -> bool {
+    url.strip_prefix(prefix).is_some_and(|rest| {
+        rest.is_empty() || prefix.ends_with('/') || rest.starts_with(['/', '?', '#'])
+    })
+}
+
+fn is_host_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.split('.').all(|label| {
+            !label.is_empty()
+                && !label.starts_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        })
+}
+
+/// Lowercases the scheme and authority of a URL and makes the path explicit.
+fn normalize_url(value: &str) -> String {
+    let (scheme, rest) = value.split_once("://").unwrap_or(("", value));
+    let path_start = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+    let (authority, path) = rest.split_at(path_start);
+    let path = if path.starts_with('/') { path.to_string() } else { format!("/{}", path) };
+    format!("{}://{}{}", scheme.to_lowercase(), authority.to_lowercase(), path)
+}
+
+/// A target as the user typed it, reduced to what scope checks need.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScopeTarget {
+    Addresses(IpRange),
+    Host(String),
+    Url(String),
+}
+
+impl ScopeTarget {
+    /// Accepts IPs, CIDRs, ranges, host names and URLs.
+    pub fn parse(value: &str) -> Self {
+        if value.contains("://") {
+            return ScopeTarget::Url(normalize_url(value));
+        }
+        let host = host_of(value);
+        match IpRange::parse(host) {
+            Some(range) => ScopeTarget::Addresses(range),
+            None => ScopeTarget::Host(host.trim_end_matches('.').to_lowercase()),
+        }
+    }
+}
+
+/// Strips the scheme, credentials, port and path of a URL.
+pub fn host_of(value: &str) -> &str {
+    let authority = match value.split_once("://") {
+        Some((_, rest)) => rest.split(['/', '?', '#']).next().unwrap_or(rest),
+        None => value,
+    };
+    let host = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+    if let Some(bracketed) = host.strip_prefix('[') {
+        return bracketed.split(']').next().unwrap_or(bracketed);
+    }
+    // Only strip a port when the rest is not a bare IPv6 address.
+    match host.rsplit_once(':') {
+        Some((name, port)) if !name.contains(':') && port.chars().all(|c| c.is_ascii_digit()) => {
+            name
+        },
+        _ => host,
+    }
+}
+
+/// In-scope rules of a project minus its exclusions.
+#[derive(Debug, Clone, Default)]
+pub struct Scope {
+    included: Vec<ScopeRule>,
+    excluded: Vec<ScopeRule>,
+}
+
+impl Scope {
+    /// Builds the scope from `(kind, value)` entries, skipping invalid ones.
+    pub fn new<'a>(entries: impl IntoIterator<Item = (ScopeKind, &'a str)>) -> Self {
+        let mut scope = Scope::default();
+        for (kind, value) in entries {
+            let Some(rule) = ScopeRule::parse(kind, value) else {
+                debug!("Skipping invalid {:?} scope entry {}", kind, value);
+                continue;
+            };
+            match kind {
+                ScopeKind::Exclusion => scope.excluded.push(rule),
+                _ => scope.included.push(rule),
+            }
+        }
+        scope
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.included.is_empty()
+    }
+
+    /// Whether every address of the range is covered by the scope, possibly
+    /// by several adjacent entries.
+    fn covers(&self, target: &IpRange) -> bool {
+        let mut ranges: Vec<&IpRange> = self
+            .included
+            .iter()
+            .filter_map(|rule| match rule {
+                ScopeRule::Addresses(range) if range.v6 == target.v6 => Some(range),
+                _ => None,
+            })
+            .collect();
+        ranges.sort_by_key(|range| range.start);
+
+        let mut next = target.start;
+        for range in ranges {
+            if range.start > next {
+                break;
+            }
+            if range.end >= target.end {
+                return true;
+            }
+            if range.end >= next {
+                next = range.end + 1;
+            }
+        }
+        false
+    }
+
+    /// URL prefixes only admit URLs, scanning their whole host is out of scope.
+    fn includes(&self, target: &ScopeTarget) -> bool {
+        match target {
+            ScopeTarget::Addresses(range) => self.covers(range),
+            ScopeTarget::Host(host) => self.included.iter().any(|rule| rule.matches_host(host)),
+            ScopeTarget::Url(url) => {
+                self.included.iter().any(|rule| rule.matches_url(url))
+                    || self.includes(&ScopeTarget::parse(host_of(url)))
+            },
+        }
+    }
+
+    fn excludes(&self, target: &ScopeTarget) -> bool {
+        self.excluded.iter().any(|rule| rule.excludes(target))
+    }
+
+    pub fn contains(&self, target: &ScopeTarget) -> bool {
+        self.includes(target) && !self.excludes(target)
+    }
+
+    /// Returns the targets that are not covered by the scope.
+    pub fn violations<'a>(&self, targets: &'a [String]) -> Vec<&'a str> {
+        targets
+            .iter()
+            .filter(|target| !self.contains(&ScopeTarget::parse(target)))
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+/// Rejects targets outside the project scope and records the attempt. Admins
+/// may launch them anyway by giving a justification, which is recorded too.
+pub fn enforce_scope(
+    conn: &mut PgConnection,
+    project_id: Uuid,
+    scanner_type: &str,
+    targets: &[String],
+    user: Option<&UserSession>,
+    justification: Option<&str>,
+) -> Result<(), AppError> {
+    Project::find_by_id(conn, project_id)?.ok_or(AppError::NotFound)?;
+    let scope = ScopeEntry::get_scope(conn, project_id)?;
+    let out_of_scope = scope.violations(targets);
+    if out_of_scope.is_empty() {
+        return Ok(());
+    }
+
+    let justification = justification.map(str::trim).filter(|text| !text.is_empty());
+    let is_admin = user.is_some_and(|user| matches!(user.role, Role::Admin));
+    let overridden = is_admin && justification.is_some();
+    ScopeAuditRecord::create_records(
+        conn,
+        out_of_scope
+            .iter()
+            .map(|target| NewScopeAuditRecord {
+                project_id,
+                user_id: user.map(|user| user.user_id),
+                scanner_type: scanner_type.to_string(),
+                target: target.to_string(),
+                overridden,
+                justification: justification.map(str::to_string),
+            })
+            .collect(),
+    )?;
+    if overridden {
+        return Ok(());
+    }
+
+    let mut details = if scope.is_empty() {
+        "Project has no scope defined".to_string()
+    } else {
+        format!("Targets outside the project scope: {}", out_of_scope.join(", "))
+    };
+    if justification.is_some() {
+        details.push_str("; only admins can override the scope");
+    }
+    Err(AppError::OutOfScope(details))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scope(text: &str) -> Scope {
+        Scope::new(split_text(text))
+    }
+
+    fn in_scope(scope: &Scope, target: &str) -> bool {
+        scope.contains(&ScopeTarget::parse(target))
+    }
+
+    #[test]
+    fn test_address_targets() {
+        let scope = scope("192.168.1.0/24\n10.0.0.1-10.0.0.20, 10.0.0.21-40; 2001:db8::/64");
+
+        assert!(in_scope(&scope, "192.168.1.77"));
+        assert!(in_scope(&scope, "192.168.1.128/25"));
+        assert!(in_scope(&scope, "10.0.0.5-35"));
+        assert!(in_scope(&scope, "2001:db8::1"));
+        assert!(!in_scope(&scope, "192.168.0.0/16"));
+        assert!(!in_scope(&scope, "10.0.0.30-41"));
+        assert!(!in_scope(&scope, "2001:db9::1"));
+    }
+
+    #[test]
+    fn test_host_targets() {
+        let scope = scope("example.com *.corp.example https://portal.example.org/login");
+
+        assert!(in_scope(&scope, "example.com"));
+        assert!(in_scope(&scope, "https://EXAMPLE.com:8443/admin"));
+        assert!(in_scope(&scope, "vpn.corp.example"));
+        assert!(in_scope(&scope, "https://portal.example.org/login?next=/"));
+        assert!(!in_scope(&scope, "portal.example.org"));
+        assert!(!in_scope(&scope, "https://portal.example.org/admin"));
+        assert!(!in_scope(&scope, "www.example.com"));
+        assert!(!in_scope(&scope, "corp.example"));
+        assert!(!in_scope(&scope, "evilcorp.example"));
+    }
+
+    #[test]
+    fn test_url_prefix_boundaries() {
+        let scope = scope("https://a.com/app https://b.com/static/");
+
+        assert!(in_scope(&scope, "https://a.com/app"));
+        assert!(in_scope(&scope, "https://a.com/app/login"));
+        assert!(in_scope(&scope, "https://a.com/app?id=1"));
+        assert!(in_scope(&scope, "https://a.com/app#top"));
+        assert!(in_scope(&scope, "https://b.com/static/app.js"));
+        assert!(!in_scope(&scope, "https://a.com/app-admin/"));
+        assert!(!in_scope(&scope, "https://a.com/application"));
+        assert!(!in_scope(&scope, "https://b.com/static-old/"));
+
+        let scope = Scope::new([
+            (ScopeKind::Domain, "a.com"),
+            (ScopeKind::Exclusion, "https://a.com/admin"),
+        ]);
+        assert!(!in_scope(&scope, "https://a.com/admin/users"));
+        assert!(!in_scope(&scope, "https://a.com/"));
+        assert!(in_scope(&scope, "https://a.com/administration"));
+        assert!(in_scope(&scope, "https://a.com/adm"));
+    }
+
+    #[test]
+    fn test_exclusions() {
+        let scope = Scope::new([
+            (ScopeKind::Cidr, "10.0.0.0/24"),
+            (ScopeKind::Domain, "*.example.com"),
+            (ScopeKind::Exclusion, "10.0.0.1"),
+            (ScopeKind::Exclusion, "vpn.example.com"),
+            (ScopeKind::Exclusion, "https://www.example.com/logout"),
+        ]);
+
+        assert!(in_scope(&scope, "10.0.0.2-254"));
+        assert!(!in_scope(&scope, "10.0.0.0/24"));
+        assert!(!in_scope(&scope, "vpn.example.com"));
+        assert!(!in_scope(&scope, "https://vpn.example.com/"));
+        assert!(in_scope(&scope, "https://www.example.com/login"));
+        assert!(!in_scope(&scope, "https://www.example.com/logout?all=1"));
+        // Scanning the whole host would crawl into the excluded path.
+        assert!(!in_scope(&scope, "www.example.com"));
+        assert!(!in_scope(&scope, "www.example.com:443"));
+        assert!(!in_scope(&scope, "https://www.example.com"));
+        assert!(in_scope(&scope, "api.example.com"));
+
+        let scope = Scope::new([
+            (ScopeKind::Cidr, "10.0.0.0/24"),
+            (ScopeKind::Exclusion, "http://10.0.0.5/admin"),
+        ]);
+        assert!(!in_scope(&scope, "10.0.0.5"));
+        assert!(!in_scope(&scope, "10.0.0.0/24"));
+        assert!(in_scope(&scope, "10.0.0.6"));
+        assert!(in_scope(&scope, "http://10.0.0.5/shop"));
+    }
+
+    #[test]
+    fn test_parse_rules() {
+        assert!(ScopeRule::parse(ScopeKind::Cidr, "10.0.0.0/8").is_some());
+        assert!(ScopeRule::parse(ScopeKind::Cidr, "10.0.0.1").is_none());
+        assert!(ScopeRule::parse(ScopeKind::IpRange, "10.0.0.1-20").is_some());
+        assert!(ScopeRule::parse(ScopeKind::Domain, "*.Example.com").is_some());
+        assert!(ScopeRule::parse(ScopeKind::Domain, "exa mple.com").is_none());
+        assert!(ScopeRule::parse(ScopeKind::UrlPrefix, "ftp://example.com").is_none());
+        assert_eq!(split_text("https://a.example/x 10.0.0.0/8")[0].0, ScopeKind::UrlPrefix);
+        assert_eq!(split_text("https://a.example/x 10.0.0.0/8")[1].0, ScopeKind::Cidr);
+    }
+
+    #[test]
+    fn test_violations() {
+        let scope = scope("192.168.1.0/24");
+        let targets = vec!["192.168.1.10".to_string(), "192.168.2.10".to_string()];
+
+        assert_eq!(scope.violations(&targets), vec!["192.168.2.10"]);
+        assert_eq!(Scope::default().violations(&targets).len(), 2);
+    }
+}
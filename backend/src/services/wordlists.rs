@@ -0,0 +1,64 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::services::scanner::types::is_safe_argument;
+
+/// A wordlist stored on the server, referred to by its file name in scan
+/// options.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct Wordlist {
+    pub name: String,
+    pub size: u64,
+}
+
+/// Wordlists live directly in the wordlists directory, so names can't have
+/// path separators. Only `.txt` files are accepted, so an upload can't pass
+/// for a script or template another scanner would run.
+pub fn is_valid_name(name: &str) -> bool {
+    is_safe_argument(name)
+        && !name.contains('/')
+        && name.strip_suffix(".txt").is_some_and(|stem| !stem.is_empty())
+}
+
+pub fn list_wordlists(dir: &Path) -> io::Result<Vec<Wordlist>> {
+    let mut wordlists = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if metadata.is_file() && is_valid_name(&name) {
+            wordlists.push(Wordlist { name, size: metadata.len() });
+        }
+    }
+    wordlists.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(wordlists)
+}
+
+/// Stores a wordlist, replacing the one with the same name.
+pub fn save_wordlist(dir: &Path, name: &str, data: &[u8]) -> io::Result<Wordlist> {
+    fs::write(dir.join(name), data)?;
+    Ok(Wordlist {
+        name: name.to_string(),
+        size: data.len() as u64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_valid_name() {
+        assert!(is_valid_name("common.txt"));
+        assert!(is_valid_name("raft-medium-directories.txt"));
+        assert!(!is_valid_name("evil.nse"));
+        assert!(!is_valid_name("template.yaml"));
+        assert!(!is_valid_name(".txt"));
+        assert!(!is_valid_name("../common.txt"));
+        assert!(!is_valid_name("/app/wordlists/common.txt"));
+        assert!(!is_valid_name("lists/common.txt"));
+    }
+}
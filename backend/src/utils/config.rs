@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::{env, fs};
 
@@ -26,6 +27,12 @@ pub struct AppConfig {
     pub templates_path: String,
     pub scans_path: String,
     pub reports_path: String,
+    pub nuclei_templates_path: String,
+    /// Wordlists content discovery scans pick from by name.
+    pub wordlists_path: String,
+    /// Number of scans allowed to run at once per scanner type.
+    pub scan_concurrency: HashMap<String, usize>,
+    pub default_scan_concurrency: usize,
 }
 
 impl AppConfig {
@@ -33,11 +40,13 @@ impl AppConfig {
         let templates_path = env::var("TEMPLATES_PATH").unwrap_or("/app/templates/".into());
         let scans_path = env::var("SCANS_PATH").unwrap_or("/app/scans/".into());
         let reports_path = env::var("REPORTS_PATH").unwrap_or("/app/reports/".into());
+        let wordlists_path = env::var("WORDLISTS_PATH").unwrap_or("/app/wordlists/".into());
 
         Self::create_dirs_if_doesnt_exist(
             templates_path.clone(),
             scans_path.clone(),
             reports_path.clone(),
+            wordlists_path.clone(),
         )
         .unwrap();
 
@@ -63,17 +72,47 @@ impl AppConfig {
             templates_path,
             scans_path,
             reports_path,
+            nuclei_templates_path: env::var("NUCLEI_TEMPLATES_PATH")
+                .unwrap_or("/home/ubuntu/nuclei-templates".into()),
+            wordlists_path,
+            scan_concurrency: env::var("SCAN_CONCURRENCY")
+                .map(|v| Self::parse_scan_concurrency(&v))
+                .unwrap_or_default(),
+            default_scan_concurrency: env::var("SCAN_DEFAULT_CONCURRENCY")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(2),
         }
     }
 
+    /// Parses a list like `nmap=2,nuclei=1`, skipping malformed entries.
+    fn parse_scan_concurrency(value: &str) -> HashMap<String, usize> {
+        value
+            .split(',')
+            .filter_map(|entry| {
+                let (scanner, limit) = entry.split_once('=')?;
+                Some((scanner.trim().to_string(), limit.trim().parse().ok()?))
+            })
+            .collect()
+    }
+
+    pub fn scan_concurrency(&self, scanner: &str) -> usize {
+        self.scan_concurrency
+            .get(scanner)
+            .copied()
+            .unwrap_or(self.default_scan_concurrency)
+    }
+
     fn create_dirs_if_doesnt_exist(
         templates: String,
         scans: String,
         reports: String,
+        wordlists: String,
     ) -> std::io::Result<()> {
         fs::create_dir_all(&templates)?;
         fs::create_dir_all(&scans)?;
-        fs::create_dir_all(&reports)
+        fs::create_dir_all(&reports)?;
+        fs::create_dir_all(&wordlists)
     }
 }
 
@@ -22,6 +22,8 @@ pub enum AppError {
     UnauthorizedError,
     #[display("Multipart Error")]
     MultipartError,
+    #[display("Out Of Scope: {_0}")]
+    OutOfScope(#[error(not(source))] String),
 }
 
 #[derive(Serialize)]
@@ -30,6 +32,13 @@ pub struct AppErrorJson {
     pub error: &'static str,
 }
 
+#[derive(Serialize)]
+pub struct AppErrorDetailsJson<'a> {
+    pub status: usize,
+    pub error: &'static str,
+    pub details: &'a str,
+}
+
 impl From<R2D2Error> for AppError {
     fn from(error: R2D2Error) -> Self {
         error!("Database pool error: {}", error);
@@ -74,11 +83,12 @@ impl error::ResponseError for AppError {
             AppError::UnauthorizedError => StatusCode::UNAUTHORIZED,
             AppError::NotFound => StatusCode::NOT_FOUND,
             AppError::MultipartError => StatusCode::BAD_REQUEST,
+            AppError::OutOfScope(_) => StatusCode::FORBIDDEN,
         }
     }
 
     fn error_response(&self) -> HttpResponse {
-        match *self {
+        match self {
             AppError::InternalServerError => HttpResponse::Ok().json(AppErrorJson {
                 status: 505,
                 error: "Internal Server Error",
@@ -103,6 +113,11 @@ impl error::ResponseError for AppError {
                 status: 400,
                 error: "Multipart Error",
             }),
+            AppError::OutOfScope(details) => HttpResponse::Forbidden().json(AppErrorDetailsJson {
+                status: 403,
+                error: "Out Of Scope",
+                details,
+            }),
         }
     }
 }